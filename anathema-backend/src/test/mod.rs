@@ -4,14 +4,17 @@ use anathema_geometry::{Pos, Size};
 use anathema_store::tree::{Node, TreeValues};
 use anathema_widgets::components::events::Event;
 use anathema_widgets::layout::text::StringSession;
-use anathema_widgets::paint::CellAttributes;
+use anathema_widgets::paint::{CellAttributes, PaintCtx, PaintOrder};
 use anathema_widgets::{AttributeStorage, Element, WidgetKind, WidgetRenderer};
+use unicode_width::UnicodeWidthChar;
 
 use crate::Backend;
 
 pub struct TestBackend {
     pub surface: TestSurface,
     pub output: String,
+    /// Draw the paint order of every element into its top-left cell.
+    pub paint_order: Option<PaintOrder>,
 }
 
 impl TestBackend {
@@ -20,6 +23,7 @@ impl TestBackend {
         Self {
             surface: TestSurface::new(size),
             output: String::new(),
+            paint_order: None,
         }
     }
 }
@@ -50,15 +54,12 @@ impl Backend for TestBackend {
         attribute_storage: &AttributeStorage<'bp>,
         ignore_floats: bool,
     ) {
-        anathema_widgets::paint::paint(
-            &mut self.surface,
-            element,
-            children,
-            values,
-            attribute_storage,
-            text,
-            ignore_floats,
-        );
+        let mut ctx = PaintCtx::new(&mut self.surface, None);
+        if let Some(paint_order) = self.paint_order.clone() {
+            ctx.show_paint_order(paint_order);
+        }
+
+        anathema_widgets::paint::paint_with_ctx(ctx, element, children, values, attribute_storage, text, ignore_floats);
     }
 
     fn clear(&mut self) {
@@ -67,9 +68,16 @@ impl Backend for TestBackend {
 
     fn render(&mut self) {
         self.output = format!("{}", self.surface);
+        if let Some(paint_order) = &self.paint_order {
+            paint_order.reset();
+        }
     }
 }
 
+// The second cell of a wide glyph.
+// This is never written to the output, as the wide glyph covers both cells.
+const CONTINUATION: char = '\0';
+
 pub struct TestSurface {
     size: Size,
     buffer: Vec<char>,
@@ -95,7 +103,21 @@ impl WidgetRenderer for TestSurface {
         let y_offset = local_pos.y as usize * self.size.width;
         let x_offset = local_pos.x as usize;
         let index = y_offset + x_offset;
+
+        // Overwriting either half of a wide glyph removes the other half
+        if self.buffer[index] == CONTINUATION && x_offset > 0 {
+            self.buffer[index - 1] = ' ';
+        }
+        if x_offset + 1 < self.size.width && self.buffer[index + 1] == CONTINUATION {
+            self.buffer[index + 1] = ' ';
+        }
+
         self.buffer[index] = c;
+
+        // A wide glyph covers the next cell as well
+        if c.width() == Some(2) && x_offset + 1 < self.size.width {
+            self.buffer[index + 1] = CONTINUATION;
+        }
     }
 
     fn size(&self) -> Size {
@@ -112,7 +134,9 @@ impl Display for TestSurface {
         for y in 0..self.size.height {
             for x in 0..self.size.width {
                 let idx = y * self.size.width + x;
-                write!(f, "{}", self.buffer[idx])?;
+                if self.buffer[idx] != CONTINUATION {
+                    write!(f, "{}", self.buffer[idx])?;
+                }
             }
             writeln!(f)?;
         }
@@ -120,3 +144,20 @@ impl Display for TestSurface {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overwrite_wide_glyph() {
+        let mut surface = TestSurface::new((4, 1));
+        surface.draw_glyph('中', Pos::new(0, 0));
+        surface.draw_glyph('a', Pos::new(2, 0));
+        assert_eq!(surface.to_string(), "中a \n");
+
+        // Writing to the second half of the wide glyph removes it
+        surface.draw_glyph('b', Pos::new(1, 0));
+        assert_eq!(surface.to_string(), " ba \n");
+    }
+}
@@ -240,6 +240,11 @@ impl Buffer {
         self.inner[index]
     }
 
+    /// Get the character at a given position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell is not occupied.
     pub fn char_at(&self, x: usize, y: usize) -> char {
         let cell = self.cell_at(x, y);
         match cell.state {
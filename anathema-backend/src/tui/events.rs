@@ -17,7 +17,7 @@ impl Events {
     pub fn poll(&self, timeout: Duration) -> Option<Event> {
         match crossterm::event::poll(timeout).ok()? {
             true => {
-                let event = read().map(Into::into).ok()?;
+                let event = read().ok()?;
 
                 let event = match event {
                     CTEvent::Paste(_) => Event::Noop,
@@ -11,6 +11,7 @@ use anathema_geometry::{LocalPos, Pos, Size};
 use anathema_store::tree::{Node, TreeValues};
 use anathema_widgets::components::events::Event;
 use anathema_widgets::layout::text::StringSession;
+use anathema_widgets::paint::{PaintCtx, PaintOrder};
 use anathema_widgets::{AttributeStorage, Element, WidgetKind, WidgetRenderer};
 use crossterm::terminal::size;
 pub use screen::Screen;
@@ -35,6 +36,7 @@ pub struct TuiBackendBuilder {
     enable_raw_mode: bool,
     enable_alt_screen: bool,
     enable_mouse: bool,
+    show_paint_order: bool,
 }
 
 impl TuiBackendBuilder {
@@ -56,6 +58,13 @@ impl TuiBackendBuilder {
         self
     }
 
+    /// Draw the paint order of every element into its top-left cell.
+    /// This is useful when debugging z-ordering and floating widgets.
+    pub fn show_paint_order(mut self) -> Self {
+        self.show_paint_order = true;
+        self
+    }
+
     /// Hide the cursor (not the mouse cursor)
     pub fn hide_cursor(mut self) -> Self {
         self.hide_cursor = true;
@@ -77,6 +86,7 @@ impl TuiBackendBuilder {
             enable_raw_mode: self.enable_raw_mode,
             enable_alt_screen: self.enable_alt_screen,
             enable_mouse: self.enable_mouse,
+            paint_order: self.show_paint_order.then(PaintOrder::default),
         };
 
         Ok(backend)
@@ -96,6 +106,7 @@ pub struct TuiBackend {
     enable_raw_mode: bool,
     enable_alt_screen: bool,
     enable_mouse: bool,
+    paint_order: Option<PaintOrder>,
 }
 
 impl TuiBackend {
@@ -111,6 +122,7 @@ impl TuiBackend {
             enable_raw_mode: false,
             enable_alt_screen: false,
             enable_mouse: false,
+            show_paint_order: false,
         }
     }
 
@@ -150,20 +162,20 @@ impl Backend for TuiBackend {
         attribute_storage: &AttributeStorage<'bp>,
         ignore_floats: bool,
     ) {
-        anathema_widgets::paint::paint(
-            &mut self.screen,
-            element,
-            children,
-            values,
-            attribute_storage,
-            text,
-            ignore_floats,
-        );
+        let mut ctx = PaintCtx::new(&mut self.screen, None);
+        if let Some(paint_order) = self.paint_order.clone() {
+            ctx.show_paint_order(paint_order);
+        }
+
+        anathema_widgets::paint::paint_with_ctx(ctx, element, children, values, attribute_storage, text, ignore_floats);
         // TODO: decide if we need `paint` to return a Result or not
     }
 
     fn render(&mut self) {
         let _ = self.screen.render(&mut self.output);
+        if let Some(paint_order) = &self.paint_order {
+            paint_order.reset();
+        }
     }
 
     fn clear(&mut self) {
@@ -1,9 +1,11 @@
 use std::io::{Result, Write};
 
 use anathema_geometry::{Pos, Size};
-use anathema_widgets::paint::CellAttributes;
+use anathema_state::Hex;
+use anathema_widgets::paint::{CellAttributes, SurfaceCell};
 use anathema_widgets::WidgetRenderer;
 use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::style::Color;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::{cursor, ExecutableCommand, QueueableCommand};
 
@@ -146,6 +148,22 @@ impl WidgetRenderer for Screen {
     fn size(&self) -> Size {
         self.new_buffer.size()
     }
+
+    fn read_cell(&self, pos: Pos) -> Option<SurfaceCell> {
+        let screen_pos = pos.try_into().ok()?;
+        let (_, style) = self.new_buffer.get(screen_pos)?;
+
+        // Only rgb colours can be blended
+        let hex = |color| match color {
+            Some(Color::Rgb { r, g, b }) => Some(Hex { r, g, b }),
+            _ => None,
+        };
+
+        Some(SurfaceCell {
+            foreground: hex(style.fg),
+            background: hex(style.bg),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -1,6 +1,6 @@
 use std::ops::ControlFlow;
 
-use anathema_geometry::{Pos, Size};
+use anathema_geometry::Size;
 use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
 use anathema_widgets::{AttributeStorage, LayoutChildren, PositionChildren, Widget, WidgetId};
 
@@ -33,26 +33,10 @@ impl Widget for Align {
         ctx: PositionCtx,
     ) {
         let attributes = attribute_storage.get(id);
-        let alignment = attributes.get(ALIGNMENT).unwrap_or_default();
+        let alignment: Alignment = attributes.get(ALIGNMENT).unwrap_or_default();
 
         children.for_each(|child, children| {
-            let width = ctx.inner_size.width as i32;
-            let height = ctx.inner_size.height as i32;
-            let child_width = child.size().width as i32;
-            let child_height = child.size().height as i32;
-
-            let child_offset = match alignment {
-                Alignment::TopLeft => Pos::ZERO,
-                Alignment::Top => Pos::new(width / 2 - child_width / 2, 0),
-                Alignment::TopRight => Pos::new(width - child_width, 0),
-                Alignment::Right => Pos::new(width - child_width, height / 2 - child_height / 2),
-                Alignment::BottomRight => Pos::new(width - child_width, height - child_height),
-                Alignment::Bottom => Pos::new(width / 2 - child_width / 2, height - child_height),
-                Alignment::BottomLeft => Pos::new(0, height - child_height),
-                Alignment::Left => Pos::new(0, height / 2 - child_height / 2),
-                Alignment::Centre => Pos::new(width / 2 - child_width / 2, height / 2 - child_height / 2),
-            };
-
+            let child_offset = alignment.offset(ctx.inner_size, child.size());
             child.position(children, ctx.pos + child_offset, attribute_storage);
             ControlFlow::Break(())
         });
@@ -5,7 +5,7 @@ use std::ops::{ControlFlow, Deref};
 use anathema_geometry::{LocalPos, Size};
 use anathema_widgets::expressions::EvalValue;
 use anathema_widgets::layout::text::StringSession;
-use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
+use anathema_widgets::layout::{BoxGlyphs, Constraints, GlyphTheme, LayoutCtx, PositionCtx};
 use anathema_widgets::paint::{PaintCtx, SizePos};
 use anathema_widgets::{
     AnyWidget, AttributeStorage, Attributes, LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId,
@@ -17,6 +17,21 @@ use crate::layout::Axis;
 use crate::{HEIGHT, MAX_HEIGHT, MAX_WIDTH, MIN_HEIGHT, MIN_WIDTH, WIDTH};
 
 pub const BORDER_STYLE: &str = "border_style";
+const SIDES: &str = "sides";
+
+// Attributes to draw or omit a single edge, and the style of that edge.
+// These take precedence over `sides` and `border_style`.
+const EDGES: [(&str, &str, Sides, usize); 4] = [
+    ("border_top", "border_top_style", Sides::TOP, BORDER_EDGE_TOP),
+    ("border_right", "border_right_style", Sides::RIGHT, BORDER_EDGE_RIGHT),
+    (
+        "border_bottom",
+        "border_bottom_style",
+        Sides::BOTTOM,
+        BORDER_EDGE_BOTTOM,
+    ),
+    ("border_left", "border_left_style", Sides::LEFT, BORDER_EDGE_LEFT),
+];
 
 // -----------------------------------------------------------------------------
 //     - Indices -
@@ -131,6 +146,31 @@ impl BorderStyle {
     }
 }
 
+impl BorderStyle {
+    /// The edges of the border using the glyph theme.
+    /// A custom style is used as is.
+    pub fn themed_edges(&self, theme: &GlyphTheme) -> [char; 8] {
+        match self {
+            BorderStyle::Thin => box_edges(theme.thin),
+            BorderStyle::Thick => box_edges(theme.thick),
+            BorderStyle::Custom(_) => self.edges(),
+        }
+    }
+}
+
+fn box_edges(glyphs: BoxGlyphs) -> [char; 8] {
+    [
+        glyphs.top_left,
+        glyphs.top,
+        glyphs.top_right,
+        glyphs.right,
+        glyphs.bottom_right,
+        glyphs.bottom,
+        glyphs.bottom_left,
+        glyphs.left,
+    ]
+}
+
 impl Display for BorderStyle {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -141,6 +181,7 @@ impl Display for BorderStyle {
     }
 }
 
+#[allow(clippy::infallible_try_from)]
 impl TryFrom<&EvalValue<'_>> for BorderStyle {
     type Error = Infallible;
 
@@ -326,14 +367,16 @@ impl BorderSize {
         let left_width = self.left.max(self.top_left).max(self.bottom_left);
         let right_width = self.right.max(self.top_right).max(self.bottom_right);
 
-        let top = if self.top > 0 { 1 } else { 2 };
-        let bottom = if self.bottom > 0 { 1 } else { 2 };
-
         Size {
             width: (left_width + right_width) as usize,
-            height: (top + bottom) as usize,
+            height: self.height(),
         }
     }
+
+    // A horizontal edge is always one row
+    pub(crate) fn height(&self) -> usize {
+        usize::from(self.top > 0) + usize::from(self.bottom > 0)
+    }
 }
 
 /// Draw a border around an element.
@@ -360,6 +403,33 @@ pub struct Border {
 }
 
 impl Border {
+    fn sides(attributes: &Attributes<'_>) -> Sides {
+        let mut sides = attributes
+            .get_val(SIDES)
+            .and_then(|s| Sides::try_from(s.deref()).ok())
+            .unwrap_or_default();
+
+        for (key, _, side, _) in EDGES {
+            if attributes.get_val(key).is_some() {
+                sides.set(side, attributes.get_bool(key));
+            }
+        }
+
+        sides
+    }
+
+    fn edges(border_style: &BorderStyle, attributes: &Attributes<'_>, theme: &GlyphTheme) -> [char; 8] {
+        let mut edges = border_style.themed_edges(theme);
+
+        for (_, style_key, _, index) in EDGES {
+            if let Some(style) = attributes.get_ref::<BorderStyle>(style_key) {
+                edges[index] = style.themed_edges(theme)[index];
+            }
+        }
+
+        edges
+    }
+
     // The additional size of the border
     // to subtract from the constraint.
     fn border_size(&self, sides: Sides) -> BorderSize {
@@ -413,13 +483,9 @@ impl Widget for Border {
         ctx: &mut LayoutCtx<'_, '_, 'bp>,
     ) -> Size {
         let attributes = ctx.attribs.get(id);
-        self.sides = attributes
-            .get_val("sides")
-            .and_then(|s| Sides::try_from(s.deref()).ok())
-            .unwrap_or_default();
-
+        self.sides = Self::sides(attributes);
         self.border_style = attributes.get_ref(BORDER_STYLE).unwrap_or_default();
-        self.edges = self.border_style.edges();
+        self.edges = Self::edges(&self.border_style, attributes, &ctx.glyph_theme);
 
         let mut layout = BorderLayout {
             min_width: attributes.get(MIN_WIDTH),
@@ -500,14 +566,9 @@ impl Widget for Border {
 pub(crate) fn make(attributes: &Attributes<'_>) -> Box<dyn AnyWidget> {
     let border_style: BorderStyle = attributes.get_ref(BORDER_STYLE).unwrap_or_default();
 
-    let sides = attributes
-        .get_val("sides")
-        .and_then(|s| Sides::try_from(s.deref()).ok())
-        .unwrap_or_default();
-
     let text = Border {
-        sides,
-        edges: border_style.edges(),
+        sides: Border::sides(attributes),
+        edges: Border::edges(&border_style, attributes, &GlyphTheme::default()),
         border_style,
     };
     Box::new(text)
@@ -516,6 +577,8 @@ pub(crate) fn make(attributes: &Attributes<'_>) -> Box<dyn AnyWidget> {
 
 #[cfg(test)]
 mod test {
+    use anathema_widgets::layout::GlyphTheme;
+
     use crate::testing::TestRunner;
 
     #[test]
@@ -680,6 +743,84 @@ mod test {
         TestRunner::new(tpl, (8, 6)).instance().render_assert(expected);
     }
 
+    #[test]
+    fn omit_left_and_right_edges() {
+        let tpl = "
+            border [border_left: false, border_right: false]
+                text 'hi'
+        ";
+
+        let expected = "
+            ╔══════╗
+            ║──    ║
+            ║hi    ║
+            ║──    ║
+            ║      ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 4)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn only_top_and_bottom_edges() {
+        let tpl = "
+            border [sides: '', border_top: true, border_bottom: true, border_bottom_style: 'thick']
+                text 'hello'
+        ";
+
+        let expected = "
+            ╔══════╗
+            ║───── ║
+            ║hello ║
+            ║═════ ║
+            ║      ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 4)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn only_bottom_edge() {
+        let tpl = "
+            vstack
+                border [sides: 'bottom']
+                    text 'a'
+                text 'b'
+        ";
+
+        let expected = "
+            ╔══════╗
+            ║a     ║
+            ║─     ║
+            ║b     ║
+            ║      ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 4)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn ascii_glyph_theme() {
+        let tpl = "border [width: 4, height: 3]";
+
+        let expected = "
+            +======+
+            |+--+  |
+            ||  |  |
+            |+--+  |
+            |      |
+            +======+
+        ";
+
+        TestRunner::new(tpl, (6, 4))
+            .instance()
+            .with_glyph_theme(GlyphTheme::ASCII)
+            .render_assert(expected);
+    }
+
     #[test]
     fn unsized_empty_border() {
         let tpl = "
@@ -1,14 +1,39 @@
+use std::sync::Arc;
+
 use anathema::CommonVal;
-use anathema_geometry::{LocalPos, Pos, Size};
+use anathema_geometry::{LocalPos, Pos, Region, Size};
 use anathema_store::slab::Slab;
 use anathema_store::smallmap::SmallMap;
-use anathema_widgets::layout::text::StringSession;
-use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
-use anathema_widgets::paint::{CellAttributes, PaintCtx, SizePos};
-use anathema_widgets::{AttributeStorage, LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId};
+use anathema_store::tree::{Node, TreeValues};
+use anathema_widgets::layout::text::{StringSession, StringStorage};
+use anathema_widgets::layout::{layout_widget, position_widget, Constraints, LayoutCtx, PositionCtx, Viewport};
+use anathema_widgets::paint::{blend, CellAttributes, PaintCtx, SizePos, SurfaceCell};
+use anathema_widgets::{
+    AttributeStorage, Element, LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId, WidgetKind,
+    WidgetRenderer,
+};
+use unicode_width::UnicodeWidthChar;
 
 use crate::{HEIGHT, WIDTH};
 
+const FULL_BLOCK: char = '█';
+const ANCHOR: &str = "anchor";
+const BLEND: &str = "blend";
+const ALPHA: &str = "alpha";
+// Style attributes copied from widgets painted into a canvas
+const STYLE_COLORS: [&str; 2] = ["foreground", "background"];
+const STYLE_FLAGS: [&str; 7] = [
+    "bold",
+    "dim",
+    "italic",
+    "underline",
+    "crossed-out",
+    "overline",
+    "inverse",
+];
+// Partial blocks in eighths, from one eighth to a full block
+const PARTIAL_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
 #[derive(Debug, Clone)]
 pub enum CanvasAttrib {
     Str(String),
@@ -50,6 +75,43 @@ impl CanvasAttribs {
     pub fn get_mut(&mut self, key: &str) -> Option<&mut CanvasAttrib> {
         self.0.get_mut(key)
     }
+
+    // The `alpha` of the cell, between zero and one
+    fn alpha(&self) -> Option<f32> {
+        let Some(CanvasAttrib::Common(CommonVal::Float(alpha))) = self.get(ALPHA) else { return None };
+        Some(alpha.clamp(0.0, 1.0) as f32)
+    }
+
+    // Blend the colours over the colours of a cell on the surface
+    fn blend_over(&self, alpha: f32, below: SurfaceCell) -> Self {
+        let mut blended = self.clone();
+        for (key, below) in STYLE_COLORS.into_iter().zip([below.foreground, below.background]) {
+            if let (Some(over), Some(under)) = (self.get_hex(key), below) {
+                blended.set(key, blend(over, under, alpha));
+            }
+        }
+        blended
+    }
+
+    /// Copy the style (colours and text attributes) out of cell attributes.
+    fn from_cell_attribs(attribs: &dyn CellAttributes) -> Self {
+        let mut canvas_attribs = Self::new();
+
+        for key in STYLE_COLORS {
+            match attribs.get_hex(key) {
+                Some(hex) => _ = canvas_attribs.set(key, hex),
+                None => attribs.with_str(key, &mut |s| _ = canvas_attribs.set_str(key, s)),
+            }
+        }
+
+        for key in STYLE_FLAGS {
+            if attribs.get_bool(key) {
+                canvas_attribs.set(key, true);
+            }
+        }
+
+        canvas_attribs
+    }
 }
 
 impl CellAttributes for CanvasAttribs {
@@ -77,11 +139,14 @@ impl CellAttributes for CanvasAttribs {
     }
 }
 
-#[derive(Debug, Default)]
+// A cell holds a string rather than a char, so a cluster of characters
+// (e.g an emoji sequence, or a character and its combining accents)
+// occupies a single cell.
+#[derive(Debug, Default, Clone)]
 enum Cell {
     #[default]
     Empty,
-    Occupied(LocalPos, char, CanvasAttribs),
+    Occupied(LocalPos, String, CanvasAttribs),
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -91,7 +156,72 @@ enum Entry {
     Occupied(usize),
 }
 
-#[derive(Debug)]
+/// The corner of the canvas that the cells stay anchored to
+/// when the canvas is resized
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum ResizeAnchor {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl ResizeAnchor {
+    // The offset applied to every cell when resizing from `old` to `new`
+    fn offset(self, old: Size, new: Size) -> Pos {
+        let dx = new.width as i32 - old.width as i32;
+        let dy = new.height as i32 - old.height as i32;
+        match self {
+            ResizeAnchor::TopLeft => Pos::ZERO,
+            ResizeAnchor::TopRight => Pos::new(dx, 0),
+            ResizeAnchor::BottomLeft => Pos::new(0, dy),
+            ResizeAnchor::BottomRight => Pos::new(dx, dy),
+        }
+    }
+}
+
+/// How the cells of the canvas are painted onto the surface
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum Blend {
+    /// The cells replace the surface cells
+    #[default]
+    Replace,
+    /// Cells with an `alpha` attribute have their colours blended
+    /// with the surface cells, and blank cells keep the glyph of the surface
+    Over,
+}
+
+impl TryFrom<CommonVal<'_>> for Blend {
+    type Error = ();
+
+    fn try_from(value: CommonVal<'_>) -> Result<Self, Self::Error> {
+        match value {
+            CommonVal::Str("replace") => Ok(Blend::Replace),
+            CommonVal::Str("over") => Ok(Blend::Over),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryFrom<CommonVal<'_>> for ResizeAnchor {
+    type Error = ();
+
+    fn try_from(value: CommonVal<'_>) -> Result<Self, Self::Error> {
+        match value {
+            CommonVal::Str(anchor) => match anchor {
+                "top_left" => Ok(ResizeAnchor::TopLeft),
+                "top_right" => Ok(ResizeAnchor::TopRight),
+                "bottom_left" => Ok(ResizeAnchor::BottomLeft),
+                "bottom_right" => Ok(ResizeAnchor::BottomRight),
+                _ => Err(()),
+            },
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 struct Buffer {
     cells: Slab<usize, Cell>,
     positions: Box<[Entry]>,
@@ -107,33 +237,99 @@ impl Buffer {
         }
     }
 
-    fn put(&mut self, c: char, attribs: CanvasAttribs, pos: impl Into<LocalPos>) {
+    // The index of a position, if the position is inside the buffer
+    fn index(&self, pos: LocalPos) -> Option<usize> {
+        if pos.x as usize >= self.size.width || pos.y as usize >= self.size.height {
+            return None;
+        }
+        Some(pos.to_index(self.size.width))
+    }
+
+    fn put_glyph(&mut self, glyph: &str, attribs: CanvasAttribs, pos: impl Into<LocalPos>) -> bool {
         let pos = pos.into();
 
-        let cell_id = self.cells.next_id();
+        // Control characters (e.g `\x07`) would be written as is to the
+        // terminal, and are therefore rejected.
+        let Some(first) = glyph.chars().next() else { return false };
+        if glyph.chars().any(|c| c.is_ascii_control()) {
+            return false;
+        }
 
-        let index = pos.to_index(self.size.width);
-        if index >= self.positions.len() {
-            return;
+        // Zero-width characters are attached to the preceding cell
+        // rather than occupying a cell of their own.
+        if first.width() == Some(0) {
+            return self.combine(glyph, attribs, pos);
         }
 
-        let cell = Cell::Occupied(pos, c, attribs);
-        let mut entry = Entry::Occupied(cell_id);
-        std::mem::swap(&mut self.positions[index], &mut entry);
+        self.insert(glyph.into(), attribs, pos)
+    }
 
-        match entry {
-            Entry::Vacant => {
-                let new_cell_id = self.cells.insert(cell);
-                assert_eq!(new_cell_id, cell_id);
+    fn put_char(&mut self, c: char, attribs: CanvasAttribs, pos: impl Into<LocalPos>) -> bool {
+        self.put_glyph(c.encode_utf8(&mut [0; 4]), attribs, pos)
+    }
+
+    // Attach zero-width characters to the glyph before the position.
+    // The glyph is either in the previous cell, or is a wide glyph
+    // with its (empty) trailing cell in the previous cell.
+    // Without a glyph to attach to, the characters are combined with a space.
+    fn combine(&mut self, s: &str, attribs: CanvasAttribs, pos: LocalPos) -> bool {
+        let base = match pos.x.checked_sub(1).map(|x| LocalPos::new(x, pos.y)) {
+            Some(prev) if self.get(prev).is_some() => Some(prev),
+            Some(prev) if prev.x > 0 => {
+                let lead = LocalPos::new(prev.x - 1, prev.y);
+                self.get(lead)
+                    .filter(|(glyph, _)| glyph.chars().next().and_then(|c| c.width()) == Some(2))
+                    .map(|_| lead)
+            }
+            _ => None,
+        };
+
+        match base.and_then(|base| self.get_mut(base)) {
+            Some(Cell::Occupied(_, glyph, _)) => {
+                glyph.push_str(s);
+                true
             }
-            Entry::Occupied(idx) => {
-                self.cells.replace(idx, cell);
+            _ => self.insert(format!(" {s}"), attribs, pos),
+        }
+    }
+
+    fn insert(&mut self, glyph: String, attribs: CanvasAttribs, pos: LocalPos) -> bool {
+        let Some(index) = self.index(pos) else { return false };
+
+        let cell = Cell::Occupied(pos, glyph, attribs);
+
+        match self.positions[index] {
+            Entry::Vacant => self.positions[index] = Entry::Occupied(self.cells.insert(cell)),
+            // Overwrite the existing cell in place
+            Entry::Occupied(idx) => _ = self.cells.replace(idx, cell),
+        }
+
+        true
+    }
+
+    fn fill_rect(&mut self, c: char, attribs: &CanvasAttribs, top_left: LocalPos, size: Size) -> usize {
+        // Control and zero-width characters can't fill a cell on their own
+        if c.is_ascii_control() || c.width() == Some(0) {
+            return 0;
+        }
+
+        let x_end = (top_left.x as usize + size.width).min(self.size.width);
+        let y_end = (top_left.y as usize + size.height).min(self.size.height);
+
+        let mut count = 0;
+        for y in top_left.y as usize..y_end {
+            for x in top_left.x as usize..x_end {
+                let pos = LocalPos::new(x as u16, y as u16);
+                if self.insert(c.into(), attribs.clone(), pos) {
+                    count += 1;
+                }
             }
         }
+        count
     }
 
     fn get_mut(&mut self, pos: impl Into<LocalPos>) -> Option<&mut Cell> {
-        let index = pos.into().to_index(self.size.width);
+        let index = self.index(pos.into())?;
         match self.positions[index] {
             Entry::Occupied(idx) => self.cells.get_mut(idx),
             Entry::Vacant => None,
@@ -141,45 +337,116 @@ impl Buffer {
     }
 
     fn remove(&mut self, pos: impl Into<LocalPos>) {
-        let index = pos.into().to_index(self.size.width);
-        if index < self.positions.len() {
-            let Entry::Occupied(idx) = std::mem::take(&mut self.positions[index]) else { return };
-            self.cells.remove(idx);
-        }
+        let Some(index) = self.index(pos.into()) else { return };
+        let Entry::Occupied(idx) = std::mem::take(&mut self.positions[index]) else { return };
+        self.cells.remove(idx);
     }
 
-    fn copy_from(other: &mut Buffer, size: Size) -> Self {
+    // Remove every cell, keeping the allocations
+    fn clear(&mut self) {
+        self.cells.consume().for_each(drop);
+        self.positions.fill(Entry::Vacant);
+    }
+
+    fn copy_from(other: &mut Buffer, size: Size, anchor: ResizeAnchor) -> Self {
+        let offset = anchor.offset(other.size, size);
+
+        // If the width is unchanged and the height grows then the index of every
+        // position stays the same, so the cells can be moved over as they are.
+        if offset == Pos::ZERO && size.width == other.size.width && size.height >= other.size.height {
+            let mut positions = std::mem::take(&mut other.positions).into_vec();
+            positions.resize(size.width * size.height, Entry::Vacant);
+
+            return Self {
+                cells: std::mem::replace(&mut other.cells, Slab::empty()),
+                positions: positions.into_boxed_slice(),
+                size,
+            };
+        }
+
         let mut new_buffer = Buffer::new(size);
 
-        for (pos, c, attrs) in other.drain() {
-            if pos.x >= size.width as u16 || pos.y >= size.height as u16 {
+        for (pos, glyph, attrs) in other.drain() {
+            let pos = Pos::from(pos) + offset;
+            if pos.x < 0 || pos.y < 0 || pos.x >= size.width as i32 || pos.y >= size.height as i32 {
                 continue;
             }
-            new_buffer.put(c, attrs, pos);
+            new_buffer.insert(glyph, attrs, LocalPos::new(pos.x as u16, pos.y as u16));
         }
 
         new_buffer
     }
 
-    fn drain(&mut self) -> impl Iterator<Item = (LocalPos, char, CanvasAttribs)> + '_ {
+    fn drain(&mut self) -> impl Iterator<Item = (LocalPos, String, CanvasAttribs)> + '_ {
         self.cells.consume().filter_map(|cell| match cell {
             Cell::Empty => None,
-            Cell::Occupied(pos, c, attribs) => Some((pos, c, attribs)),
+            Cell::Occupied(pos, glyph, attribs) => Some((pos, glyph, attribs)),
         })
     }
 
-    fn iter(&self) -> impl Iterator<Item = (LocalPos, char, &CanvasAttribs)> + '_ {
+    fn iter(&self) -> impl Iterator<Item = (LocalPos, &str, &CanvasAttribs)> + '_ {
         self.cells.iter().filter_map(|(_, cell)| match cell {
             Cell::Empty => None,
-            Cell::Occupied(pos, c, attribs) => Some((*pos, *c, attribs)),
+            Cell::Occupied(pos, glyph, attribs) => Some((*pos, glyph.as_str(), attribs)),
         })
     }
+
+    fn get(&self, pos: impl Into<LocalPos>) -> Option<(&str, &CanvasAttribs)> {
+        let index = self.index(pos.into())?;
+        match self.positions[index] {
+            Entry::Occupied(idx) => match self.cells.get(idx)? {
+                Cell::Occupied(_, glyph, attribs) => Some((glyph, attribs)),
+                Cell::Empty => None,
+            },
+            Entry::Vacant => None,
+        }
+    }
+
+    fn to_string_grid(&self) -> String {
+        let mut grid = vec![vec![String::from(" "); self.size.width]; self.size.height];
+        for (pos, glyph, _) in self.iter() {
+            grid[pos.y as usize][pos.x as usize] = glyph.to_string();
+        }
+
+        grid.into_iter().map(|row| row.concat()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// An immutable snapshot of a canvas, see [`Canvas::freeze`].
+/// The view can be sent to another thread and read
+/// while the canvas is drawn to.
+#[derive(Debug, Clone)]
+pub struct CanvasView {
+    buffer: Arc<Buffer>,
+}
+
+impl CanvasView {
+    pub fn size(&self) -> Size {
+        self.buffer.size
+    }
+
+    pub fn get(&self, pos: impl Into<LocalPos>) -> Option<(&str, &CanvasAttribs)> {
+        self.buffer.get(pos)
+    }
+
+    /// Iterate over all occupied cells
+    pub fn iter(&self) -> impl Iterator<Item = (LocalPos, &str, &CanvasAttribs)> + '_ {
+        self.buffer.iter()
+    }
+
+    /// The glyphs of the view as rows of text.
+    /// Empty cells are written as a space.
+    pub fn to_string_grid(&self) -> String {
+        self.buffer.to_string_grid()
+    }
 }
 
 #[derive(Debug)]
 pub struct Canvas {
-    buffer: Buffer,
+    // Shared with any frozen views, and copied on write
+    buffer: Arc<Buffer>,
     pos: Pos,
+    clipped: bool,
 }
 
 impl Canvas {
@@ -188,27 +455,393 @@ impl Canvas {
         LocalPos::new(offset.x as u16, offset.y as u16)
     }
 
-    pub fn put(&mut self, c: char, attribs: CanvasAttribs, pos: impl Into<LocalPos>) {
-        self.buffer.put(c, attribs, pos);
+    /// Translate a position local to the canvas into a global position,
+    /// the inverse of [`Canvas::translate`]
+    pub fn to_global(&self, local: impl Into<LocalPos>) -> Pos {
+        self.pos + local.into()
+    }
+
+    /// Put a character in a single cell of the canvas.
+    /// Returns `false` if the character was rejected, either because it's
+    /// a control character or the position is outside of the canvas.
+    pub fn put(&mut self, c: char, attribs: CanvasAttribs, pos: impl Into<LocalPos>) -> bool {
+        Arc::make_mut(&mut self.buffer).put_char(c, attribs, pos)
     }
 
-    pub fn get(&mut self, pos: impl Into<LocalPos>) -> Option<(&mut char, &mut CanvasAttribs)> {
-        match self.buffer.get_mut(pos)? {
-            Cell::Occupied(_, c, attribs) => Some((c, attribs)),
+    /// Put a glyph in a single cell of the canvas, where the glyph can be
+    /// more than one character, e.g an emoji sequence or a ligature.
+    /// Returns `false` if the glyph was rejected, either because it's empty,
+    /// contains a control character or the position is outside of the canvas.
+    pub fn put_glyph(&mut self, glyph: &str, attribs: CanvasAttribs, pos: impl Into<LocalPos>) -> bool {
+        Arc::make_mut(&mut self.buffer).put_glyph(glyph, attribs, pos)
+    }
+
+    /// Write a string starting at `start`, advancing the cursor by the
+    /// width of each character. A newline moves the cursor to the next line,
+    /// back to the column of `start`.
+    ///
+    /// Wide characters occupy their leading cell and leave the trailing cell empty.
+    /// Characters outside of the canvas are skipped.
+    ///
+    /// Returns the position of the cursor after the last character.
+    pub fn put_str(&mut self, s: &str, attribs: &CanvasAttribs, start: impl Into<LocalPos>) -> LocalPos {
+        let start = start.into();
+        let width = self.buffer.size.width;
+        let buffer = Arc::make_mut(&mut self.buffer);
+        let mut pos = start;
+
+        for c in s.chars() {
+            if c == '\n' {
+                pos = LocalPos::new(start.x, pos.y + 1);
+                continue;
+            }
+
+            let advance = c.width().unwrap_or(0) as u16;
+            // Zero-width characters attach to the glyph before the cursor,
+            // which can be the last glyph of the line
+            let inside = (pos.x as usize) < width || advance == 0;
+            if inside && buffer.put_char(c, attribs.clone(), pos) && advance > 1 {
+                buffer.remove(LocalPos::new(pos.x + 1, pos.y));
+            }
+            pos.x += advance;
+        }
+
+        pos
+    }
+
+    /// Fill a rectangle with a character.
+    /// Cells outside of the canvas are skipped.
+    ///
+    /// Returns the number of cells written,
+    /// which is less than the area of the rectangle if it was clipped.
+    pub fn fill_rect(
+        &mut self,
+        c: char,
+        attribs: &CanvasAttribs,
+        top_left: impl Into<LocalPos>,
+        size: impl Into<Size>,
+    ) -> usize {
+        Arc::make_mut(&mut self.buffer).fill_rect(c, attribs, top_left.into(), size.into())
+    }
+
+    /// Read a cell without a mutable borrow of the canvas
+    pub fn get_ref(&self, pos: impl Into<LocalPos>) -> Option<(&str, &CanvasAttribs)> {
+        self.buffer.get(pos)
+    }
+
+    pub fn get(&mut self, pos: impl Into<LocalPos>) -> Option<(&mut String, &mut CanvasAttribs)> {
+        match Arc::make_mut(&mut self.buffer).get_mut(pos)? {
+            Cell::Occupied(_, glyph, attribs) => Some((glyph, attribs)),
+            Cell::Empty => None,
+        }
+    }
+
+    /// Get the attributes of a cell, leaving the glyph as is
+    pub fn get_attribs_mut(&mut self, pos: impl Into<LocalPos>) -> Option<&mut CanvasAttribs> {
+        match Arc::make_mut(&mut self.buffer).get_mut(pos)? {
+            Cell::Occupied(_, _, attribs) => Some(attribs),
             Cell::Empty => None,
         }
     }
 
     pub fn erase(&mut self, pos: impl Into<LocalPos>) {
-        self.buffer.remove(pos)
+        Arc::make_mut(&mut self.buffer).remove(pos)
+    }
+
+    /// Copy the cells of another canvas into this canvas,
+    /// with the top left of the source placed at `dest`.
+    ///
+    /// Empty source cells, and cells with the `transparent` glyph,
+    /// leave the destination unchanged. Cells outside of this canvas are skipped.
+    pub fn blit_from(&mut self, source: &Canvas, dest: impl Into<LocalPos>, transparent: Option<&str>) {
+        let dest = dest.into();
+        let size = self.buffer.size;
+        let buffer = Arc::make_mut(&mut self.buffer);
+
+        for (pos, glyph, attribs) in source.buffer.iter() {
+            if Some(glyph) == transparent {
+                continue;
+            }
+
+            let x = dest.x as usize + pos.x as usize;
+            let y = dest.y as usize + pos.y as usize;
+            if x >= size.width || y >= size.height {
+                continue;
+            }
+
+            buffer.insert(glyph.into(), attribs.clone(), LocalPos::new(x as u16, y as u16));
+        }
+    }
+
+    /// The occupied cells inside a region.
+    /// The region is in global coordinates, the same as [`Canvas::translate`],
+    /// while the positions of the cells are local to the canvas.
+    pub fn cells_in(&self, region: Region) -> impl Iterator<Item = (LocalPos, &str, &CanvasAttribs)> + '_ {
+        self.buffer
+            .iter()
+            .filter(move |(pos, _, _)| region.contains(self.to_global(*pos)))
+    }
+
+    /// Remove every cell from the canvas.
+    /// The size of the canvas is unchanged.
+    pub fn clear(&mut self) {
+        match Arc::get_mut(&mut self.buffer) {
+            Some(buffer) => buffer.clear(),
+            // The buffer is shared with a frozen view,
+            // so there is nothing to reuse
+            None => self.buffer = Buffer::new(self.buffer.size).into(),
+        }
+    }
+
+    /// Read a block of cells as rows, where empty cells
+    /// (and cells outside of the canvas) are `None`.
+    pub fn read_block(
+        &self,
+        top_left: impl Into<LocalPos>,
+        size: impl Into<Size>,
+    ) -> Vec<Vec<Option<(String, CanvasAttribs)>>> {
+        let top_left = top_left.into();
+        let size = size.into();
+        let canvas_size = self.buffer.size;
+
+        (0..size.height)
+            .map(|y| {
+                (0..size.width)
+                    .map(|x| {
+                        let x = top_left.x as usize + x;
+                        let y = top_left.y as usize + y;
+                        if x >= canvas_size.width || y >= canvas_size.height {
+                            return None;
+                        }
+                        let (glyph, attribs) = self.buffer.get((x as u16, y as u16))?;
+                        Some((glyph.into(), attribs.clone()))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Create an immutable snapshot of the canvas.
+    /// This is cheap as the cells are only copied once the canvas is changed.
+    pub fn freeze(&self) -> CanvasView {
+        CanvasView {
+            buffer: self.buffer.clone(),
+        }
+    }
+
+    /// Returns `true` if any cell fell outside of the visible region
+    /// during the last paint, e.g to show a scroll indicator.
+    pub fn was_clipped(&self) -> bool {
+        self.clipped
+    }
+
+    /// Layout, position and paint an element on top of the canvas,
+    /// using the size of the canvas as the viewport.
+    pub fn render<'bp>(
+        &mut self,
+        element: &mut Element<'bp>,
+        children: &[Node],
+        values: &mut TreeValues<WidgetKind<'bp>>,
+        attribute_storage: &AttributeStorage<'bp>,
+    ) {
+        let size = self.buffer.size;
+        let mut text = StringStorage::new();
+        let viewport = Viewport::new(size);
+        let constraints = Constraints::loose(size);
+
+        let mut layout_ctx = LayoutCtx::new(text.new_session(), attribute_storage, &viewport);
+        layout_widget(element, children, values, constraints, &mut layout_ctx, true);
+        position_widget(Pos::ZERO, element, children, values, attribute_storage, true);
+        anathema_widgets::paint::paint(
+            self,
+            element,
+            children,
+            values,
+            attribute_storage,
+            &mut text.new_session(),
+            true,
+        );
+    }
+
+    /// The glyphs of the canvas as rows of text.
+    /// Empty cells are written as a space.
+    pub fn to_string_grid(&self) -> String {
+        self.buffer.to_string_grid()
     }
+
+    /// Same as [`Canvas::to_string_grid`] but without
+    /// trailing spaces on each row, and without trailing empty rows.
+    pub fn to_string_grid_trimmed(&self) -> String {
+        let grid = self.buffer.to_string_grid();
+        let mut rows = grid.lines().map(str::trim_end).collect::<Vec<_>>();
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+        rows.join("\n")
+    }
+
+    /// Draw a vertical bar from the bottom of the canvas.
+    /// The integer part of the height is drawn as full blocks, and the
+    /// fractional part as a partial block (rounded to the nearest eighth) on top.
+    pub fn draw_vbar(&mut self, x: u16, height: f64, attribs: CanvasAttribs) {
+        let canvas_height = self.buffer.size.height as u16;
+        let height = height.clamp(0.0, canvas_height as f64);
+        let full = height.trunc() as u16;
+        let eighths = (height.fract() * 8.0).round() as usize;
+
+        for offset in 1..=full {
+            self.put(FULL_BLOCK, attribs.clone(), (x, canvas_height - offset));
+        }
+
+        if eighths > 0 && full < canvas_height {
+            self.put(PARTIAL_BLOCKS[eighths - 1], attribs, (x, canvas_height - full - 1));
+        }
+    }
+
+    /// Draw a line from `from` to `to` (inclusive).
+    /// Cells outside of the canvas are skipped.
+    pub fn draw_line(&mut self, from: LocalPos, to: LocalPos, c: char, attribs: &CanvasAttribs) {
+        let size = self.buffer.size;
+        let buffer = Arc::make_mut(&mut self.buffer);
+        bresenham(from, to)
+            .filter(|pos| (pos.x as usize) < size.width && (pos.y as usize) < size.height)
+            .for_each(|pos| _ = buffer.put_char(c, attribs.clone(), pos));
+    }
+
+    /// Draw connected line segments between consecutive points.
+    /// The joint between two segments is only drawn once.
+    /// Cells outside of the canvas are skipped.
+    pub fn draw_polyline(&mut self, points: &[LocalPos], c: char, attribs: CanvasAttribs) {
+        let size = self.buffer.size;
+        let buffer = Arc::make_mut(&mut self.buffer);
+        let inside = |pos: &LocalPos| (pos.x as usize) < size.width && (pos.y as usize) < size.height;
+
+        if let [point] = points {
+            if inside(point) {
+                buffer.put_char(c, attribs, *point);
+            }
+            return;
+        }
+
+        for (index, segment) in points.windows(2).enumerate() {
+            let skip = usize::from(index > 0);
+            bresenham(segment[0], segment[1])
+                .skip(skip)
+                .filter(inside)
+                .for_each(|pos| _ = buffer.put_char(c, attribs.clone(), pos));
+        }
+    }
+}
+
+// Every position on the line between `from` and `to`, inclusive
+fn bresenham(from: LocalPos, to: LocalPos) -> impl Iterator<Item = LocalPos> {
+    let (mut x, mut y) = (from.x as i32, from.y as i32);
+    let (x1, y1) = (to.x as i32, to.y as i32);
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let step_x = if x < x1 { 1 } else { -1 };
+    let step_y = if y < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut done = false;
+
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+
+        let pos = LocalPos::new(x as u16, y as u16);
+        if x == x1 && y == y1 {
+            done = true;
+            return Some(pos);
+        }
+
+        let e2 = err * 2;
+        if e2 >= dy {
+            err += dy;
+            x += step_x;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += step_y;
+        }
+
+        Some(pos)
+    })
+}
+
+// Painting into a canvas makes it possible to render a widget tree
+// into a buffer, e.g for screenshots, rather than a terminal.
+//
+// The painted elements are positioned at zero (see `Canvas::render`),
+// so the positions are already local to the canvas, regardless of where
+// the canvas itself is positioned.
+impl WidgetRenderer for Canvas {
+    fn draw_glyph(&mut self, c: char, pos: Pos) {
+        self.draw_cluster(c.encode_utf8(&mut [0; 4]), pos);
+    }
+
+    fn draw_cluster(&mut self, cluster: &str, pos: Pos) {
+        let Ok(pos) = LocalPos::try_from(pos) else { return };
+        match self.get(pos) {
+            Some((glyph, _)) => {
+                glyph.clear();
+                glyph.push_str(cluster);
+            }
+            None => _ = self.put_glyph(cluster, CanvasAttribs::new(), pos),
+        }
+    }
+
+    fn set_attributes(&mut self, attribs: &dyn CellAttributes, pos: Pos) {
+        let Ok(pos) = LocalPos::try_from(pos) else { return };
+        let attribs = CanvasAttribs::from_cell_attribs(attribs);
+        match self.get(pos) {
+            // Like the terminal, only the attributes that are set are replaced,
+            // e.g a child without a background keeps the background of the parent
+            Some((_, existing)) => {
+                for (key, value) in attribs.0.iter() {
+                    existing.set(key.clone(), value.clone());
+                }
+            }
+            None => _ = self.put(' ', attribs, pos),
+        }
+    }
+
+    fn size(&self) -> Size {
+        self.buffer.size
+    }
+
+    fn read_cell(&self, pos: Pos) -> Option<SurfaceCell> {
+        let (_, attribs) = self.buffer.get(LocalPos::try_from(pos).ok()?)?;
+        Some(SurfaceCell {
+            foreground: attribs.get_hex(STYLE_COLORS[0]),
+            background: attribs.get_hex(STYLE_COLORS[1]),
+        })
+    }
+}
+
+/// Layout, position and paint an element into a new canvas of a given size.
+pub fn render_to_canvas<'bp>(
+    element: &mut Element<'bp>,
+    children: &[Node],
+    values: &mut TreeValues<WidgetKind<'bp>>,
+    attribute_storage: &AttributeStorage<'bp>,
+    size: Size,
+) -> Canvas {
+    let mut canvas = Canvas {
+        buffer: Buffer::new(size).into(),
+        pos: Pos::ZERO,
+        clipped: false,
+    };
+    canvas.render(element, children, values, attribute_storage);
+    canvas
 }
 
 impl Default for Canvas {
     fn default() -> Self {
         Self {
-            buffer: Buffer::new((32, 32).into()),
+            buffer: Buffer::new((32, 32).into()).into(),
             pos: Pos::ZERO,
+            clipped: false,
         }
     }
 }
@@ -234,7 +867,8 @@ impl Widget for Canvas {
         let size = constraints.max_size();
 
         if self.buffer.size != size {
-            self.buffer = Buffer::copy_from(&mut self.buffer, size);
+            let anchor = attribs.get(ANCHOR).unwrap_or_default();
+            self.buffer = Buffer::copy_from(Arc::make_mut(&mut self.buffer), size, anchor).into();
         }
 
         self.buffer.size
@@ -253,20 +887,43 @@ impl Widget for Canvas {
     fn paint<'bp>(
         &mut self,
         _children: PaintChildren<'_, '_, 'bp>,
-        _id: WidgetId,
-        _attribute_storage: &AttributeStorage<'bp>,
+        id: WidgetId,
+        attribute_storage: &AttributeStorage<'bp>,
         mut ctx: PaintCtx<'_, SizePos>,
         _text: &mut StringSession<'_>,
     ) {
-        for (pos, c, attribs) in self.buffer.iter() {
+        self.clipped = false;
+        let blend = attribute_storage.get(id).get(BLEND).unwrap_or_default();
+
+        for (pos, glyph, attribs) in self.buffer.iter() {
+            if !ctx.is_visible(pos) {
+                self.clipped = true;
+                continue;
+            }
+
+            let below = match blend {
+                Blend::Over => attribs.alpha().zip(ctx.read_cell(pos)),
+                Blend::Replace => None,
+            };
+
+            if let Some((alpha, below)) = below {
+                ctx.set_attributes(&attribs.blend_over(alpha, below), pos);
+                if glyph != " " {
+                    ctx.place_cluster(glyph, pos);
+                }
+                continue;
+            }
+
             ctx.set_attributes(attribs, pos);
-            ctx.place_glyph(c, pos);
+            ctx.place_cluster(glyph, pos);
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use anathema::Hex;
+
     use super::*;
     use crate::testing::TestRunner;
 
@@ -281,12 +938,220 @@ mod test {
         TestRunner::new("canvas", (2, 2)).instance().render_assert(expected);
     }
 
+    #[test]
+    fn clipped_canvas() {
+        let expected = "
+            ╔════╗
+            ║    ║
+            ║    ║
+            ║    ║
+            ║    ║
+            ╚════╝
+        ";
+
+        let mut clipped = false;
+        TestRunner::new("canvas [width: 10, height: 10]", (4, 4))
+            .instance()
+            .with_widget(|mut query| {
+                query.by_tag("canvas").first(|el, _| {
+                    el.to::<Canvas>().put('a', CanvasAttribs::new(), (9, 9));
+                });
+            })
+            .render_assert(expected)
+            .with_widget(|mut query| {
+                query
+                    .by_tag("canvas")
+                    .first(|el, _| clipped = el.to::<Canvas>().was_clipped());
+            });
+
+        assert!(clipped);
+    }
+
+    #[test]
+    fn unclipped_canvas() {
+        let expected = "
+            ╔════╗
+            ║a   ║
+            ║    ║
+            ║    ║
+            ║    ║
+            ╚════╝
+        ";
+
+        let mut clipped = true;
+        TestRunner::new("canvas", (4, 4))
+            .instance()
+            .with_widget(|mut query| {
+                query.by_tag("canvas").first(|el, _| {
+                    el.to::<Canvas>().put('a', CanvasAttribs::new(), (0, 0));
+                });
+            })
+            .render_assert(expected)
+            .with_widget(|mut query| {
+                query
+                    .by_tag("canvas")
+                    .first(|el, _| clipped = el.to::<Canvas>().was_clipped());
+            });
+
+        assert!(!clipped);
+    }
+
     #[test]
     fn get_set_glyph() {
         let mut canvas = Canvas::default();
         canvas.put('a', CanvasAttribs::new(), (0, 0));
         let (c, _) = canvas.get((0, 0)).unwrap();
-        assert_eq!(*c, 'a');
+        assert_eq!(*c, "a");
+    }
+
+    #[test]
+    fn put_cluster() {
+        let mut canvas = Canvas::default();
+        // A flag is two regional indicators, shown as a single glyph
+        assert!(canvas.put_glyph("🇳🇴", CanvasAttribs::new(), (0, 0)));
+        assert_eq!(canvas.get_ref((0, 0)).map(|(c, _)| c), Some("🇳🇴"));
+        assert!(canvas.get_ref((1, 0)).is_none());
+        assert_eq!(canvas.buffer.iter().count(), 1);
+
+        assert!(!canvas.put_glyph("", CanvasAttribs::new(), (1, 0)));
+        assert!(!canvas.put_glyph("a\x07", CanvasAttribs::new(), (1, 0)));
+    }
+
+    #[test]
+    fn grow_height_preserves_cells() {
+        let mut buffer = Buffer::new((4, 2).into());
+        buffer.put_char('a', CanvasAttribs::new(), (0, 0));
+        buffer.put_char('b', CanvasAttribs::new(), (3, 1));
+
+        let mut buffer = Buffer::copy_from(&mut buffer, (4, 5).into(), ResizeAnchor::TopLeft);
+        assert_eq!(buffer.size, Size::new(4, 5));
+
+        let Some(Cell::Occupied(_, glyph, _)) = buffer.get_mut((0, 0)) else { panic!() };
+        assert_eq!(*glyph, "a");
+        let Some(Cell::Occupied(_, glyph, _)) = buffer.get_mut((3, 1)) else { panic!() };
+        assert_eq!(*glyph, "b");
+        assert!(buffer.get_mut((0, 4)).is_none());
+    }
+
+    #[test]
+    fn resize_anchored_to_bottom() {
+        let mut buffer = Buffer::new((4, 4).into());
+        buffer.put_char('a', CanvasAttribs::new(), (0, 3));
+        buffer.put_char('b', CanvasAttribs::new(), (3, 0));
+
+        // Shrinking keeps the bottom row, and drops the top row
+        let mut buffer = Buffer::copy_from(&mut buffer, (4, 2).into(), ResizeAnchor::BottomLeft);
+        assert_eq!(buffer.get((0, 1)).map(|(c, _)| c), Some("a"));
+        assert_eq!(buffer.iter().count(), 1);
+
+        // Growing moves the cells down
+        let buffer = Buffer::copy_from(&mut buffer, (4, 5).into(), ResizeAnchor::BottomLeft);
+        assert_eq!(buffer.get((0, 4)).map(|(c, _)| c), Some("a"));
+        assert_eq!(buffer.iter().count(), 1);
+    }
+
+    #[test]
+    fn resize_anchored_to_bottom_right() {
+        let mut buffer = Buffer::new((4, 4).into());
+        buffer.put_char('a', CanvasAttribs::new(), (3, 3));
+        buffer.put_char('b', CanvasAttribs::new(), (0, 0));
+
+        let buffer = Buffer::copy_from(&mut buffer, (2, 2).into(), ResizeAnchor::BottomRight);
+        assert_eq!(buffer.get((1, 1)).map(|(c, _)| c), Some("a"));
+        assert_eq!(buffer.iter().count(), 1);
+    }
+
+    #[test]
+    fn grow_height_keeps_cell_ids() {
+        let mut buffer = Buffer::new((8, 8).into());
+        for y in 0..8 {
+            for x in 0..8 {
+                buffer.put_char('x', CanvasAttribs::new(), (x, y));
+            }
+        }
+        let before = buffer
+            .positions
+            .iter()
+            .map(|entry| match entry {
+                Entry::Occupied(id) => *id,
+                Entry::Vacant => panic!("every position is occupied"),
+            })
+            .collect::<Vec<_>>();
+
+        let buffer = Buffer::copy_from(&mut buffer, (8, 16).into(), ResizeAnchor::TopLeft);
+
+        for (index, id) in before.into_iter().enumerate() {
+            assert!(matches!(buffer.positions[index], Entry::Occupied(i) if i == id));
+        }
+        assert!(buffer.positions[64..].iter().all(|e| matches!(e, Entry::Vacant)));
+    }
+
+    #[test]
+    fn overwrite_cell_in_place() {
+        let mut buffer = Buffer::new((4, 1).into());
+        buffer.put_char('a', CanvasAttribs::new(), (1, 0));
+        let Entry::Occupied(id) = buffer.positions[1] else { panic!("the position is occupied") };
+
+        buffer.put_char('b', CanvasAttribs::new(), (1, 0));
+        assert!(matches!(buffer.positions[1], Entry::Occupied(i) if i == id));
+        assert_eq!(buffer.iter().count(), 1);
+        let Some(Cell::Occupied(_, glyph, _)) = buffer.get_mut((1, 0)) else { panic!() };
+        assert_eq!(*glyph, "b");
+    }
+
+    #[test]
+    fn combining_char_attaches_to_previous_cell() {
+        let mut buffer = Buffer::new((4, 1).into());
+        buffer.put_char('e', CanvasAttribs::new(), (0, 0));
+        buffer.put_char('\u{301}', CanvasAttribs::new(), (1, 0));
+
+        assert!(buffer.get_mut((1, 0)).is_none());
+        assert_eq!(buffer.iter().count(), 1);
+        let Some(Cell::Occupied(_, glyph, _)) = buffer.get_mut((0, 0)) else { panic!() };
+        assert_eq!(*glyph, "e\u{301}");
+    }
+
+    #[test]
+    fn combining_char_after_wide_char() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((4, 1).into()).into(),
+            pos: Pos::ZERO,
+            clipped: false,
+        };
+        canvas.put_str("字\u{301}a", &CanvasAttribs::new(), (0, 0));
+
+        assert_eq!(canvas.get_ref((0, 0)).unwrap().0, "字\u{301}");
+        assert!(canvas.get_ref((1, 0)).is_none());
+        assert_eq!(canvas.get_ref((2, 0)).unwrap().0, "a");
+
+        canvas.put_str("bc\u{301}", &CanvasAttribs::new(), (2, 0));
+        assert_eq!(canvas.get_ref((3, 0)).unwrap().0, "c\u{301}");
+    }
+
+    #[test]
+    fn combining_char_without_base() {
+        let mut buffer = Buffer::new((4, 1).into());
+        assert!(buffer.put_char('\u{301}', CanvasAttribs::new(), (0, 0)));
+        assert!(buffer.put_char('\u{301}', CanvasAttribs::new(), (3, 0)));
+
+        assert_eq!(buffer.get((0, 0)).unwrap().0, " \u{301}");
+        assert_eq!(buffer.get((3, 0)).unwrap().0, " \u{301}");
+    }
+
+    #[test]
+    fn paint_combining_chars() {
+        let canvas = TestRunner::new("canvas [width: 2, height: 1]", (2, 1))
+            .instance()
+            .with_widget(|mut query| {
+                query.by_tag("canvas").first(|el, _| {
+                    let canvas = el.to::<Canvas>();
+                    canvas.put_str("e\u{301}", &CanvasAttribs::new(), (0, 0));
+                });
+            })
+            .render_to_canvas();
+
+        let (glyph, _) = canvas.get_ref((1, 1)).unwrap();
+        assert_eq!(glyph, "e\u{301}");
     }
 
     #[test]
@@ -297,4 +1162,494 @@ mod test {
         canvas.erase((0, 0));
         assert!(canvas.get((0, 0)).is_none());
     }
+
+    #[test]
+    fn put_outside_of_canvas() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((4, 3).into()).into(),
+            pos: Pos::ZERO,
+            clipped: false,
+        };
+
+        // Positions past the edge do not wrap on to the next row
+        assert!(!canvas.put('z', CanvasAttribs::new(), (5, 0)));
+        assert!(!canvas.put('z', CanvasAttribs::new(), (0, 3)));
+        assert!(canvas.get((1, 1)).is_none());
+        assert!(canvas.get((5, 0)).is_none());
+        canvas.erase((5, 0));
+
+        assert_eq!(canvas.to_string_grid(), "    \n    \n    ");
+    }
+
+    #[test]
+    fn vbar() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((2, 4).into()).into(),
+            pos: Pos::ZERO,
+            clipped: false,
+        };
+        canvas.draw_vbar(1, 2.5, CanvasAttribs::new());
+
+        assert!(canvas.get((1, 0)).is_none());
+        assert_eq!(*canvas.get((1, 1)).unwrap().0, "▄");
+        assert_eq!(*canvas.get((1, 2)).unwrap().0, "█");
+        assert_eq!(*canvas.get((1, 3)).unwrap().0, "█");
+        assert!(canvas.get((0, 3)).is_none());
+    }
+
+    #[test]
+    fn polyline() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((5, 3).into()).into(),
+            pos: Pos::ZERO,
+            clipped: false,
+        };
+        let points = [LocalPos::new(0, 0), LocalPos::new(2, 2), LocalPos::new(4, 0)];
+        canvas.draw_polyline(&points, '*', CanvasAttribs::new());
+
+        let expected = "
+*   *
+ * * 
+  *  ";
+        assert_eq!(canvas.to_string_grid(), &expected[1..]);
+        assert_eq!(canvas.buffer.iter().count(), 5);
+    }
+
+    #[test]
+    fn polyline_outside_of_canvas() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((3, 3).into()).into(),
+            pos: Pos::ZERO,
+            clipped: false,
+        };
+        let points = [
+            LocalPos::new(0, 0),
+            LocalPos::new(5, 0),
+            LocalPos::new(5, 2),
+            LocalPos::new(0, 2),
+        ];
+        canvas.draw_polyline(&points, '*', CanvasAttribs::new());
+
+        let expected = "
+***
+   
+***";
+        assert_eq!(canvas.to_string_grid(), &expected[1..]);
+        assert_eq!(canvas.buffer.iter().count(), 6);
+    }
+
+    #[test]
+    fn blit_with_transparent_key() {
+        let mut sprite = Canvas {
+            buffer: Buffer::new((3, 2).into()).into(),
+            ..Default::default()
+        };
+        for (x, c) in "x x".chars().enumerate() {
+            sprite.put(c, CanvasAttribs::new(), (x as u16, 0));
+        }
+        for (x, c) in " x ".chars().enumerate() {
+            sprite.put(c, CanvasAttribs::new(), (x as u16, 1));
+        }
+
+        let mut canvas = Canvas {
+            buffer: Buffer::new((4, 3).into()).into(),
+            ..Default::default()
+        };
+        canvas.fill_rect('.', &CanvasAttribs::new(), (0, 0), (4, 3));
+        canvas.blit_from(&sprite, (1, 1), Some(" "));
+
+        let expected = "
+....
+.x.x
+..x.";
+        assert_eq!(canvas.to_string_grid(), &expected[1..]);
+    }
+
+    #[test]
+    fn string_grid_trimmed() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((5, 4).into()).into(),
+            ..Default::default()
+        };
+        canvas.put('a', CanvasAttribs::new(), (0, 0));
+        canvas.put('b', CanvasAttribs::new(), (1, 1));
+
+        assert_eq!(canvas.to_string_grid_trimmed(), "a\n b");
+    }
+
+    #[test]
+    fn blit_without_transparent_key() {
+        let mut sprite = Canvas {
+            buffer: Buffer::new((2, 1).into()).into(),
+            ..Default::default()
+        };
+        sprite.put('x', CanvasAttribs::new(), (0, 0));
+        sprite.put(' ', CanvasAttribs::new(), (1, 0));
+
+        let mut canvas = Canvas {
+            buffer: Buffer::new((3, 1).into()).into(),
+            ..Default::default()
+        };
+        canvas.fill_rect('.', &CanvasAttribs::new(), (0, 0), (3, 1));
+        // The last cell of the sprite is outside of the canvas
+        canvas.blit_from(&sprite, (2, 0), None);
+        assert_eq!(canvas.to_string_grid(), "..x");
+
+        canvas.blit_from(&sprite, (0, 0), None);
+        assert_eq!(canvas.to_string_grid(), "x x");
+    }
+
+    #[test]
+    fn cells_in_region() {
+        let mut canvas = Canvas {
+            pos: Pos::new(10, 5),
+            ..Default::default()
+        };
+        canvas.put('a', CanvasAttribs::new(), (0, 0));
+        canvas.put('b', CanvasAttribs::new(), (2, 1));
+        canvas.put('c', CanvasAttribs::new(), (5, 5));
+
+        // Global region covering the first two local rows and three columns
+        let region = Region::new(Pos::new(10, 5), Pos::new(13, 7));
+        let mut cells = canvas.cells_in(region).map(|(pos, c, _)| (pos, c)).collect::<Vec<_>>();
+        cells.sort_by_key(|(pos, _)| (pos.y, pos.x));
+        assert_eq!(cells, vec![(LocalPos::new(0, 0), "a"), (LocalPos::new(2, 1), "b")]);
+
+        // The same region in local coordinates only covers the origin of the screen
+        let region = Region::new(Pos::new(0, 0), Pos::new(3, 2));
+        assert_eq!(canvas.cells_in(region).count(), 0);
+    }
+
+    #[test]
+    fn clear() {
+        let mut canvas = Canvas::default();
+        let positions = [(0, 0), (3, 1), (5, 5)];
+        for pos in positions {
+            canvas.put('a', CanvasAttribs::new(), pos);
+        }
+
+        canvas.clear();
+        for pos in positions {
+            assert!(canvas.get(pos).is_none());
+        }
+        assert_eq!(canvas.buffer.size, Size::new(32, 32));
+
+        // Cells can be added again after clearing
+        canvas.put('b', CanvasAttribs::new(), (3, 1));
+        assert_eq!(canvas.get_ref((3, 1)).map(|(c, _)| c), Some("b"));
+    }
+
+    #[test]
+    fn clear_frozen_canvas() {
+        let mut canvas = Canvas::default();
+        canvas.put('a', CanvasAttribs::new(), (0, 0));
+        let view = canvas.freeze();
+
+        canvas.clear();
+        assert!(canvas.get_ref((0, 0)).is_none());
+        assert_eq!(view.get((0, 0)).map(|(c, _)| c), Some("a"));
+    }
+
+    #[test]
+    fn draw_line() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((5, 4).into()).into(),
+            ..Default::default()
+        };
+
+        let attribs = CanvasAttribs::new();
+        let corners = [(0, 0), (4, 0), (4, 3), (0, 3)].map(|(x, y)| LocalPos::new(x, y));
+        for (i, from) in corners.iter().enumerate() {
+            let to = corners[(i + 1) % corners.len()];
+            canvas.draw_line(*from, to, '#', &attribs);
+        }
+
+        let expected = "
+#####
+#   #
+#   #
+#####";
+        assert_eq!(canvas.to_string_grid(), &expected[1..]);
+    }
+
+    #[test]
+    fn draw_diagonal_line() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((3, 3).into()).into(),
+            ..Default::default()
+        };
+
+        // The end of the line is outside of the canvas
+        canvas.draw_line(LocalPos::new(0, 0), LocalPos::new(5, 5), '\\', &CanvasAttribs::new());
+
+        let expected = "
+\\  
+ \\ 
+  \\";
+        assert_eq!(canvas.to_string_grid(), &expected[1..]);
+        assert_eq!(canvas.buffer.iter().count(), 3);
+    }
+
+    #[test]
+    fn frozen_view_is_unchanged() {
+        let mut canvas = Canvas::default();
+        canvas.put('a', CanvasAttribs::new(), (0, 0));
+        canvas.put('b', CanvasAttribs::new(), (1, 0));
+
+        let view = canvas.freeze();
+        canvas.put('x', CanvasAttribs::new(), (0, 0));
+        canvas.erase((1, 0));
+        canvas.put('c', CanvasAttribs::new(), (2, 0));
+
+        assert_eq!(view.get((0, 0)).unwrap().0, "a");
+        assert_eq!(view.get((1, 0)).unwrap().0, "b");
+        assert!(view.get((2, 0)).is_none());
+        assert_eq!(view.iter().count(), 2);
+
+        assert_eq!(*canvas.get((0, 0)).unwrap().0, "x");
+        assert!(canvas.get((1, 0)).is_none());
+    }
+
+    #[test]
+    fn read_block() {
+        let mut canvas = Canvas::default();
+        canvas.put('a', CanvasAttribs::new(), (3, 4));
+
+        let block = canvas.read_block((2, 4), (2, 2));
+        assert_eq!(block.len(), 2);
+        assert!(block.iter().all(|row| row.len() == 2));
+
+        assert!(block[0][0].is_none());
+        assert_eq!(block[0][1].as_ref().unwrap().0, "a");
+        assert!(block[1][0].is_none());
+        assert!(block[1][1].is_none());
+    }
+
+    #[test]
+    fn get_ref() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((4, 3).into()).into(),
+            ..Default::default()
+        };
+        canvas.put('a', CanvasAttribs::new(), (1, 2));
+
+        let canvas = &canvas;
+        assert_eq!(canvas.get_ref((1, 2)).map(|(c, _)| c), Some("a"));
+        assert!(canvas.get_ref((0, 0)).is_none());
+        // Outside of the canvas
+        assert!(canvas.get_ref((4, 0)).is_none());
+        assert!(canvas.get_ref((0, 3)).is_none());
+    }
+
+    #[test]
+    fn get_attribs_mut() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((4, 3).into()).into(),
+            ..Default::default()
+        };
+        canvas.put('a', CanvasAttribs::new(), (1, 2));
+
+        canvas.get_attribs_mut((1, 2)).unwrap().set("bold", true);
+        let (c, attribs) = canvas.get_ref((1, 2)).unwrap();
+        assert_eq!(c, "a");
+        assert!(attribs.get_bool("bold"));
+
+        assert!(canvas.get_attribs_mut((0, 0)).is_none());
+    }
+
+    #[test]
+    fn fill_rect() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((4, 3).into()).into(),
+            ..Default::default()
+        };
+
+        let written = canvas.fill_rect('x', &CanvasAttribs::new(), (1, 1), (2, 2));
+        assert_eq!(written, 4);
+        assert_eq!(canvas.freeze().to_string_grid(), "    \n xx \n xx ");
+    }
+
+    #[test]
+    fn fill_rect_zero_size() {
+        let mut canvas = Canvas::default();
+        assert_eq!(canvas.fill_rect('x', &CanvasAttribs::new(), (0, 0), (0, 5)), 0);
+        assert_eq!(canvas.fill_rect('x', &CanvasAttribs::new(), (0, 0), (5, 0)), 0);
+        assert!(canvas.freeze().iter().next().is_none());
+    }
+
+    #[test]
+    fn fill_rect_overflow() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((4, 3).into()).into(),
+            ..Default::default()
+        };
+
+        // Only the 2 x 1 cells in the bottom right corner are inside the canvas
+        let written = canvas.fill_rect('x', &CanvasAttribs::new(), (2, 2), (5, 5));
+        assert_eq!(written, 2);
+        assert_eq!(canvas.freeze().to_string_grid(), "    \n    \n  xx");
+    }
+
+    #[test]
+    fn local_to_global() {
+        let canvas = Canvas {
+            pos: Pos::new(3, 2),
+            ..Default::default()
+        };
+
+        let global = canvas.to_global((1, 1));
+        assert_eq!(global, Pos::new(4, 3));
+        assert_eq!(canvas.translate(global), LocalPos::new(1, 1));
+    }
+
+    #[test]
+    fn render_tree_to_canvas() {
+        let tpl = "
+            container
+                text 'hi'
+        ";
+
+        let canvas = TestRunner::new(tpl, (4, 1)).instance().render_to_canvas();
+        let expected = "╔════╗\n║hi  ║\n╚════╝";
+        assert_eq!(canvas.to_string_grid(), expected);
+    }
+
+    #[test]
+    fn render_into_positioned_canvas() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new(Size::new(6, 3)).into(),
+            pos: Pos::new(3, 2),
+            clipped: false,
+        };
+
+        TestRunner::new("text 'hi'", (4, 1)).instance().render_into(&mut canvas);
+        let expected = "╔════╗\n║hi  ║\n╚════╝";
+        assert_eq!(canvas.to_string_grid(), expected);
+    }
+
+    #[test]
+    fn reject_control_chars() {
+        let mut canvas = Canvas::default();
+        assert!(!canvas.put('\x07', CanvasAttribs::new(), (0, 0)));
+        assert!(canvas.get((0, 0)).is_none());
+
+        assert!(canvas.put('a', CanvasAttribs::new(), (0, 0)));
+        assert!(!canvas.put('\x07', CanvasAttribs::new(), (0, 0)));
+        let (c, _) = canvas.get((0, 0)).unwrap();
+        assert_eq!(*c, "a");
+    }
+
+    #[test]
+    fn put_str() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((6, 3).into()).into(),
+            ..Default::default()
+        };
+
+        let end = canvas.put_str("ab\ncd", &CanvasAttribs::new(), (1, 0));
+        assert_eq!(end, LocalPos::new(3, 1));
+        assert_eq!(canvas.freeze().to_string_grid(), " ab   \n cd   \n      ");
+    }
+
+    #[test]
+    fn put_str_wide_chars() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((6, 1).into()).into(),
+            ..Default::default()
+        };
+        canvas.fill_rect('x', &CanvasAttribs::new(), (0, 0), (6, 1));
+
+        let end = canvas.put_str("a😀b", &CanvasAttribs::new(), (0, 0));
+        assert_eq!(end, LocalPos::new(4, 0));
+        assert_eq!(canvas.get_ref((1, 0)).map(|(c, _)| c), Some("😀"));
+        // The trailing cell of the wide character is left empty
+        assert!(canvas.get_ref((2, 0)).is_none());
+        assert_eq!(canvas.get_ref((3, 0)).map(|(c, _)| c), Some("b"));
+    }
+
+    #[test]
+    fn put_str_overflow() {
+        let mut canvas = Canvas {
+            buffer: Buffer::new((3, 2).into()).into(),
+            ..Default::default()
+        };
+
+        // Characters past the edge are not wrapped onto the next row
+        let end = canvas.put_str("abcde", &CanvasAttribs::new(), (1, 0));
+        assert_eq!(end, LocalPos::new(6, 0));
+        assert_eq!(canvas.freeze().to_string_grid(), " ab\n   ");
+    }
+
+    #[test]
+    fn blend_over_surface() {
+        let tpl = "
+            container [background: #0000ff]
+                canvas [blend: 'over', width: 2, height: 1]
+        ";
+
+        let mut attribs = CanvasAttribs::new();
+        attribs.set("background", Hex::from((255, 0, 0)));
+        attribs.set(ALPHA, 0.5);
+
+        let canvas = TestRunner::new(tpl, (2, 1))
+            .instance()
+            .with_widget(|mut query| {
+                query.by_tag("canvas").first(|el, _| {
+                    let canvas = el.to::<Canvas>();
+                    canvas.put('a', attribs.clone(), (0, 0));
+                    canvas.put(' ', attribs.clone(), (1, 0));
+                });
+            })
+            .render_to_canvas();
+
+        let purple = Hex::from((128, 0, 128));
+        let (c, attribs) = canvas.get_ref((1, 1)).unwrap();
+        assert_eq!(c, "a");
+        assert_eq!(attribs.get_hex("background"), Some(purple));
+
+        let (c, attribs) = canvas.get_ref((2, 1)).unwrap();
+        assert_eq!(c, " ");
+        assert_eq!(attribs.get_hex("background"), Some(purple));
+    }
+
+    #[test]
+    fn blend_replace_surface() {
+        let tpl = "
+            container [background: #0000ff]
+                canvas [width: 1, height: 1]
+        ";
+
+        let mut attribs = CanvasAttribs::new();
+        attribs.set("background", Hex::from((255, 0, 0)));
+        attribs.set(ALPHA, 0.5);
+
+        let canvas = TestRunner::new(tpl, (1, 1))
+            .instance()
+            .with_widget(|mut query| {
+                query.by_tag("canvas").first(|el, _| {
+                    el.to::<Canvas>().put('a', attribs.clone(), (0, 0));
+                });
+            })
+            .render_to_canvas();
+
+        let (_, attribs) = canvas.get_ref((1, 1)).unwrap();
+        assert_eq!(attribs.get_hex("background"), Some(Hex::from((255, 0, 0))));
+    }
+
+    #[test]
+    fn paint_clusters() {
+        let canvas = TestRunner::new("canvas [width: 2, height: 1]", (2, 1))
+            .instance()
+            .with_widget(|mut query| {
+                query.by_tag("canvas").first(|el, _| {
+                    let canvas = el.to::<Canvas>();
+                    canvas.put_glyph("🇳🇴", CanvasAttribs::new(), (0, 0));
+                });
+            })
+            .render_to_canvas();
+
+        // The whole cluster is painted, not only the leading char
+        let (glyph, _) = canvas.get_ref((1, 1)).unwrap();
+        assert_eq!(glyph, "🇳🇴");
+    }
 }
@@ -1,13 +1,93 @@
 use std::ops::ControlFlow;
 
-use anathema_geometry::Size;
+use anathema_geometry::{LocalPos, Size};
+use anathema_widgets::layout::text::{StringSession, StringStorage};
 use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
-use anathema_widgets::{AttributeStorage, LayoutChildren, PositionChildren, Widget, WidgetId};
+use anathema_widgets::paint::{CellAttributes, PaintCtx, SizePos};
+use anathema_widgets::{
+    AttributeStorage, Attributes, LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId,
+};
 
 use crate::{HEIGHT, MAX_HEIGHT, MAX_WIDTH, MIN_HEIGHT, MIN_WIDTH, WIDTH};
 
+const BACKGROUND: &str = "background";
+const ASPECT_RATIO: &str = "aspect_ratio";
+
+// The aspect ratio as width / height, either as a number
+// or a string, e.g `1.5`, `"1.5"` or `"16:9"`.
+fn aspect_ratio(attribs: &Attributes<'_>) -> Option<f64> {
+    let ratio = match attribs.get::<f64>(ASPECT_RATIO) {
+        Some(ratio) => ratio,
+        None => match attribs.get_int(ASPECT_RATIO) {
+            Some(ratio) => ratio as f64,
+            None => {
+                let ratio = attribs.get_ref::<&str>(ASPECT_RATIO)?;
+                match ratio.split_once(':') {
+                    Some((width, height)) => width.trim().parse::<f64>().ok()? / height.trim().parse::<f64>().ok()?,
+                    None => ratio.trim().parse().ok()?,
+                }
+            }
+        },
+    };
+
+    (ratio.is_finite() && ratio > 0.0).then_some(ratio)
+}
+
+// Fit the constraints to the aspect ratio.
+// The width wins: the height is derived from the max width,
+// and only if that height doesn't fit is the width derived from the
+// max height instead.
+fn apply_aspect_ratio(constraints: &mut Constraints, ratio: f64) {
+    if constraints.is_unbounded() {
+        return;
+    }
+
+    let max_width = constraints.max_width();
+    let max_height = constraints.max_height();
+
+    let mut width = match constraints.is_width_unbounded() {
+        true => (max_height as f64 * ratio).round() as usize,
+        false => max_width,
+    };
+    let mut height = (width as f64 / ratio).round() as usize;
+    if height > max_height {
+        height = max_height;
+        width = ((height as f64 * ratio).round() as usize).min(max_width);
+    }
+
+    constraints.make_width_tight(width);
+    constraints.make_height_tight(height);
+}
+
+// The outcome of the previous layout, reused as long as neither
+// the constraints nor anything below the container has changed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct LayoutCache {
+    constraints: Constraints,
+    viewport: Size,
+    size: Size,
+}
+
+/// A container that lays out a single child.
+///
+/// If the size attributes contradict each other the max size wins,
+/// e.g `container [width: 10, max_width: 5]` is five cells wide,
+/// followed by the min size and lastly the exact size.
+///
+/// With an `aspect_ratio`, e.g `"16:9"` or `2.0`, the container
+/// fills as much of the width as possible while keeping the ratio.
+/// When both axes are constrained the width wins, unless the derived
+/// height doesn't fit, in which case the width is derived from the height.
+///
+/// With a `background` the entire container is filled
+/// with the colour before the child is painted.
 #[derive(Debug, Default)]
-pub struct Container;
+pub struct Container {
+    cache: Option<LayoutCache>,
+    // The text of the child is kept here rather than in the
+    // text storage of the frame, so it outlives the cached layout
+    text: StringStorage,
+}
 
 impl Widget for Container {
     fn layout<'bp>(
@@ -20,42 +100,71 @@ impl Widget for Container {
         let mut size = Size::ZERO;
 
         let attribs = ctx.attribs.get(id);
+        let viewport = ctx.viewport.size();
+
+        if let Ok(Some(width)) = attribs.get_viewport_cells(WIDTH, viewport) {
+            constraints.make_width_tight(width);
+        }
 
-        if let Some(width @ 0..=i64::MAX) = attribs.get(WIDTH) {
-            constraints.make_width_tight(width as usize);
+        if let Ok(Some(height)) = attribs.get_viewport_cells(HEIGHT, viewport) {
+            constraints.make_height_tight(height);
         }
 
-        if let Some(height @ 0..=i64::MAX) = attribs.get(HEIGHT) {
-            constraints.make_height_tight(height as usize);
+        if let Ok(Some(width)) = attribs.get_viewport_cells(MIN_WIDTH, viewport) {
+            constraints.min_width = width;
         }
 
-        if let Some(width @ 0..=i64::MAX) = attribs.get(MIN_WIDTH) {
-            constraints.min_width = width as usize;
+        if let Ok(Some(height)) = attribs.get_viewport_cells(MIN_HEIGHT, viewport) {
+            constraints.min_height = height;
         }
 
-        if let Some(height @ 0..=i64::MAX) = attribs.get(MIN_HEIGHT) {
-            constraints.min_height = height as usize;
+        if let Ok(Some(width)) = attribs.get_viewport_cells(MAX_WIDTH, viewport) {
+            constraints.set_max_width(width);
         }
 
-        if let Some(width @ 0..=i64::MAX) = attribs.get(MAX_WIDTH) {
-            constraints.set_max_width(width as usize);
+        if let Ok(Some(height)) = attribs.get_viewport_cells(MAX_HEIGHT, viewport) {
+            constraints.set_max_height(height);
         }
 
-        if let Some(height @ 0..=i64::MAX) = attribs.get(MAX_HEIGHT) {
-            constraints.set_max_height(height as usize);
+        // The max size takes precedence over both `width` and `min_width`
+        constraints.min_width = constraints.min_width.min(constraints.max_width());
+        constraints.min_height = constraints.min_height.min(constraints.max_height());
+
+        if let Some(ratio) = aspect_ratio(attribs) {
+            apply_aspect_ratio(&mut constraints, ratio);
         }
 
+        // Only the layout attributes are part of the constraints,
+        // so any other attribute can change without a new layout.
+        if let Some(cache) = self.cache {
+            if cache.constraints == constraints && cache.viewport == viewport {
+                return cache.size;
+            }
+        }
+
+        self.text.clear();
+        let mut ctx = ctx.with_text(self.text.new_session());
         children.for_each(|child, children| {
-            size = child.layout(children, constraints, ctx);
+            size = child.layout(children, constraints, &mut ctx);
             ControlFlow::Break(())
         });
 
-        size.width = size.width.max(constraints.min_width);
-        size.height = size.height.max(constraints.min_height);
+        size.width = size.width.clamp(constraints.min_width, constraints.max_width());
+        size.height = size.height.clamp(constraints.min_height, constraints.max_height());
+
+        self.cache = Some(LayoutCache {
+            constraints,
+            viewport,
+            size,
+        });
 
         size
     }
 
+    fn invalidate_layout(&mut self) {
+        self.cache = None;
+    }
+
     fn position<'bp>(
         &mut self,
         mut children: PositionChildren<'_, '_, 'bp>,
@@ -68,12 +177,113 @@ impl Widget for Container {
             ControlFlow::Break(())
         });
     }
+
+    fn paint<'bp>(
+        &mut self,
+        mut children: PaintChildren<'_, '_, 'bp>,
+        id: WidgetId,
+        attribute_storage: &AttributeStorage<'bp>,
+        mut ctx: PaintCtx<'_, SizePos>,
+        _text: &mut StringSession<'_>,
+    ) {
+        let attributes = attribute_storage.get(id);
+        if attributes.get_hex(BACKGROUND).is_some() {
+            for y in 0..ctx.local_size.height as u16 {
+                for x in 0..ctx.local_size.width as u16 {
+                    let pos = LocalPos::new(x, y);
+                    ctx.place_glyph(' ', pos);
+                    ctx.set_attributes(attributes, pos);
+                }
+            }
+        }
+
+        children.for_each(|child, children| {
+            let ctx = ctx.to_unsized();
+            child.paint(children, ctx, &mut self.text.new_session(), attribute_storage);
+            ControlFlow::Break(())
+        });
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use std::cell::Cell;
+    use std::ops::ControlFlow;
+
+    use anathema::Hex;
+    use anathema_geometry::Size;
+    use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
+    use anathema_widgets::paint::CellAttributes;
+    use anathema_widgets::{AttributeStorage, LayoutChildren, PositionChildren, Widget, WidgetId};
+
     use crate::testing::TestRunner;
 
+    thread_local! {
+        static LAYOUTS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Default)]
+    struct Counter;
+
+    impl Widget for Counter {
+        fn layout<'bp>(
+            &mut self,
+            _: LayoutChildren<'_, '_, 'bp>,
+            _: Constraints,
+            _: WidgetId,
+            _: &mut LayoutCtx<'_, '_, 'bp>,
+        ) -> Size {
+            LAYOUTS.with(|layouts| layouts.set(layouts.get() + 1));
+            Size::new(1, 1)
+        }
+
+        fn position<'bp>(
+            &mut self,
+            _: PositionChildren<'_, '_, 'bp>,
+            _: WidgetId,
+            _: &AttributeStorage<'bp>,
+            _: PositionCtx,
+        ) {
+        }
+    }
+
+    // Lays out the children top to bottom, handing the
+    // remaining constraints on to the next child
+    #[derive(Default)]
+    struct Sequence {
+        constraints: Vec<Constraints>,
+    }
+
+    impl Widget for Sequence {
+        fn layout<'bp>(
+            &mut self,
+            mut children: LayoutChildren<'_, '_, 'bp>,
+            mut constraints: Constraints,
+            _: WidgetId,
+            ctx: &mut LayoutCtx<'_, '_, 'bp>,
+        ) -> Size {
+            let mut size = Size::ZERO;
+            children.for_each(|child, children| {
+                self.constraints.push(constraints);
+                let layout = child.layout_with_remaining(children, constraints, ctx);
+                constraints = layout.remaining_vertical();
+                size.width = size.width.max(layout.size.width);
+                size.height += layout.size.height;
+                ControlFlow::Continue(())
+            });
+            size
+        }
+
+        fn position<'bp>(
+            &mut self,
+            _: PositionChildren<'_, '_, 'bp>,
+            _: WidgetId,
+            _: &AttributeStorage<'bp>,
+            _: PositionCtx,
+        ) {
+        }
+    }
+
     #[test]
     fn container() {
         let tpl = "
@@ -90,4 +300,310 @@ mod test {
 
         TestRunner::new(tpl, (6, 2)).instance().render_assert(expected);
     }
+
+    #[test]
+    fn viewport_width() {
+        // The viewport is 20 wide, including the border of the test runner
+        let tpl = "
+            container [width: '50vw']
+                border
+                    text 'a'
+        ";
+
+        let expected = "
+            ╔══════════════════╗
+            ║┌────────┐        ║
+            ║│a       │        ║
+            ║└────────┘        ║
+            ╚══════════════════╝
+        ";
+
+        TestRunner::new(tpl, (18, 3)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn max_width_overrides_width() {
+        let tpl = "
+            container [width: 10, max_width: 5]
+                text 'abcdefgh'
+        ";
+
+        let expected = "
+            ╔════════════╗
+            ║abcde       ║
+            ║fgh         ║
+            ╚════════════╝
+        ";
+
+        TestRunner::new(tpl, (12, 2)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn max_width_overrides_min_width() {
+        let tpl = "
+            container [min_width: 8, max_width: 4]
+                text 'a'
+        ";
+
+        let mut size = Size::ZERO;
+        TestRunner::new(tpl, (12, 1))
+            .instance()
+            .layout()
+            .with_widget(|mut query| {
+                query.by_tag("container").first(|el, _| size = el.size());
+            });
+        assert_eq!(size.width, 4);
+    }
+
+    #[test]
+    fn skip_layout_on_style_change() {
+        let tpl = "
+            container [color: 'red']
+                counter
+        ";
+
+        let mut runner = TestRunner::new(tpl, (6, 2));
+        runner.register::<Counter>("counter");
+        let mut instance = runner.instance();
+
+        instance.layout();
+        assert_eq!(LAYOUTS.with(Cell::get), 1);
+
+        // A change of style keeps the cached layout
+        instance
+            .with_widget(|mut query| {
+                query
+                    .by_tag("container")
+                    .first(|_, attribs| attribs.set("color", "blue"))
+            })
+            .layout();
+        assert_eq!(LAYOUTS.with(Cell::get), 1);
+
+        // ... but a change of size does not
+        instance
+            .with_widget(|mut query| query.by_tag("container").first(|_, attribs| attribs.set("width", 3)))
+            .layout();
+        assert_eq!(LAYOUTS.with(Cell::get), 2);
+    }
+
+    #[test]
+    fn keep_layout_and_text_across_frames() {
+        let tpl = "
+            container [color: 'red']
+                vstack
+                    counter
+                    text 'abc'
+        ";
+
+        let mut runner = TestRunner::new(tpl, (6, 2));
+        runner.register::<Counter>("counter");
+        let mut instance = runner.instance();
+
+        // Every frame clears the text storage of the frame
+        instance.render_assert(
+            "
+            ╔══════╗
+            ║      ║
+            ║abc   ║
+            ╚══════╝
+        ",
+        );
+        instance.with_widget(|mut query| {
+            query
+                .by_tag("container")
+                .first(|_, attribs| attribs.set("color", "blue"))
+        });
+        instance.render_assert(
+            "
+            ╔══════╗
+            ║      ║
+            ║abc   ║
+            ╚══════╝
+        ",
+        );
+        assert_eq!(LAYOUTS.with(Cell::get), 1);
+
+        // The text is painted from the cached layout
+        let canvas = instance.render_to_canvas();
+        assert_eq!(LAYOUTS.with(Cell::get), 1);
+        assert_eq!(canvas.to_string_grid().lines().nth(2), Some("║abc   ║"));
+
+        // A change to a child is a change to the layout of the container
+        instance
+            .with_widget(|mut query| query.by_tag("counter").first(|_, attribs| attribs.set("width", 2)))
+            .layout();
+        assert_eq!(LAYOUTS.with(Cell::get), 2);
+    }
+
+    #[test]
+    fn static_subtree_layout_once() {
+        let tpl = "
+            sequence [height: 2]
+                counter
+                counter
+        ";
+
+        let mut runner = TestRunner::new(tpl, (6, 2));
+        runner.register::<Counter>("counter");
+        runner.register::<Sequence>("sequence");
+        let mut instance = runner.instance();
+
+        instance.layout().layout().layout();
+        assert_eq!(LAYOUTS.with(Cell::get), 2);
+
+        // Changing an attribute lays out the element again,
+        // but the children keep their layout as long as
+        // their constraints are the same
+        instance
+            .with_widget(|mut query| query.by_tag("sequence").first(|_, attribs| attribs.set("height", 1)))
+            .layout();
+        assert_eq!(LAYOUTS.with(Cell::get), 2);
+
+        instance
+            .with_widget(|mut query| query.by_tag("counter").first(|_, attribs| attribs.set("width", 1)))
+            .layout();
+        assert_eq!(LAYOUTS.with(Cell::get), 3);
+    }
+
+    #[test]
+    fn static_subtree_across_frames() {
+        let tpl = "
+            vstack
+                counter
+                text 'abc'
+        ";
+
+        let expected = "
+            ╔══════╗
+            ║      ║
+            ║abc   ║
+            ╚══════╝
+        ";
+
+        let mut runner = TestRunner::new(tpl, (6, 2));
+        runner.register::<Counter>("counter");
+        let mut instance = runner.instance();
+
+        // Every frame clears the text storage of the frame
+        instance.render_assert(expected).render_assert(expected);
+        assert_eq!(LAYOUTS.with(Cell::get), 1);
+
+        // The text is painted from the cached layout
+        let canvas = instance.render_to_canvas();
+        assert_eq!(LAYOUTS.with(Cell::get), 1);
+        assert_eq!(canvas.to_string_grid().lines().nth(2), Some("║abc   ║"));
+
+        // A change to a child lays out the subtree again
+        instance
+            .with_widget(|mut query| query.by_tag("counter").first(|_, attribs| attribs.set("width", 2)))
+            .render_assert(expected);
+        assert_eq!(LAYOUTS.with(Cell::get), 2);
+    }
+
+    #[test]
+    fn remaining_constraints() {
+        let tpl = "
+            sequence
+                text 'a\nb'
+                text 'c'
+        ";
+
+        let mut runner = TestRunner::new(tpl, (6, 5));
+        runner.register::<Sequence>("sequence");
+        let mut constraints = vec![];
+        runner.instance().layout().with_widget(|mut query| {
+            query
+                .by_tag("sequence")
+                .first(|el, _| constraints = el.to::<Sequence>().constraints.clone());
+        });
+
+        assert_eq!(constraints.len(), 2);
+        assert_eq!(constraints[0].max_height(), 5);
+        assert_eq!(constraints[1].max_height(), 3);
+        assert_eq!(constraints[1].max_width(), constraints[0].max_width());
+    }
+
+    #[test]
+    fn background() {
+        let tpl = "
+            container [background: #ff0000, width: 3, height: 2]
+                text 'a'
+        ";
+
+        let canvas = TestRunner::new(tpl, (4, 3)).instance().render_to_canvas();
+
+        let expected = "
+╔════╗
+║a   ║
+║    ║
+║    ║
+╚════╝";
+        assert_eq!(canvas.to_string_grid(), expected.trim());
+
+        let red = Hex::from((255, 0, 0));
+        let (c, attribs) = canvas.get_ref((1, 1)).unwrap();
+        assert_eq!(c, "a");
+        assert_eq!(attribs.get_hex("background"), Some(red));
+
+        for pos in [(2, 1), (3, 1), (1, 2), (3, 2)] {
+            let (c, attribs) = canvas.get_ref(pos).unwrap();
+            assert_eq!(c, " ");
+            assert_eq!(attribs.get_hex("background"), Some(red));
+        }
+
+        // Outside of the container
+        let background = canvas
+            .get_ref((4, 1))
+            .and_then(|(_, attribs)| attribs.get_hex("background"));
+        assert!(background.is_none());
+    }
+
+    fn container_size(tpl: &str, size: (u16, u16)) -> Size {
+        let mut container = Size::ZERO;
+        TestRunner::new(tpl, size).instance().layout().with_widget(|mut query| {
+            query.by_tag("container").first(|el, _| container = el.size());
+        });
+        container
+    }
+
+    #[test]
+    fn aspect_ratio_in_square() {
+        let tpl = "
+            container [aspect_ratio: '16:9']
+                text 'a'
+        ";
+
+        assert_eq!(container_size(tpl, (16, 16)), Size::new(16, 9));
+        assert_eq!(container_size(tpl, (9, 9)), Size::new(9, 5));
+    }
+
+    #[test]
+    fn aspect_ratio_height_wins_when_width_does_not_fit() {
+        let tpl = "
+            container [aspect_ratio: '16:9', height: 2]
+                text 'a'
+        ";
+
+        assert_eq!(container_size(tpl, (16, 16)), Size::new(4, 2));
+    }
+
+    #[test]
+    fn aspect_ratio_number() {
+        let tpl = "
+            container [aspect_ratio: 2.0]
+                text 'a'
+        ";
+
+        assert_eq!(container_size(tpl, (10, 10)), Size::new(10, 5));
+    }
+
+    #[test]
+    fn aspect_ratio_invalid() {
+        let tpl = "
+            container [aspect_ratio: '16:0']
+                text 'a'
+        ";
+
+        assert_eq!(container_size(tpl, (10, 10)), Size::new(1, 1));
+    }
 }
@@ -0,0 +1,71 @@
+use anathema_geometry::Size;
+use anathema_widgets::layout::text::StringSession;
+use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
+use anathema_widgets::paint::{PaintCtx, SizePos};
+use anathema_widgets::{AttributeStorage, LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId};
+
+/// A placeholder widget that takes up no space and paints nothing.
+/// Any children are ignored.
+#[derive(Debug, Default)]
+pub struct Empty;
+
+impl Widget for Empty {
+    fn layout<'bp>(
+        &mut self,
+        _: LayoutChildren<'_, '_, 'bp>,
+        _: Constraints,
+        _: WidgetId,
+        _: &mut LayoutCtx<'_, '_, 'bp>,
+    ) -> Size {
+        Size::ZERO
+    }
+
+    fn paint<'bp>(
+        &mut self,
+        _: PaintChildren<'_, '_, 'bp>,
+        _: WidgetId,
+        _: &AttributeStorage<'bp>,
+        _: PaintCtx<'_, SizePos>,
+        _: &mut StringSession<'_>,
+    ) {
+        // The empty widget paints nothing
+    }
+
+    fn position<'bp>(
+        &mut self,
+        _: PositionChildren<'_, '_, 'bp>,
+        _: WidgetId,
+        _: &AttributeStorage<'bp>,
+        _: PositionCtx,
+    ) {
+        // The empty widget has nothing to position
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::TestRunner;
+
+    #[test]
+    fn empty_takes_no_space() {
+        let tpl = "
+            vstack
+                hstack
+                    text 'a'
+                    empty [background: 'red']
+                        text 'hidden'
+                    text 'b'
+                empty
+                text 'c'
+        ";
+
+        let expected = "
+            ╔═══╗
+            ║ab ║
+            ║c  ║
+            ╚═══╝
+        ";
+
+        TestRunner::new(tpl, (3, 2)).instance().render_assert(expected);
+    }
+}
@@ -65,6 +65,10 @@ impl Widget for Expand {
                         let Some(p) = ctx.place_glyphs(s, pos) else {
                             return ControlFlow::Break(());
                         };
+                        // The line is filled
+                        if p.y != pos.y {
+                            return ControlFlow::Break(());
+                        }
                         used_width += p.x - used_width;
                         match used_width >= ctx.local_size.width as u16 {
                             true => ControlFlow::Break(()),
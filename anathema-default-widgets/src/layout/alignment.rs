@@ -1,4 +1,5 @@
 use anathema::CommonVal;
+use anathema_geometry::{Pos, Size};
 
 pub const ALIGNMENT: &str = "alignment";
 
@@ -17,6 +18,28 @@ pub enum Alignment {
     Centre,
 }
 
+impl Alignment {
+    /// The offset of the inner size when aligned within the outer size.
+    pub(crate) fn offset(&self, outer: Size, inner: Size) -> Pos {
+        let width = outer.width as i32;
+        let height = outer.height as i32;
+        let inner_width = inner.width as i32;
+        let inner_height = inner.height as i32;
+
+        match self {
+            Alignment::TopLeft => Pos::ZERO,
+            Alignment::Top => Pos::new(width / 2 - inner_width / 2, 0),
+            Alignment::TopRight => Pos::new(width - inner_width, 0),
+            Alignment::Right => Pos::new(width - inner_width, height / 2 - inner_height / 2),
+            Alignment::BottomRight => Pos::new(width - inner_width, height - inner_height),
+            Alignment::Bottom => Pos::new(width / 2 - inner_width / 2, height - inner_height),
+            Alignment::BottomLeft => Pos::new(0, height - inner_height),
+            Alignment::Left => Pos::new(0, height / 2 - inner_height / 2),
+            Alignment::Centre => Pos::new(width / 2 - inner_width / 2, height / 2 - inner_height / 2),
+        }
+    }
+}
+
 impl TryFrom<CommonVal<'_>> for Alignment {
     type Error = ();
 
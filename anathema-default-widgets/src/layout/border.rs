@@ -72,7 +72,7 @@ impl BorderLayout {
             //         ...
 
             child_constraints.sub_max_width((border_size.left + border_size.right) as usize);
-            child_constraints.sub_max_height((border_size.top + border_size.bottom) as usize);
+            child_constraints.sub_max_height(border_size.height());
             let mut child_size = child.layout(children, child_constraints, ctx);
             child_size += border_size.as_size();
             size.width = child_size.width.max(size.width);
@@ -87,20 +87,34 @@ impl Many {
 
         let mut size = Size::ZERO;
 
-        children.for_each(|node, children| {
+        children.for_each(|node, mut children| {
             if ["spacer", "expand"].contains(&node.ident) {
                 return ControlFlow::Continue(());
             }
 
-            let widget_constraints = {
-                let mut constraints = self.used_size.to_constraints();
-                if self.unconstrained {
-                    match self.axis {
-                        Axis::Vertical => constraints.unbound_height(),
-                        Axis::Horizontal => constraints.unbound_width(),
+            let remaining = self.used_size.to_constraints();
+            let mut unbounded = remaining;
+            match self.axis {
+                Axis::Vertical => unbounded.unbound_height(),
+                Axis::Horizontal => unbounded.unbound_width(),
+            }
+
+            // A constrained child is only offered the remaining space
+            // if its unconstrained size would overflow it,
+            // e.g text that fits on one line is not wrapped
+            let widget_constraints = match self.unconstrained {
+                true => unbounded,
+                false => {
+                    let size = node.negotiate(children.reborrow(), unbounded, ctx);
+                    let fits = match self.axis {
+                        Axis::Vertical => size.height <= remaining.max_height(),
+                        Axis::Horizontal => size.width <= remaining.max_width(),
+                    };
+                    match fits {
+                        true => unbounded,
+                        false => remaining,
                     }
                 }
-                constraints
             };
 
             let widget_size = node.layout(children, widget_constraints, ctx);
@@ -6,9 +6,12 @@ mod alignment;
 mod border;
 mod canvas;
 mod container;
+mod empty;
 mod expand;
 mod layout;
+mod list;
 mod overflow;
+mod overlay;
 mod padding;
 mod position;
 mod spacer;
@@ -31,9 +34,12 @@ pub(crate) const LEFT: &str = "left";
 
 pub use alignment::Align;
 pub use border::Border;
-pub use canvas::{Canvas, CanvasAttribs};
+pub use canvas::{render_to_canvas, Blend, Canvas, CanvasAttribs, CanvasView, ResizeAnchor};
+pub use empty::Empty;
 pub use expand::Expand;
+pub use list::List;
 pub use overflow::Overflow;
+pub use overlay::Overlay;
 pub use padding::Padding;
 pub use position::Position;
 pub use stacks::{Column, HStack, Row, VStack};
@@ -44,6 +50,7 @@ pub fn register_default_widgets(factory: &mut Factory) {
     factory.register_default::<expand::Expand>("expand");
     factory.register_default::<canvas::Canvas>("canvas");
     factory.register_default::<container::Container>("container");
+    factory.register_default::<empty::Empty>("empty");
     factory.register_default::<padding::Padding>("padding");
     factory.register_default::<position::Position>("position");
     factory.register_default::<stacks::Column>("column");
@@ -55,5 +62,7 @@ pub fn register_default_widgets(factory: &mut Factory) {
     factory.register_default::<text::Span>("span");
     factory.register_default::<text::Text>("text");
     factory.register_default::<overflow::Overflow>("overflow");
+    factory.register_default::<list::List>("list");
+    factory.register_default::<overlay::Overlay>("overlay");
     factory.register_widget("border", border::make);
 }
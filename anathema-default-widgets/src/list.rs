@@ -0,0 +1,160 @@
+use std::ops::{ControlFlow, Range};
+
+use anathema_geometry::Size;
+use anathema_widgets::layout::text::StringSession;
+use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
+use anathema_widgets::paint::{PaintCtx, SizePos};
+use anathema_widgets::{AttributeStorage, LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId};
+
+use crate::HEIGHT;
+
+const OFFSET: &str = "offset";
+
+/// A vertical list that only lays out, positions and paints
+/// the children within its visible height.
+///
+/// The `offset` is the index of the first visible child.
+/// Children before the offset and after the visible window are skipped entirely.
+#[derive(Debug, Default)]
+pub struct List {
+    // Index range of the children that were laid out
+    visible: Range<usize>,
+}
+
+impl Widget for List {
+    fn layout<'bp>(
+        &mut self,
+        mut children: LayoutChildren<'_, '_, 'bp>,
+        mut constraints: Constraints,
+        id: WidgetId,
+        ctx: &mut LayoutCtx<'_, '_, 'bp>,
+    ) -> Size {
+        let attributes = ctx.attribs.get(id);
+        let offset = attributes.get_int(OFFSET).unwrap_or(0).max(0) as usize;
+
+        if let Some(height) = attributes.get(HEIGHT) {
+            constraints.make_height_tight(height);
+        }
+
+        let max_height = constraints.max_height();
+        let mut size = Size::ZERO;
+        let mut index = 0;
+
+        children.for_each(|child, children| {
+            if size.height >= max_height {
+                return ControlFlow::Break(());
+            }
+
+            if index >= offset {
+                let mut child_constraints = constraints;
+                child_constraints.sub_max_height(size.height);
+                let child_size = child.layout(children, child_constraints, ctx);
+                size.width = size.width.max(child_size.width);
+                size.height += child_size.height;
+            }
+
+            index += 1;
+            ControlFlow::Continue(())
+        });
+
+        self.visible = offset.min(index)..index;
+
+        size.width = size.width.min(constraints.max_width());
+        size.height = size.height.min(max_height);
+        size
+    }
+
+    fn position<'bp>(
+        &mut self,
+        mut children: PositionChildren<'_, '_, 'bp>,
+        _: WidgetId,
+        attribute_storage: &AttributeStorage<'bp>,
+        ctx: PositionCtx,
+    ) {
+        let mut pos = ctx.pos;
+        let mut index = 0;
+
+        children.for_each(|child, children| {
+            if index >= self.visible.end {
+                return ControlFlow::Break(());
+            }
+
+            if self.visible.contains(&index) {
+                child.position(children, pos, attribute_storage);
+                pos.y += child.size().height as i32;
+            }
+
+            index += 1;
+            ControlFlow::Continue(())
+        });
+    }
+
+    fn paint<'bp>(
+        &mut self,
+        mut children: PaintChildren<'_, '_, 'bp>,
+        _: WidgetId,
+        attribute_storage: &AttributeStorage<'bp>,
+        mut ctx: PaintCtx<'_, SizePos>,
+        text: &mut StringSession<'_>,
+    ) {
+        let region = ctx.create_region();
+        let mut index = 0;
+
+        children.for_each(|child, children| {
+            if index >= self.visible.end {
+                return ControlFlow::Break(());
+            }
+
+            if self.visible.contains(&index) {
+                ctx.set_clip_region(region);
+                let ctx = ctx.to_unsized();
+                child.paint(children, ctx, text, attribute_storage);
+            }
+
+            index += 1;
+            ControlFlow::Continue(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::TestRunner;
+
+    #[test]
+    fn only_visible_children() {
+        let mut tpl = String::from("list [offset: 10, height: 5]\n");
+        for i in 0..100 {
+            tpl.push_str(&format!("    text '{i}'\n"));
+        }
+
+        let expected = "
+            ╔══╗
+            ║10║
+            ║11║
+            ║12║
+            ║13║
+            ║14║
+            ╚══╝
+        ";
+
+        TestRunner::new(&tpl, (2, 5)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn offset_past_the_end() {
+        let tpl = "
+            list [offset: 5]
+                text 'a'
+                text 'b'
+        ";
+
+        let expected = "
+            ╔══╗
+            ║  ║
+            ╚══╝
+        ";
+
+        TestRunner::new(tpl, (2, 1)).instance().render_assert(expected);
+    }
+}
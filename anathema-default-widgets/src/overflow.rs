@@ -20,6 +20,7 @@ pub struct Overflow {
     inner_size: Size,
 
     direction: Direction,
+    overflowed: bool,
 }
 
 impl Overflow {
@@ -72,6 +73,12 @@ impl Overflow {
         self.offset
     }
 
+    /// Returns `true` if any of the children were painted outside of
+    /// the overflow during the last paint, e.g to show a scrollbar.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
     fn clamp(&mut self, children: Size, parent: Size) {
         if self.offset.x < 0 {
             self.offset.x = 0;
@@ -202,12 +209,14 @@ impl Widget for Overflow {
         text: &mut StringSession<'_>,
     ) {
         let region = ctx.create_region();
+        let overflow = ctx.track_overflow();
         children.for_each(|widget, children| {
             ctx.set_clip_region(region);
             let ctx = ctx.to_unsized();
             widget.paint(children, ctx, text, attribute_storage);
             ControlFlow::Continue(())
         });
+        self.overflowed = overflow.overflowed();
     }
 }
 
@@ -306,4 +315,29 @@ mod test {
             })
             .render_assert(expected_first);
     }
+
+    #[test]
+    fn overflowed() {
+        let tpl = "
+            overflow
+                for i in [0, 1, 2]
+                    text i
+        ";
+
+        let overflowed = |size: (u16, u16)| {
+            let mut overflowed = None;
+            let mut runner = TestRunner::new(tpl, size);
+            let mut instance = runner.instance();
+            instance.render_to_string();
+            instance.with_widget(|mut query| {
+                query
+                    .by_tag("overflow")
+                    .first(|el, _| overflowed = Some(el.to::<Overflow>().overflowed()));
+            });
+            overflowed.unwrap()
+        };
+
+        assert!(overflowed((1, 2)));
+        assert!(!overflowed((1, 3)));
+    }
 }
@@ -0,0 +1,187 @@
+use std::ops::ControlFlow;
+
+use anathema::Hex;
+use anathema_geometry::{LocalPos, Pos, Region, Size};
+use anathema_widgets::layout::text::StringSession;
+use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
+use anathema_widgets::paint::{CellAttributes, PaintCtx, SizePos};
+use anathema_widgets::{AttributeStorage, LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId};
+
+const FILL: &str = "fill";
+const DIM: &str = "dim";
+const BACKGROUND: &str = "background";
+
+// The style applied to every cell behind the overlay
+struct Dimmed {
+    background: Option<Hex>,
+}
+
+impl CellAttributes for Dimmed {
+    fn with_str(&self, _: &str, _: &mut dyn FnMut(&str)) {}
+
+    fn get_i64(&self, _: &str) -> Option<i64> {
+        None
+    }
+
+    fn get_hex(&self, key: &str) -> Option<Hex> {
+        match key {
+            BACKGROUND => self.background,
+            _ => None,
+        }
+    }
+
+    fn get_bool(&self, key: &str) -> bool {
+        key == DIM
+    }
+}
+
+/// A floating widget that covers the viewport, dims everything
+/// behind it and paints the child centered on top.
+///
+/// The cells behind the overlay can be filled with a glyph
+/// using the `fill` attribute, e.g `overlay [fill: '░']`,
+/// and given a colour using the `background` attribute.
+#[derive(Debug)]
+pub struct Overlay {
+    size: Size,
+    child: Region,
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Self {
+            size: Size::ZERO,
+            child: Region::ZERO,
+        }
+    }
+}
+
+impl Widget for Overlay {
+    fn floats(&self) -> bool {
+        true
+    }
+
+    fn layout<'bp>(
+        &mut self,
+        mut children: LayoutChildren<'_, '_, 'bp>,
+        _: Constraints,
+        _: WidgetId,
+        ctx: &mut LayoutCtx<'_, '_, 'bp>,
+    ) -> Size {
+        // The overlay covers the viewport regardless of the parent
+        let constraints = ctx.viewport.constraints();
+        self.size = ctx.viewport.size();
+
+        children.for_each(|child, children| {
+            let _ = child.layout(children, constraints, ctx);
+            ControlFlow::Break(())
+        });
+
+        self.size
+    }
+
+    fn position<'bp>(
+        &mut self,
+        mut children: PositionChildren<'_, '_, 'bp>,
+        _: WidgetId,
+        attribute_storage: &AttributeStorage<'bp>,
+        _: PositionCtx,
+    ) {
+        self.child = Region::ZERO;
+
+        children.for_each(|child, children| {
+            let size = child.size();
+            let x = self.size.width.saturating_sub(size.width) / 2;
+            let y = self.size.height.saturating_sub(size.height) / 2;
+            let pos = Pos::new(x as i32, y as i32);
+
+            child.position(children, pos, attribute_storage);
+            self.child = Region::from((pos, size));
+            ControlFlow::Break(())
+        });
+    }
+
+    fn paint<'bp>(
+        &mut self,
+        mut children: PaintChildren<'_, '_, 'bp>,
+        id: WidgetId,
+        attribute_storage: &AttributeStorage<'bp>,
+        mut ctx: PaintCtx<'_, SizePos>,
+        text: &mut StringSession<'_>,
+    ) {
+        let attributes = attribute_storage.get(id);
+        let fill = attributes.get_ref::<&str>(FILL).and_then(|fill| fill.chars().next());
+        let dimmed = Dimmed {
+            background: attributes.get_hex(BACKGROUND),
+        };
+
+        // The overlay is painted from the origin of the viewport,
+        // not the position of the parent.
+        let mut unsized_ctx = ctx.to_unsized();
+        unsized_ctx.clip = None;
+        let mut background = unsized_ctx.into_sized(self.size, Pos::ZERO);
+
+        for y in 0..self.size.height as u16 {
+            for x in 0..self.size.width as u16 {
+                if self.child.contains(Pos::new(x as i32, y as i32)) {
+                    continue;
+                }
+
+                let pos = LocalPos::new(x, y);
+                if let Some(c) = fill {
+                    background.place_glyph(c, pos);
+                }
+                background.set_attributes(&dimmed, pos);
+            }
+        }
+
+        children.for_each(|child, children| {
+            let mut ctx = ctx.to_unsized();
+            ctx.clip = None;
+            child.paint(children, ctx, text, attribute_storage);
+            ControlFlow::Break(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::TestRunner;
+    use crate::CanvasAttribs;
+    use anathema_widgets::paint::CellAttributes;
+
+    fn is_dimmed(attribs: &CanvasAttribs) -> bool {
+        attribs.get_bool("dim")
+    }
+
+    #[test]
+    fn dim_background_and_center_child() {
+        let tpl = "
+            zstack
+                text 'abcdef'
+                overlay
+                    text 'hi'
+        ";
+
+        let mut runner = TestRunner::new(tpl, (6, 3));
+        let mut canvas = runner.instance().render_to_canvas();
+
+        let expected = "
+╔══════╗
+║abcdef║
+║  hi  ║
+║      ║
+╚══════╝";
+        assert_eq!(canvas.to_string_grid(), expected.trim());
+
+        // Everything behind the overlay is dimmed...
+        assert!(is_dimmed(canvas.get((0, 0)).unwrap().1));
+        assert!(is_dimmed(canvas.get((1, 1)).unwrap().1));
+        assert!(is_dimmed(canvas.get((2, 2)).unwrap().1));
+
+        // ... but not the child
+        let (c, attribs) = canvas.get((3, 2)).unwrap();
+        assert_eq!(*c, "h");
+        assert!(!is_dimmed(attribs));
+    }
+}
@@ -2,11 +2,17 @@ use std::ops::ControlFlow;
 
 use anathema_geometry::Size;
 use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
-use anathema_widgets::{AttributeStorage, LayoutChildren, PositionChildren, Widget, WidgetId};
+use anathema_widgets::units::{parse_relative_cells, UnitError};
+use anathema_widgets::{AttributeStorage, Attributes, LayoutChildren, PositionChildren, Widget, WidgetId};
 
+use crate::layout::alignment::{Alignment, ALIGNMENT};
 use crate::{BOTTOM, LEFT, RIGHT, TOP};
 
 const PADDING: &str = "padding";
+const PADDING_TOP: &str = "padding_top";
+const PADDING_RIGHT: &str = "padding_right";
+const PADDING_BOTTOM: &str = "padding_bottom";
+const PADDING_LEFT: &str = "padding_left";
 
 #[derive(Default)]
 struct PaddingValues {
@@ -19,14 +25,84 @@ struct PaddingValues {
 impl PaddingValues {
     fn size(&self) -> Size {
         Size {
-            height: (self.top + self.bottom) as usize,
-            width: (self.left + self.right) as usize,
+            height: self.top as usize + self.bottom as usize,
+            width: self.left as usize + self.right as usize,
         }
     }
 }
 
+/// Padding around a single child.
+///
+/// With an `alignment`, e.g `"centre"`, a child smaller than the
+/// space inside the padding is aligned within that space.
 #[derive(Default)]
-pub struct Padding(PaddingValues);
+pub struct Padding {
+    values: PaddingValues,
+    clamped: bool,
+}
+
+impl Padding {
+    // The first of the keys that is set is the padding of the edge.
+    // The padding is either a number of cells or a percentage
+    // of the constraint, e.g `"10%"`, rounded down.
+    fn edge<'bp>(attributes: &Attributes<'bp>, keys: [&'bp str; 2], total: usize) -> Option<u16> {
+        keys.into_iter().find_map(|key| match attributes.get_int(key) {
+            Some(cells) => u16::try_from(cells).ok(),
+            None => {
+                let cells = attributes.try_unit(key, |value| Self::cells(value, total))?;
+                cells.ok()
+            }
+        })
+    }
+
+    fn cells(value: &str, total: usize) -> Result<u16, UnitError> {
+        parse_relative_cells(value, total).map(|cells| cells.min(u16::MAX as usize) as u16)
+    }
+
+    // The `padding` shorthand as top, right, bottom and left.
+    // The value is either a single value for all edges,
+    // two values (`"1 2"`, vertical and horizontal)
+    // or four values (`"1 2 3 4"`, top, right, bottom and left).
+    // Anything else is treated as no padding.
+    fn shorthand(attributes: &Attributes<'_>, width: usize, height: usize) -> [u16; 4] {
+        if let Some(cells) = attributes.get_int(PADDING) {
+            return [u16::try_from(cells).unwrap_or(0); 4];
+        }
+
+        attributes
+            .try_unit(PADDING, |value| {
+                let tokens = value.split_whitespace().collect::<Vec<_>>();
+                match tokens[..] {
+                    [all] => Ok([
+                        Self::cells(all, height)?,
+                        Self::cells(all, width)?,
+                        Self::cells(all, height)?,
+                        Self::cells(all, width)?,
+                    ]),
+                    [vertical, horizontal] => {
+                        let vertical = Self::cells(vertical, height)?;
+                        let horizontal = Self::cells(horizontal, width)?;
+                        Ok([vertical, horizontal, vertical, horizontal])
+                    }
+                    [top, right, bottom, left] => Ok([
+                        Self::cells(top, height)?,
+                        Self::cells(right, width)?,
+                        Self::cells(bottom, height)?,
+                        Self::cells(left, width)?,
+                    ]),
+                    _ => Err(UnitError::InvalidNumber(value.into())),
+                }
+            })
+            .and_then(Result::ok)
+            .unwrap_or([0; 4])
+    }
+
+    /// Returns `true` if the padding exceeded the available space
+    /// during the last layout, leaving less room for the child than requested.
+    pub fn is_clamped(&self) -> bool {
+        self.clamped
+    }
+}
 
 impl Widget for Padding {
     fn layout<'bp>(
@@ -38,19 +114,27 @@ impl Widget for Padding {
     ) -> Size {
         let attributes = ctx.attribs.get(id);
         let mut size = Size::ZERO;
-        let padding = attributes.get(PADDING).unwrap_or(0);
-
-        self.0.top = attributes.get(TOP).unwrap_or(padding);
-        self.0.right = attributes.get(RIGHT).unwrap_or(padding);
-        self.0.bottom = attributes.get(BOTTOM).unwrap_or(padding);
-        self.0.left = attributes.get(LEFT).unwrap_or(padding);
-
-        let padding_size = self.0.size();
+        // `top`, `right`, `bottom` and `left` take precedence
+        // over `padding_top` etc, which in turn take precedence
+        // over the `padding` shorthand.
+        // Percentages are relative to the constraints, and an unbounded
+        // constraint has no size to take a percentage of.
+        let width = if constraints.is_width_unbounded() { 0 } else { constraints.max_width() };
+        let height = if constraints.is_height_unbounded() { 0 } else { constraints.max_height() };
+        let [top, right, bottom, left] = Self::shorthand(attributes, width, height);
+        self.values.top = Self::edge(attributes, [TOP, PADDING_TOP], height).unwrap_or(top);
+        self.values.right = Self::edge(attributes, [RIGHT, PADDING_RIGHT], width).unwrap_or(right);
+        self.values.bottom = Self::edge(attributes, [BOTTOM, PADDING_BOTTOM], height).unwrap_or(bottom);
+        self.values.left = Self::edge(attributes, [LEFT, PADDING_LEFT], width).unwrap_or(left);
+
+        let padding_size = self.values.size();
+        self.clamped = false;
 
         children.for_each(|child, children| {
             let mut child_constraints = constraints;
-            child_constraints.sub_max_width(padding_size.width);
-            child_constraints.sub_max_height(padding_size.height);
+            let width = child_constraints.sub_max_width_checked(padding_size.width);
+            let height = child_constraints.sub_max_height_checked(padding_size.height);
+            self.clamped = width < padding_size.width || height < padding_size.height;
             let mut child_size = child.layout(children, child_constraints, ctx);
             child_size += padding_size;
             size.width = child_size.width.max(size.width);
@@ -68,13 +152,21 @@ impl Widget for Padding {
     fn position<'bp>(
         &mut self,
         mut children: PositionChildren<'_, '_, 'bp>,
-        _: WidgetId,
+        id: WidgetId,
         attribute_storage: &AttributeStorage<'bp>,
         mut ctx: PositionCtx,
     ) {
+        let alignment: Alignment = attribute_storage.get(id).get(ALIGNMENT).unwrap_or_default();
+        // The space left for the child once the padding is removed
+        let content_size = Size::new(
+            ctx.inner_size.width.saturating_sub(self.values.size().width),
+            ctx.inner_size.height.saturating_sub(self.values.size().height),
+        );
+
         children.for_each(|child, children| {
-            ctx.pos.y += self.0.top as i32;
-            ctx.pos.x += self.0.left as i32;
+            ctx.pos.y += self.values.top as i32;
+            ctx.pos.x += self.values.left as i32;
+            ctx.pos += alignment.offset(content_size, child.size());
 
             child.position(children, ctx.pos, attribute_storage);
             ControlFlow::Break(())
@@ -93,10 +185,10 @@ impl Widget for Padding {
         children.for_each(|child, children| {
             let mut ctx = ctx.to_unsized();
             if let Some(clip) = ctx.clip.as_mut() {
-                clip.from.x += self.0.left as i32;
-                clip.from.y += self.0.top as i32;
-                clip.to.x -= self.0.right as i32;
-                clip.to.y -= self.0.bottom as i32;
+                clip.from.x += self.values.left as i32;
+                clip.from.y += self.values.top as i32;
+                clip.to.x -= self.values.right as i32;
+                clip.to.y -= self.values.bottom as i32;
             }
             child.paint(children, ctx, text, attribute_storage);
             ControlFlow::Break(())
@@ -110,9 +202,32 @@ impl Widget for Padding {
 
 #[cfg(test)]
 mod test {
+    use anathema_geometry::Size;
 
+    use super::Padding;
     use crate::testing::TestRunner;
 
+    #[test]
+    fn padding_alignment() {
+        let tpl = "
+            container [width: 7, height: 5]
+                padding [padding: 1, alignment: 'centre']
+                    text 'x'
+        ";
+
+        let expected = "
+            ╔═══════╗
+            ║       ║
+            ║       ║
+            ║   x   ║
+            ║       ║
+            ║       ║
+            ╚═══════╝
+        ";
+
+        TestRunner::new(tpl, (7, 5)).instance().render_assert(expected);
+    }
+
     #[test]
     fn padding_all() {
         let tpl = "
@@ -276,4 +391,231 @@ mod test {
 
         TestRunner::new(tpl, (4, 3)).instance().render_assert(expected);
     }
+
+    #[test]
+    fn padding_prefixed_edges() {
+        let tpl = "
+            padding [padding: 1, padding_left: 2]
+                text 'a'
+        ";
+
+        let expected = "
+            ╔════╗
+            ║    ║
+            ║  a ║
+            ║    ║
+            ╚════╝
+        ";
+
+        TestRunner::new(tpl, (4, 3)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn negative_padding() {
+        // A negative edge is ignored rather than wrapping around,
+        // falling back to the `padding` shorthand
+        let tpl = "
+            padding [padding: 1, padding_left: -2]
+                text 'a'
+        ";
+
+        let expected = "
+            ╔════╗
+            ║    ║
+            ║ a  ║
+            ║    ║
+            ╚════╝
+        ";
+
+        TestRunner::new(tpl, (4, 3)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn padding_exceeds_width() {
+        let tpl = "
+            padding [padding: 3]
+                text 'a'
+        ";
+
+        let expected = "
+            ╔════╗
+            ║    ║
+            ║    ║
+            ║    ║
+            ╚════╝
+        ";
+
+        let mut clamped = false;
+        TestRunner::new(tpl, (4, 3))
+            .instance()
+            .render_assert(expected)
+            .with_widget(|mut query| {
+                query
+                    .by_tag("padding")
+                    .first(|el, _| clamped = el.to::<Padding>().is_clamped());
+            });
+
+        assert!(clamped);
+    }
+
+    #[test]
+    fn padding_larger_than_container() {
+        let tpl = "
+            container [width: 4]
+                padding [left: 10]
+                    text 'a'
+        ";
+
+        let mut padding = Size::ZERO;
+        let mut text = None;
+        TestRunner::new(tpl, (8, 2))
+            .instance()
+            .layout()
+            .with_widget(|mut query| {
+                query.by_tag("padding").first(|el, _| padding = el.size());
+                query.by_tag("text").first(|el, _| text = Some(el.size()));
+            });
+
+        assert_eq!(padding.width, 4);
+        assert_eq!(text.unwrap().width, 0);
+    }
+
+    #[test]
+    fn padding_percentage() {
+        let tpl = "
+            padding [padding: '25%']
+                text 'a'
+        ";
+
+        let expected = "
+            ╔════════╗
+            ║        ║
+            ║  a     ║
+            ║        ║
+            ║        ║
+            ╚════════╝
+        ";
+
+        TestRunner::new(tpl, (8, 4)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn padding_mixed_percentage() {
+        let tpl = "
+            padding [left: '50%', top: 1]
+                text 'a'
+        ";
+
+        let expected = "
+            ╔════════╗
+            ║        ║
+            ║    a   ║
+            ╚════════╝
+        ";
+
+        TestRunner::new(tpl, (8, 2)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn padding_oversized_percentage() {
+        let tpl = "
+            padding [left: '200%', padding_right: '0%']
+                text 'a'
+        ";
+
+        let expected = "
+            ╔═══╗
+            ║   ║
+            ╚═══╝
+        ";
+
+        let mut runner = TestRunner::new(tpl, (3, 1));
+        runner.instance().render_assert(expected).with_widget(|mut query| {
+            query
+                .by_tag("padding")
+                .first(|el, _| assert!(el.to::<Padding>().is_clamped()));
+        });
+    }
+
+    #[test]
+    fn padding_shorthand() {
+        let tpl = "
+            padding [padding: '1 2 0 3']
+                text 'a'
+        ";
+
+        let expected = "
+            ╔════════╗
+            ║        ║
+            ║   a    ║
+            ║        ║
+            ╚════════╝
+        ";
+
+        TestRunner::new(tpl, (8, 3)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn padding_shorthand_vertical_horizontal() {
+        let tpl = "
+            border
+                padding [padding: '1 2']
+                    text 'a'
+        ";
+
+        let expected = "
+            ╔═════════╗
+            ║┌─────┐  ║
+            ║│     │  ║
+            ║│  a  │  ║
+            ║│     │  ║
+            ║└─────┘  ║
+            ╚═════════╝
+        ";
+
+        TestRunner::new(tpl, (9, 5)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn padding_shorthand_overridden() {
+        let tpl = "
+            border
+                padding [padding: '1 2', padding_left: 0, top: 0]
+                    text 'a'
+        ";
+
+        let expected = "
+            ╔═════════╗
+            ║┌───┐    ║
+            ║│a  │    ║
+            ║│   │    ║
+            ║└───┘    ║
+            ╚═════════╝
+        ";
+
+        TestRunner::new(tpl, (9, 4)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn padding_shorthand_malformed() {
+        let tpl = "
+            border
+                padding [padding: '1 2 3']
+                    border
+                        padding [padding: 'a b']
+                            text 'a'
+        ";
+
+        let expected = "
+            ╔═════════╗
+            ║┌───┐    ║
+            ║│┌─┐│    ║
+            ║││a││    ║
+            ║│└─┘│    ║
+            ║└───┘    ║
+            ╚═════════╝
+        ";
+
+        TestRunner::new(tpl, (9, 5)).instance().render_assert(expected);
+    }
 }
@@ -5,12 +5,16 @@ use anathema_geometry::{Pos, Size};
 use anathema_widgets::layout::text::StringSession;
 use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
 use anathema_widgets::paint::{PaintCtx, SizePos};
-use anathema_widgets::{AttributeStorage, LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId};
+use anathema_widgets::units::parse_relative_cells;
+use anathema_widgets::{
+    AttributeStorage, Attributes, LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId,
+};
 
 use crate::{BOTTOM, LEFT, RIGHT, TOP};
 
 const RELATIVE: &str = "relative";
 const ABSOLUTE: &str = "absolute";
+const CENTER: &str = "center";
 const PLACEMENT: &str = "placement";
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -32,6 +36,9 @@ pub enum Placement {
     Relative,
     /// Absolute position of a widget
     Absolute,
+    /// Center the widget in the space of the parent.
+    /// The edges (`left`, `top`, `right` and `bottom`) are ignored
+    Center,
 }
 
 impl TryFrom<CommonVal<'_>> for Placement {
@@ -42,6 +49,7 @@ impl TryFrom<CommonVal<'_>> for Placement {
             CommonVal::Str(wrap) => match wrap {
                 RELATIVE => Ok(Placement::Relative),
                 ABSOLUTE => Ok(Placement::Absolute),
+                CENTER => Ok(Placement::Center),
                 _ => Err(()),
             },
             _ => Err(()),
@@ -54,6 +62,49 @@ pub struct Position {
     horz_edge: HorzEdge,
     vert_edge: VertEdge,
     placement: Placement,
+    // The bounded space available to the position,
+    // used to resolve percentages
+    available: Size,
+}
+
+impl Position {
+    // The edges are read again once the size is known,
+    // so `left`, `right`, `top` and `bottom` can reference
+    // the `width` and `height` of the widget, e.g `left: width / 2`.
+    //
+    // An edge can also be a percentage of the space available to the
+    // position, e.g `left: "25%"`.
+    fn set_edges(&mut self, attribs: &Attributes<'_>, size: Size) {
+        let available = self.available;
+        let edge = |key, total| {
+            attribs
+                .get_int_sized(key, size)
+                .map(|value| value.clamp(0, u32::MAX.into()) as u32)
+                .or_else(|| {
+                    Some(
+                        attribs
+                            .try_unit(key, |value| parse_relative_cells(value, total))?
+                            .ok()? as u32,
+                    )
+                })
+        };
+
+        self.horz_edge = match edge(LEFT, available.width) {
+            Some(left) => HorzEdge::Left(left),
+            None => match edge(RIGHT, available.width) {
+                Some(right) => HorzEdge::Right(right),
+                None => HorzEdge::Left(0),
+            },
+        };
+
+        self.vert_edge = match edge(TOP, available.height) {
+            Some(top) => VertEdge::Top(top),
+            None => match edge(BOTTOM, available.height) {
+                Some(bottom) => VertEdge::Bottom(bottom),
+                None => VertEdge::Top(0),
+            },
+        };
+    }
 }
 
 impl Default for Position {
@@ -62,6 +113,7 @@ impl Default for Position {
             horz_edge: HorzEdge::Left(0),
             vert_edge: VertEdge::Top(0),
             placement: Placement::Relative,
+            available: Size::ZERO,
         }
     }
 }
@@ -81,22 +133,6 @@ impl Widget for Position {
         let attribs = ctx.attribs.get(id);
         self.placement = attribs.get(PLACEMENT).unwrap_or_default();
 
-        self.horz_edge = match attribs.get_int(LEFT) {
-            Some(left) => HorzEdge::Left(left as u32),
-            None => match attribs.get_int(RIGHT) {
-                Some(right) => HorzEdge::Right(right as u32),
-                None => HorzEdge::Left(0),
-            },
-        };
-
-        self.vert_edge = match attribs.get_int(TOP) {
-            Some(top) => VertEdge::Top(top as u32),
-            None => match attribs.get_int(BOTTOM) {
-                Some(bottom) => VertEdge::Bottom(bottom as u32),
-                None => VertEdge::Top(0),
-            },
-        };
-
         // Relative:
         // Position relative to parent means calculating a new constraint
         // based of the position of the top, left - the size
@@ -109,10 +145,16 @@ impl Widget for Position {
         // Has no constraints
 
         let constraints = match self.placement {
-            Placement::Relative => constraints,
+            Placement::Relative | Placement::Center => constraints,
             Placement::Absolute => ctx.viewport.constraints(),
         };
 
+        self.available = Size::new(
+            if constraints.is_width_unbounded() { 0 } else { constraints.max_width() },
+            if constraints.is_height_unbounded() { 0 } else { constraints.max_height() },
+        );
+        self.set_edges(attribs, Size::ZERO);
+
         let mut size = Size::ZERO;
 
         children.for_each(|child, children| {
@@ -120,14 +162,23 @@ impl Widget for Position {
             ControlFlow::Break(())
         });
 
+        // Take up all the available space so the
+        // child can be centred in it
+        if let Placement::Center = self.placement {
+            return Size::new(
+                if constraints.is_width_unbounded() { size.width } else { constraints.max_width() },
+                if constraints.is_height_unbounded() { size.height } else { constraints.max_height() },
+            );
+        }
+
         size.width = match self.horz_edge {
             HorzEdge::Left(left) => size.width + left as usize,
-            HorzEdge::Right(right) => constraints.max_width() - right as usize,
+            HorzEdge::Right(right) => constraints.max_width().saturating_sub(right as usize),
         };
 
         size.height = match self.vert_edge {
             VertEdge::Top(top) => size.height + top as usize,
-            VertEdge::Bottom(bottom) => constraints.max_height() - bottom as usize,
+            VertEdge::Bottom(bottom) => constraints.max_height().saturating_sub(bottom as usize),
         };
 
         size
@@ -136,28 +187,50 @@ impl Widget for Position {
     fn position<'bp>(
         &mut self,
         mut children: PositionChildren<'_, '_, 'bp>,
-        _: WidgetId,
+        id: WidgetId,
         attribute_storage: &AttributeStorage<'bp>,
         mut ctx: PositionCtx,
     ) {
+        self.set_edges(attribute_storage.get(id), ctx.inner_size);
+
         if let Placement::Absolute = self.placement {
             ctx.pos = Pos::ZERO;
         }
 
+        if let Placement::Center = self.placement {
+            children.for_each(|child, children| {
+                let x = ctx.inner_size.width.saturating_sub(child.size().width) / 2;
+                let y = ctx.inner_size.height.saturating_sub(child.size().height) / 2;
+                ctx.pos.x += x as i32;
+                ctx.pos.y += y as i32;
+                child.position(children, ctx.pos, attribute_storage);
+                ControlFlow::Break(())
+            });
+            return;
+        }
+
         children.for_each(|child, children| {
             match self.horz_edge {
                 HorzEdge::Left(left) => ctx.pos.x += left as i32,
                 HorzEdge::Right(right) => {
-                    let offset = ctx.inner_size.width - child.size().width - right as usize;
+                    let offset = ctx
+                        .inner_size
+                        .width
+                        .saturating_sub(child.size().width)
+                        .saturating_sub(right as usize);
                     ctx.pos.x = offset as i32;
                 }
             }
 
             match self.vert_edge {
                 VertEdge::Top(top) => ctx.pos.y += top as i32,
-                VertEdge::Bottom(right) => {
-                    let offset = ctx.inner_size.width - child.size().width - right as usize;
-                    ctx.pos.x = offset as i32;
+                VertEdge::Bottom(bottom) => {
+                    let offset = ctx
+                        .inner_size
+                        .height
+                        .saturating_sub(child.size().height)
+                        .saturating_sub(bottom as usize);
+                    ctx.pos.y = offset as i32;
                 }
             }
             child.position(children, ctx.pos, attribute_storage);
@@ -181,3 +254,181 @@ impl Widget for Position {
         });
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::testing::TestRunner;
+
+    #[test]
+    fn left_from_width() {
+        let tpl = "
+            position [left: width / 2]
+                text '1234'
+        ";
+
+        // Floating widgets are positioned from the origin
+        // of the test runner, not the inside of the border.
+        let expected = "
+            ╔═1234═╗
+            ║      ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 1)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn left_percentage() {
+        let tpl = "
+            container [width: 8]
+                position [left: '50%']
+                    text '1234'
+        ";
+
+        // The container starts inside the border,
+        // so the text is four columns from the left edge of the container
+        let expected = "
+            ╔════1234╗
+            ║        ║
+            ╚════════╝
+        ";
+
+        TestRunner::new(tpl, (8, 1)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn right_larger_than_width() {
+        let tpl = "
+            position [right: 20]
+                text '1234'
+        ";
+
+        // The position is clamped to the left edge
+        let expected = "
+            1234═══╗
+            ║      ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 1)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn right_from_extreme_width() {
+        let tpl = "
+            position [right: width * 9223372036854775807]
+                text '1234'
+        ";
+
+        // The edge saturates rather than overflowing,
+        // and is clamped to the left edge
+        let expected = "
+            1234═══╗
+            ║      ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 1)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn bottom_larger_than_height() {
+        let tpl = "
+            position [bottom: 20]
+                text '1234'
+        ";
+
+        // The position is clamped to the top edge
+        let expected = "
+            1234═══╗
+            ║      ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 1)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn bottom() {
+        let tpl = "
+            position [bottom: 0]
+                text 'ab'
+        ";
+
+        // Floating widgets are laid out in the space of the test runner,
+        // so the last row is the bottom of the border
+        let expected = "
+            ╔══════╗
+            ║      ║
+            ║      ║
+            ║      ║
+            ║      ║
+            ab═════╝
+        ";
+
+        TestRunner::new(tpl, (6, 4)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn center() {
+        let tpl = "
+            position [placement: 'center', left: 1]
+                text 'ab'
+        ";
+
+        // Floating widgets are laid out in the space of the test runner,
+        // including the border
+        let expected = "
+            ╔══════╗
+            ║      ║
+            ║  ab  ║
+            ║      ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 3)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn center_larger_child() {
+        let tpl = "
+            position [placement: 'center']
+                text 'abcdefghij'
+        ";
+
+        // The child fills the width, so it starts at the origin
+        let expected = "
+            abcdefgh
+            ij     ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 1)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn paint_order_overlay() {
+        let tpl = "
+            vstack
+                text 'abcd'
+                position [left: 3, top: 2]
+                    text 'efgh'
+        ";
+
+        // The border (0), expand (1), vstack (2) and text (3) share an origin,
+        // and the outermost element is drawn last.
+        // The floating widgets are painted after the tree,
+        // so the position (4) covers the border and the text (5) comes last.
+        let expected = "
+            4══════╗
+            ║1bcd  ║
+            ║  5fgh║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 2))
+            .instance()
+            .show_paint_order()
+            .render_assert(expected);
+    }
+}
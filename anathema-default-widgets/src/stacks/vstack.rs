@@ -119,6 +119,28 @@ mod test {
         TestRunner::new(tpl, (7, 3)).instance().render_assert(expected);
     }
 
+    #[test]
+    fn optional_min_width() {
+        let tpl = "
+            border
+                vstack [min_width?: value + 2]
+                    text 'a'
+        ";
+
+        let expected = "
+            ╔══════╗
+            ║┌───┐ ║
+            ║│a  │ ║
+            ║└───┘ ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 3))
+            .instance()
+            .with_state(|state| *state.value.to_mut() = 1)
+            .render_assert(expected);
+    }
+
     #[test]
     fn bottom_up_vstack() {
         let tpl = "
@@ -160,4 +182,41 @@ mod test {
 
         TestRunner::new(tpl, (6, 2)).instance().render_assert(expected);
     }
+
+    #[test]
+    fn vstack_for_map() {
+        let tpl = "
+            vstack
+                for entry in {b: 2, a: 1}
+                    text entry.key '=' entry.value
+        ";
+
+        let expected = "
+            ╔══════╗
+            ║a=1   ║
+            ║b=2   ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 2)).instance().render_assert(expected);
+    }
+
+    #[test]
+    fn vstack_for_with_index() {
+        let tpl = "
+            vstack
+                for (i, x) in ['a', 'b', 'c']
+                    text i ': ' x
+        ";
+
+        let expected = "
+            ╔══════╗
+            ║0: a  ║
+            ║1: b  ║
+            ║2: c  ║
+            ╚══════╝
+        ";
+
+        TestRunner::new(tpl, (6, 3)).instance().render_assert(expected);
+    }
 }
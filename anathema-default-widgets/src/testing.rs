@@ -1,18 +1,23 @@
+use std::time::{Duration, Instant};
+
 use anathema_backend::test::TestBackend;
 use anathema_backend::Backend;
 use anathema_geometry::{Pos, Size};
-use anathema_state::{State, StateId, States, Value};
+use anathema_state::{Hex, State, StateId, States, Value};
 use anathema_templates::blueprints::Blueprint;
 use anathema_templates::{Document, Globals, ToSourceKind};
-use anathema_widgets::components::ComponentRegistry;
+use anathema_widgets::components::{ComponentKind, ComponentRegistry};
 use anathema_widgets::layout::text::StringStorage;
-use anathema_widgets::layout::{layout_widget, position_widget, Constraints, LayoutCtx, LayoutFilter, Viewport};
+use anathema_widgets::layout::{
+    layout_widget, position_widget, Constraints, GlyphTheme, LayoutCtx, LayoutFilter, Viewport,
+};
+use anathema_widgets::paint::{CellAttributes, PaintOrder};
 use anathema_widgets::{
-    eval_blueprint, AttributeStorage, Components, Elements, EvalContext, Factory, FloatingWidgets, Scope, WidgetKind,
-    WidgetRenderer as _, WidgetTree,
+    eval_blueprint, AttributeStorage, Components, Elements, EvalContext, Factory, FloatingWidgets, Scope, Widget,
+    WidgetId, WidgetKind, WidgetRenderer as _, WidgetTree,
 };
 
-use crate::register_default_widgets;
+use crate::{register_default_widgets, render_to_canvas, Canvas};
 
 pub struct TestRunner {
     states: States,
@@ -59,37 +64,37 @@ impl TestRunner {
         }
     }
 
+    pub(crate) fn register<W: 'static + Widget + Default>(&mut self, ident: &str) -> &mut Self {
+        self.factory.register_default::<W>(ident);
+        self
+    }
+
     pub fn instance(&mut self) -> TestInstance<'_> {
-        let mut tree = WidgetTree::empty();
-        let mut attribute_storage = AttributeStorage::empty();
-        let mut floating_widgets = FloatingWidgets::empty();
-        let mut components = Components::new();
         let viewport = Viewport::new(self.backend.surface.size());
 
-        let mut scope = Scope::new();
-        scope.insert_state(StateId::ZERO);
-        let mut ctx = EvalContext::new(
-            &self.globals,
-            &self.factory,
-            &mut scope,
-            &mut self.states,
-            &mut self.component_registry,
-            &mut attribute_storage,
-            &mut floating_widgets,
-            &mut components,
-        );
-
-        eval_blueprint(&self.blueprint, &mut ctx, &[], &mut tree).unwrap();
-
-        TestInstance {
+        let mut instance = TestInstance {
             states: &mut self.states,
             backend: &mut self.backend,
-            floating_widgets,
-            tree,
-            attribute_storage,
+            component_registry: &mut self.component_registry,
+            factory: &self.factory,
+            blueprint: &self.blueprint,
+            globals: &self.globals,
+            floating_widgets: FloatingWidgets::empty(),
+            tree: WidgetTree::empty(),
+            attribute_storage: AttributeStorage::empty(),
             text: StringStorage::new(),
             viewport,
-        }
+            glyph_theme: GlyphTheme::default(),
+        };
+
+        instance.eval();
+        instance
+    }
+}
+
+impl Drop for TestInstance<'_> {
+    fn drop(&mut self) {
+        self.reset();
     }
 }
 
@@ -100,10 +105,56 @@ pub struct TestInstance<'bp> {
     text: StringStorage,
     states: &'bp mut States,
     backend: &'bp mut TestBackend,
+    component_registry: &'bp mut ComponentRegistry,
+    factory: &'bp Factory,
+    blueprint: &'bp Blueprint,
+    globals: &'bp Globals,
     viewport: Viewport,
+    glyph_theme: GlyphTheme,
 }
 
 impl TestInstance<'_> {
+    // Remove every widget and move the components back to the registry
+    // so they can be used by the next evaluation.
+    fn reset(&mut self) {
+        for (_, widget) in self.tree.reset().into_iter() {
+            let WidgetKind::Component(comp) = widget else { continue };
+            let ComponentKind::Instance = comp.kind else { continue };
+            let state = self.states.remove(comp.state_id);
+            self.component_registry
+                .return_component(comp.component_id, comp.dyn_component, state);
+        }
+    }
+
+    fn eval(&mut self) {
+        let mut components = Components::new();
+        let mut scope = Scope::new();
+        scope.insert_state(StateId::ZERO);
+        let mut ctx = EvalContext::new(
+            self.globals,
+            self.factory,
+            &mut scope,
+            self.states,
+            self.component_registry,
+            &mut self.attribute_storage,
+            &mut self.floating_widgets,
+            &mut components,
+        );
+
+        eval_blueprint(self.blueprint, &mut ctx, &[], &mut self.tree).unwrap();
+    }
+
+    /// Remove every widget and evaluate the blueprint again.
+    /// The widget ids are allocated from the same base as the first evaluation,
+    /// so the new widgets have the same ids as before.
+    pub fn rebuild(&mut self) -> &mut Self {
+        self.reset();
+        self.attribute_storage = AttributeStorage::empty();
+        self.floating_widgets = FloatingWidgets::empty();
+        self.eval();
+        self
+    }
+
     pub fn with_state<F>(&mut self, mut f: F) -> &mut Self
     where
         F: FnMut(&mut TestState),
@@ -114,17 +165,123 @@ impl TestInstance<'_> {
         self
     }
 
+    /// Draw the paint order of every element into its top-left cell.
+    pub fn show_paint_order(&mut self) -> &mut Self {
+        self.backend.paint_order = Some(PaintOrder::default());
+        self
+    }
+
+    pub fn with_glyph_theme(&mut self, glyph_theme: GlyphTheme) -> &mut Self {
+        self.glyph_theme = glyph_theme;
+        self
+    }
+
+    /// Layout the tree without painting it.
+    /// Unlike rendering this keeps the text storage between layouts.
+    pub(crate) fn layout(&mut self) -> &mut Self {
+        let constraints = Constraints::loose(self.backend.surface.size());
+        let attribute_storage = &self.attribute_storage;
+
+        let mut filter = LayoutFilter::new(true, attribute_storage);
+        self.tree.for_each(&mut filter).first(&mut |widget, children, values| {
+            let mut layout_ctx = LayoutCtx::new(self.text.new_session(), attribute_storage, &self.viewport);
+            layout_ctx.glyph_theme = self.glyph_theme;
+            layout_widget(widget, children, values, constraints, &mut layout_ctx, true);
+        });
+
+        self
+    }
+
     pub fn render_assert(&mut self, expected: &str) -> &mut Self {
         let expected = expected.trim().lines().map(str::trim).collect::<Vec<_>>().join("\n");
 
-        let (width, height) = self.backend.surface.size().into();
-        let constraints = Constraints::new(width as usize, height as usize);
+        self.render_frame();
+
+        let actual = std::mem::take(&mut self.backend.output);
+        let actual = actual.trim().lines().map(str::trim).collect::<Vec<_>>().join("\n");
+
+        eprintln!("{actual}");
+
+        assert_eq!(actual, expected);
+        self
+    }
+
+    /// Render the tree and compare both the glyphs and the style of every cell,
+    /// including the border added by the test runner.
+    ///
+    /// Each character in `styles` is a cell, looked up in the `legend`.
+    /// A `.` (or any character missing from the legend) is a cell without style.
+    ///
+    /// ```text
+    /// instance.render_assert_styled(
+    ///     "
+    ///     ╔══╗
+    ///     ║ab║
+    ///     ╚══╝
+    ///     ",
+    ///     "
+    ///     ....
+    ///     .r..
+    ///     ....
+    ///     ",
+    ///     &[('r', CellStyle::foreground(Hex::RED))],
+    /// );
+    /// ```
+    pub fn render_assert_styled(&mut self, expected: &str, styles: &str, legend: &[(char, CellStyle)]) -> &mut Self {
+        fn lines(s: &str) -> Vec<&str> {
+            s.trim().lines().map(str::trim).collect()
+        }
+
+        let canvas = self.render_to_canvas();
+        let actual = canvas.to_string_grid();
+        assert_eq!(lines(&actual), lines(expected));
+
+        for (y, row) in lines(styles).into_iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let expected = legend
+                    .iter()
+                    .find(|(key, _)| *key == c)
+                    .map(|(_, style)| *style)
+                    .unwrap_or_default();
+
+                let actual = canvas
+                    .get_ref((x as u16, y as u16))
+                    .map(|(_, attribs)| CellStyle {
+                        foreground: attribs.get_hex("foreground"),
+                        background: attribs.get_hex("background"),
+                        bold: attribs.get_bool("bold"),
+                    })
+                    .unwrap_or_default();
+
+                assert_eq!(actual, expected, "style of the cell at ({x}, {y})");
+            }
+        }
+
+        self
+    }
+
+    /// Render the tree `frames` times and return the total time spent.
+    /// The text storage and the backend are reused between frames,
+    /// the same as the runtime does.
+    pub fn bench_render(&mut self, frames: usize) -> Duration {
+        let start = Instant::now();
+        for _ in 0..frames {
+            self.render_frame();
+        }
+        start.elapsed()
+    }
+
+    // Layout, position and paint the tree followed by the floating widgets,
+    // and render the result to the backend output.
+    fn render_frame(&mut self) {
+        let constraints = Constraints::loose(self.backend.surface.size());
 
         let attribute_storage = &self.attribute_storage;
 
         let mut filter = LayoutFilter::new(true, attribute_storage);
         self.tree.for_each(&mut filter).first(&mut |widget, children, values| {
             let mut layout_ctx = LayoutCtx::new(self.text.new_session(), attribute_storage, &self.viewport);
+            layout_ctx.glyph_theme = self.glyph_theme;
             layout_widget(widget, children, values, constraints, &mut layout_ctx, true);
 
             // Position
@@ -146,6 +303,7 @@ impl TestInstance<'_> {
             self.tree.with_nodes_and_values(*widget_id, |widget, children, values| {
                 let WidgetKind::Element(el) = widget else { unreachable!("this is always a floating widget") };
                 let mut layout_ctx = LayoutCtx::new(self.text.new_session(), &self.attribute_storage, &self.viewport);
+                layout_ctx.glyph_theme = self.glyph_theme;
 
                 layout_widget(el, children, values, constraints, &mut layout_ctx, true);
 
@@ -165,16 +323,56 @@ impl TestInstance<'_> {
         }
 
         self.backend.render();
+        self.text.clear();
+    }
 
-        let actual = std::mem::take(&mut self.backend.output);
-        let actual = actual.trim().lines().map(str::trim).collect::<Vec<_>>().join("\n");
+    /// Render the tree, followed by the floating widgets,
+    /// into a canvas the size of the test backend.
+    pub fn render_to_canvas(&mut self) -> Canvas {
+        let size = self.backend.surface.size();
+        let attribute_storage = &self.attribute_storage;
+        let mut canvas = None;
 
-        self.text.clear();
+        let mut filter = LayoutFilter::new(true, attribute_storage);
+        self.tree.for_each(&mut filter).first(&mut |widget, children, values| {
+            canvas = Some(render_to_canvas(widget, children, values, attribute_storage, size));
+        });
 
-        eprintln!("{actual}");
+        let mut canvas = canvas.expect("the tree has a root widget");
 
-        assert_eq!(actual, expected);
-        self
+        for widget_id in self.floating_widgets.iter() {
+            self.tree.with_nodes_and_values(*widget_id, |widget, children, values| {
+                let WidgetKind::Element(el) = widget else { unreachable!("this is always a floating widget") };
+                canvas.render(el, children, values, attribute_storage);
+            });
+        }
+
+        canvas
+    }
+
+    /// Render the tree into an existing canvas,
+    /// at the position of the canvas.
+    pub fn render_into(&mut self, canvas: &mut Canvas) {
+        let attribute_storage = &self.attribute_storage;
+        let mut filter = LayoutFilter::new(true, attribute_storage);
+        self.tree.for_each(&mut filter).first(&mut |widget, children, values| {
+            canvas.render(widget, children, values, attribute_storage);
+        });
+    }
+
+    /// Render the tree and return the glyphs as rows of text,
+    /// including the border added by the test runner.
+    /// Use [`Self::render_to_canvas`] to inspect the attributes of a cell.
+    pub fn render_to_string(&mut self) -> String {
+        self.render_to_canvas().to_string_grid()
+    }
+
+    /// The id of every widget in the tree, in allocation order.
+    /// Every instance allocates ids from the same base,
+    /// so the same template always produces the same ids.
+    pub fn widget_ids(&mut self) -> Vec<WidgetId> {
+        let (_, values) = self.tree.split_mut();
+        values.iter_keys().map(|(id, _)| id).collect()
     }
 
     pub(crate) fn with_widget<F>(&mut self, mut f: F) -> &mut Self
@@ -194,6 +392,27 @@ impl TestInstance<'_> {
     }
 }
 
+/// The expected style of a cell, see [`TestInstance::render_assert_styled`]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct CellStyle {
+    pub foreground: Option<Hex>,
+    pub background: Option<Hex>,
+    pub bold: bool,
+}
+
+impl CellStyle {
+    pub fn foreground(hex: Hex) -> Self {
+        Self {
+            foreground: Some(hex),
+            ..Self::default()
+        }
+    }
+
+    pub fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+}
+
 #[derive(State)]
 pub struct TestState {
     pub value: Value<usize>,
@@ -208,3 +427,104 @@ impl TestState {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_assert_styled() {
+        let tpl = "
+            hstack
+                text 'a'
+                text [foreground: #ff0000, bold: true] 'b'
+        ";
+
+        let expected = "
+            ╔══╗
+            ║ab║
+            ╚══╝
+        ";
+
+        let styles = "
+            ....
+            ..r.
+            ....
+        ";
+
+        TestRunner::new(tpl, (2, 1)).instance().render_assert_styled(
+            expected,
+            styles,
+            &[('r', CellStyle::foreground(Hex::RED).bold())],
+        );
+    }
+
+    #[test]
+    fn bench_render() {
+        let tpl = "
+            vstack
+                for x in [1, 2, 3]
+                    text 'item ' x
+        ";
+
+        let mut runner = TestRunner::new(tpl, (6, 3));
+        let mut instance = runner.instance();
+        instance.bench_render(1);
+        let first = std::mem::take(&mut instance.backend.output);
+
+        let elapsed = instance.bench_render(10);
+        assert!(elapsed > Duration::ZERO);
+        assert!(first.contains("item 3"));
+        assert_eq!(instance.backend.output, first);
+    }
+
+    #[test]
+    fn deterministic_widget_ids() {
+        let tpl = "
+            vstack
+                text 'a'
+                for x in [1, 2, 3]
+                    text x
+        ";
+
+        let mut runner = TestRunner::new(tpl, (3, 3));
+        let mut instance = runner.instance();
+        let first = instance.widget_ids();
+        assert!(!first.is_empty());
+
+        // Rebuilding the tree allocates the same ids,
+        // even though the previous widgets were removed
+        let rebuilt = instance.rebuild().widget_ids();
+        assert_eq!(first, rebuilt);
+
+        // ... as does another instance of the same runner,
+        // once the components of the first one are returned
+        drop(instance);
+        let second = runner.instance().widget_ids();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn element_user_data() {
+        #[derive(Debug, PartialEq)]
+        struct Model {
+            id: usize,
+        }
+
+        let mut model_id = None;
+        TestRunner::new("text 'a'", (3, 3))
+            .instance()
+            .with_widget(|mut query| {
+                query.by_tag("text").first(|el, _| el.set_user_data(Model { id: 7 }));
+            })
+            .layout()
+            .with_widget(|mut query| {
+                query.by_tag("text").first(|el, _| {
+                    assert!(el.user_data::<String>().is_none());
+                    model_id = el.user_data::<Model>().map(|model| model.id);
+                });
+            });
+
+        assert_eq!(model_id, Some(7));
+    }
+}
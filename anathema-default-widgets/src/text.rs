@@ -2,10 +2,11 @@ use std::ops::ControlFlow;
 
 use anathema_geometry::{LocalPos, Size};
 use anathema_state::CommonVal;
-use anathema_widgets::layout::text::{LayoutKey, ProcessResult, Segment, StringSession};
+use anathema_widgets::layout::text::{MeasureCache, StringSession, Wrap};
 use anathema_widgets::layout::{Constraints, LayoutCtx, PositionCtx};
 use anathema_widgets::paint::{PaintCtx, SizePos};
 use anathema_widgets::{AttributeStorage, LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId};
+use unicode_width::UnicodeWidthChar;
 
 use crate::{LEFT, RIGHT};
 
@@ -68,43 +69,50 @@ impl TryFrom<CommonVal<'_>> for TextAlignment {
 /// A `Text` widget will be as wide as its text.
 #[derive(Debug, Default)]
 pub struct Text {
-    text_key: LayoutKey,
+    // The text and wrapping from the last layout
+    content: Content,
+    wrap: Wrap,
+    // The width the text was laid out within
+    width: usize,
+    // The lines of unchanged text are read from the cache
+    // rather than being laid out again every frame
+    measure_cache: MeasureCache,
 }
 
-impl Widget for Text {
-    fn layout<'bp>(
+impl Text {
+    // Collect the text and wrapping of the widget and its spans.
+    // The measure cache is cleared if the text changed.
+    fn collect<'bp>(
         &mut self,
         mut children: LayoutChildren<'_, '_, 'bp>,
-        constraints: Constraints,
         id: WidgetId,
         ctx: &mut LayoutCtx<'_, '_, 'bp>,
-    ) -> Size {
+    ) {
         let attributes = ctx.attribs.get(id);
-        let wrap = attributes.get(WRAP).unwrap_or_default();
+        self.wrap = attributes.get(WRAP).unwrap_or_default();
+        self.content.begin();
+        self.content.set_style(id);
 
-        let mut layout = ctx.text.new_layout(constraints.max_size(), wrap);
-        layout.set_style(id);
-
-        // Layout text
+        // Collect the text
         attributes.value().map(|value| {
-            value.str_iter(|s| match layout.add_str(s) {
-                ProcessResult::Break => ControlFlow::Break(()),
-                ProcessResult::Continue => ControlFlow::Continue(()),
+            value.str_iter(|s| {
+                self.content.push(s);
+                ControlFlow::Continue(())
             })
         });
 
-        // Layout text of all the sub-nodes
+        // Collect the text of all the sub-nodes
         children.for_each(|child, _| {
             let Some(_span) = child.try_to_ref::<Span>() else {
                 return ControlFlow::Continue(());
             };
-            layout.set_style(child.id());
+            self.content.set_style(child.id());
 
             let attributes = ctx.attribs.get(child.id());
             if let Some(text) = attributes.value() {
-                text.str_iter(|s| match layout.add_str(s) {
-                    ProcessResult::Break => ControlFlow::Break(()),
-                    ProcessResult::Continue => ControlFlow::Continue(()),
+                text.str_iter(|s| {
+                    self.content.push(s);
+                    ControlFlow::Continue(())
                 })?;
 
                 ControlFlow::Continue(())
@@ -113,10 +121,45 @@ impl Widget for Text {
             }
         });
 
-        let (key, size) = layout.finish();
+        if self.content.finish() {
+            self.measure_cache.clear();
+        }
+    }
+}
 
-        self.text_key = key;
+impl Widget for Text {
+    fn layout<'bp>(
+        &mut self,
+        children: LayoutChildren<'_, '_, 'bp>,
+        constraints: Constraints,
+        id: WidgetId,
+        ctx: &mut LayoutCtx<'_, '_, 'bp>,
+    ) -> Size {
+        self.collect(children, id, ctx);
+        self.width = constraints.max_width();
+
+        let mut size = self
+            .measure_cache
+            .measure(&self.content.text, self.width, self.wrap)
+            .size;
+        size.height = size.height.min(constraints.max_height());
+        size
+    }
 
+    fn negotiate<'bp>(
+        &mut self,
+        children: LayoutChildren<'_, '_, 'bp>,
+        constraints: Constraints,
+        id: WidgetId,
+        ctx: &mut LayoutCtx<'_, '_, 'bp>,
+    ) -> Size {
+        self.collect(children, id, ctx);
+
+        let mut size = self
+            .measure_cache
+            .measure(&self.content.text, constraints.max_width(), self.wrap)
+            .size;
+        size.height = size.height.min(constraints.max_height());
         size
     }
 
@@ -126,39 +169,53 @@ impl Widget for Text {
         id: WidgetId,
         attribute_storage: &AttributeStorage<'bp>,
         mut ctx: PaintCtx<'_, SizePos>,
-        text: &mut StringSession<'_>,
+        _: &mut StringSession<'_>,
     ) {
-        let lines = text.lines(self.text_key);
+        let Some(measured) = self.measure_cache.get(&self.content.text, self.width, self.wrap) else {
+            return;
+        };
         let alignment = attribute_storage.get(id).get(TEXT_ALIGN).unwrap_or_default();
 
         let mut pos = LocalPos::ZERO;
         let mut style = attribute_storage.get(id);
+        let mut styles = self.content.styles.iter().peekable();
+        let mut chars = self.content.text.chars().enumerate().peekable();
 
-        for line in lines {
+        let lines = measured.lines.iter().zip(&measured.widths);
+        for (line, width) in lines.take(ctx.local_size.height) {
             let x = match alignment {
                 TextAlignment::Left => 0,
-                TextAlignment::Centre => ctx.local_size.width as u16 / 2 - line.width / 2,
-                TextAlignment::Right => ctx.local_size.width as u16 - line.width,
+                TextAlignment::Centre => ctx.local_size.width as u16 / 2 - width / 2,
+                TextAlignment::Right => ctx.local_size.width as u16 - width,
             };
 
             pos.x = x;
 
-            for entry in line.entries {
-                match entry {
-                    Segment::Str(s) => {
-                        if let Some(new_pos) = ctx.place_glyphs(s, pos) {
-                            // NOTE:
-                            // This isn't very nice, but it works for now.
-                            // In the future there should probably be a means to
-                            // provide both style and glyph at the same time.
-                            for x in pos.x..new_pos.x {
-                                ctx.set_attributes(style, (x, pos.y).into());
-                            }
-                            pos = new_pos;
-                        }
-                    }
-                    Segment::SetStyle(attribute_id) => style = attribute_storage.get(attribute_id),
+            while let Some((index, c)) = chars.next_if(|(index, _)| *index < line.end) {
+                while let Some((_, style_id)) = styles.next_if(|(start, _)| *start <= index) {
+                    style = attribute_storage.get(*style_id);
+                }
+
+                // Skip the characters between the lines, and any
+                // character too wide to ever fit
+                if index < line.start || c.width().unwrap_or(0) > self.width {
+                    continue;
+                }
+
+                // Once the line is filled the cursor moves to the next line
+                let end = match ctx.place_glyph(c, pos) {
+                    Some(new_pos) if new_pos.y == pos.y => new_pos.x,
+                    _ => ctx.local_size.width as u16,
+                };
+
+                // NOTE:
+                // This isn't very nice, but it works for now.
+                // In the future there should probably be a means to
+                // provide both style and glyph at the same time.
+                for x in pos.x..end {
+                    ctx.set_attributes(style, (x, pos.y).into());
                 }
+                pos.x = end;
             }
             pos.y += 1;
             pos.x = 0;
@@ -177,6 +234,58 @@ impl Widget for Text {
     }
 }
 
+// The text of the last layout.
+// The text being laid out is compared with it part by part,
+// so an unchanged text isn't copied on every layout.
+#[derive(Debug, Default)]
+struct Content {
+    text: String,
+    // The end of the text matched by the current layout
+    end: usize,
+    // The number of chars up to the end
+    chars: usize,
+    changed: bool,
+    // The char index where each style starts
+    styles: Vec<(usize, WidgetId)>,
+}
+
+impl Content {
+    fn begin(&mut self) {
+        self.end = 0;
+        self.chars = 0;
+        self.changed = false;
+        self.styles.clear();
+    }
+
+    fn set_style(&mut self, style: WidgetId) {
+        self.styles.push((self.chars, style));
+    }
+
+    // Only the text from the first difference is replaced
+    fn push(&mut self, s: &str) {
+        let end = self.end + s.len();
+        self.chars += s.chars().count();
+        if !self.changed && self.text.get(self.end..end) == Some(s) {
+            self.end = end;
+            return;
+        }
+
+        self.text.truncate(self.end);
+        self.text.push_str(s);
+        self.end = end;
+        self.changed = true;
+    }
+
+    // Returns `true` if the text changed since the last layout
+    fn finish(&mut self) -> bool {
+        if self.end != self.text.len() {
+            self.text.truncate(self.end);
+            self.changed = true;
+        }
+        self.changed
+    }
+}
+
 #[derive(Default, Copy, Clone)]
 pub struct Span;
 
@@ -206,8 +315,46 @@ impl Widget for Span {
 
 #[cfg(test)]
 mod test {
+    use anathema::Hex;
+    use anathema_widgets::paint::CellAttributes;
+
+    use super::{Content, Text};
     use crate::testing::TestRunner;
 
+    fn layout_content(content: &mut Content, parts: &[&str]) -> bool {
+        content.begin();
+        parts.iter().for_each(|s| content.push(s));
+        content.finish()
+    }
+
+    #[test]
+    fn content_changes() {
+        let mut content = Content::default();
+        assert!(layout_content(&mut content, &["ab", "c"]));
+        assert!(!layout_content(&mut content, &["ab", "c"]));
+        assert!(!layout_content(&mut content, &["a", "bc"]));
+        assert_eq!(content.text, "abc");
+
+        assert!(layout_content(&mut content, &["ab", "d"]));
+        assert_eq!(content.text, "abd");
+
+        assert!(layout_content(&mut content, &["ab"]));
+        assert_eq!(content.text, "ab");
+    }
+
+    #[test]
+    fn wide_glyph() {
+        // The wide glyph covers two columns,
+        // so the right edge of the border stays aligned
+        let src = "text '中a'";
+        let expected = "
+           ╔════╗
+           ║中a ║
+           ╚════╝";
+
+        TestRunner::new(src, (4, 1)).instance().render_assert(expected);
+    }
+
     #[test]
     fn word_wrap_excessive_space() {
         let src = "text 'hello      how are     you'";
@@ -333,4 +480,104 @@ mod test {
 
         TestRunner::new(src, (9, 3)).instance().render_assert(expected);
     }
+
+    #[test]
+    fn format_state_value() {
+        let src = r#"text f"n: {value:03}""#;
+        let first = "
+               ╔══════╗
+               ║n: 000║
+               ╚══════╝
+           ";
+        let second = "
+               ╔══════╗
+               ║n: 007║
+               ╚══════╝
+           ";
+
+        TestRunner::new(src, (6, 1))
+            .instance()
+            .render_assert(first)
+            .with_state(|state| *state.value.to_mut() = 7)
+            .render_assert(second);
+    }
+
+    #[test]
+    fn unchanged_text_is_measured_once() {
+        // The text reads from the state so it's laid out every frame
+        let src = "text 'hello ' value";
+        let expected = "
+               ╔═════╗
+               ║hello║
+               ║0    ║
+               ╚═════╝
+           ";
+
+        let mut measurements = 0;
+        let mut hits = 0;
+        TestRunner::new(src, (5, 2))
+            .instance()
+            .render_assert(expected)
+            .render_assert(expected)
+            .with_widget(|mut query| {
+                query.by_tag("text").first(|el, _| {
+                    let text = el.to::<Text>();
+                    measurements = text.measure_cache.measurements();
+                    hits = text.measure_cache.hits();
+                })
+            });
+
+        // The second layout reads the lines from the cache
+        assert_eq!(measurements, 1);
+        assert_eq!(hits, 1);
+    }
+
+    #[test]
+    fn stack_negotiates_text_size() {
+        let src = "
+            hstack
+                text 'hi'
+                text 'hello world'
+        ";
+
+        let expected = "
+               ╔═══════╗
+               ║hihello║
+               ║  world║
+               ╚═══════╝
+           ";
+
+        let mut measured = vec![];
+        TestRunner::new(src, (7, 2))
+            .instance()
+            .render_assert(expected)
+            .with_widget(|mut query| {
+                query.by_tag("text").each(|el, _| {
+                    let text = el.to::<Text>();
+                    measured.push((text.measure_cache.measurements(), text.measure_cache.hits()));
+                })
+            });
+
+        // The text that fits is laid out with the negotiated measurement,
+        // the overflowing text is measured again within the remaining width
+        assert_eq!(measured, vec![(1, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn inspect_rendered_cell() {
+        let src = "text [foreground: #ff0000] 'hi'";
+
+        let mut runner = TestRunner::new(src, (3, 1));
+        let mut instance = runner.instance();
+        assert_eq!(instance.render_to_string(), "╔═══╗\n║hi ║\n╚═══╝");
+
+        let mut canvas = instance.render_to_canvas();
+        let (c, attribs) = canvas.get((1, 1)).unwrap();
+        assert_eq!(*c, "h");
+        assert_eq!(attribs.get_hex("foreground"), Some(Hex::RED));
+
+        // The text is only as wide as its content
+        let (_, attribs) = canvas.get((3, 1)).unwrap();
+        assert_eq!(attribs.get_hex("foreground"), None);
+    }
 }
@@ -7,7 +7,7 @@ use anathema_store::storage::strings::Strings;
 use anathema_widgets::components::events::{Event, KeyCode, KeyEvent, KeyState};
 use anathema_widgets::components::{AssociatedEvents, Emitter, UntypedContext};
 use anathema_widgets::layout::{Constraints, Viewport};
-use anathema_widgets::{AttributeStorage, Components, Elements, WidgetKind, WidgetTree};
+use anathema_widgets::{AttributeStorage, Components, Elements, RepaintQueue, WidgetKind, WidgetTree};
 
 use crate::error::{Error, Result};
 
@@ -29,6 +29,7 @@ impl EventHandler {
         constraints: &mut Constraints,
         assoc_events: &mut AssociatedEvents,
         strings: &Strings,
+        repaints: &mut RepaintQueue,
     ) -> Result<()> {
         while let Some(event) = backend.next_event(poll_duration) {
             let Some(event) = global_event(
@@ -42,6 +43,7 @@ impl EventHandler {
                 *viewport,
                 assoc_events,
                 strings,
+                repaints,
             ) else {
                 return Ok(());
             };
@@ -68,6 +70,7 @@ impl EventHandler {
                             parent,
                             strings,
                             assoc_functions: component.assoc_functions,
+                            repaints,
                         };
                         component.dyn_component.any_event(event, state, elements, context);
                     });
@@ -107,6 +110,7 @@ impl EventHandler {
                                 parent,
                                 strings,
                                 assoc_functions: component.assoc_functions,
+                                repaints,
                             };
                             component.dyn_component.any_resize(state, elements, context);
                         });
@@ -157,6 +161,7 @@ impl EventHandler {
                             parent,
                             strings,
                             assoc_functions: component.assoc_functions,
+                            repaints,
                         };
 
                         component
@@ -182,6 +187,7 @@ pub fn global_event<'bp, T: Backend>(
     viewport: Viewport,
     assoc_events: &mut AssociatedEvents,
     strings: &Strings,
+    repaints: &mut RepaintQueue,
 ) -> Option<Event> {
     // -----------------------------------------------------------------------------
     //   - Ctrl-c to quite -
@@ -227,6 +233,7 @@ pub fn global_event<'bp, T: Backend>(
                     parent,
                     strings,
                     assoc_functions: component.assoc_functions,
+                    repaints,
                 };
                 component.dyn_component.any_blur(state, elements, context);
             });
@@ -252,6 +259,7 @@ pub fn global_event<'bp, T: Backend>(
                     parent,
                     strings,
                     assoc_functions: component.assoc_functions,
+                    repaints,
                 };
                 component.dyn_component.any_focus(state, elements, context);
             });
@@ -283,6 +291,7 @@ pub fn global_event<'bp, T: Backend>(
                     parent,
                     strings,
                     assoc_functions: component.assoc_functions,
+                    repaints,
                 };
                 let _ = component.dyn_component.any_event(event, state, elements, context);
             });
@@ -29,15 +29,18 @@ use anathema_state::{
 };
 use anathema_store::tree::{root_node, AsNodePath};
 use anathema_templates::blueprints::Blueprint;
-use anathema_templates::{Document, Globals, ToSourceKind};
+use anathema_templates::{Document, Globals, ToSourceKind, WidgetComponentId};
 use anathema_widgets::components::{
-    AssociatedEvents, Component, ComponentId, ComponentKind, ComponentRegistry, Emitter, UntypedContext, ViewMessage,
+    AssociatedEvents, Component, ComponentId, ComponentKind, ComponentRegistry, Emitter, Payload, StateUpdate,
+    UntypedContext, ViewMessage,
 };
 use anathema_widgets::layout::text::StringStorage;
-use anathema_widgets::layout::{layout_widget, position_widget, Constraints, LayoutCtx, LayoutFilter, Viewport};
+use anathema_widgets::layout::{
+    layout_widget, position_widget, Capabilities, Constraints, GlyphTheme, LayoutCtx, LayoutFilter, Viewport,
+};
 use anathema_widgets::{
-    eval_blueprint, try_resolve_future_values, update_tree, AttributeStorage, Components, Elements, EvalContext,
-    Factory, FloatingWidgets, Scope, WidgetKind, WidgetTree,
+    eval_blueprint, rebind_component_state, try_resolve_future_values, update_tree, AttributeStorage, Components,
+    Elements, EvalContext, Factory, FloatingWidgets, RepaintQueue, Scope, WidgetKind, WidgetTree,
 };
 use events::EventHandler;
 use notify::{recommended_watcher, Event, RecommendedWatcher, RecursiveMode, Watcher};
@@ -56,6 +59,8 @@ pub struct RuntimeBuilder<T> {
     factory: Factory,
     message_receiver: flume::Receiver<ViewMessage>,
     emitter: Emitter,
+    capabilities: Capabilities,
+    glyph_theme: GlyphTheme,
 }
 
 impl<T> RuntimeBuilder<T> {
@@ -110,6 +115,18 @@ impl<T> RuntimeBuilder<T> {
         self.emitter.clone()
     }
 
+    /// Set the terminal capabilities.
+    /// These are available to templates as `truecolor` and `unicode`.
+    pub fn capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    /// Set the glyphs used by widgets drawing boxes and lines,
+    /// e.g `GlyphTheme::ASCII` for terminals without unicode support.
+    pub fn glyph_theme(&mut self, glyph_theme: GlyphTheme) {
+        self.glyph_theme = glyph_theme;
+    }
+
     fn set_watcher(&mut self) -> Result<RecommendedWatcher> {
         let paths = self
             .document
@@ -144,6 +161,7 @@ impl<T> RuntimeBuilder<T> {
     where
         T: Backend,
     {
+        self.capabilities.set_globals(&mut self.document);
         let (blueprint, globals) = self.document.compile()?;
         let watcher = match self.document.hot_reload {
             false => None,
@@ -172,8 +190,10 @@ impl<T> RuntimeBuilder<T> {
             string_storage: StringStorage::new(),
             viewport: Viewport::new((width, height)),
             floating_widgets: FloatingWidgets::empty(),
+            repaints: RepaintQueue::empty(),
             components: Components::new(),
             event_handler: EventHandler,
+            glyph_theme: self.glyph_theme,
         };
 
         Ok(inst)
@@ -200,6 +220,8 @@ pub struct Runtime<T> {
     factory: Factory,
     globals: Globals,
     document: Document,
+    // Read by the layout, set once by the builder
+    glyph_theme: GlyphTheme,
 
     // -----------------------------------------------------------------------------
     //   - Mut during runtime -
@@ -232,6 +254,9 @@ pub struct Runtime<T> {
     component_registry: ComponentRegistry,
     // * Layout
     floating_widgets: FloatingWidgets,
+    // * Event handling
+    // * Changes
+    repaints: RepaintQueue,
 }
 
 impl<T> Runtime<T>
@@ -257,6 +282,8 @@ where
             factory,
             emitter: message_sender.into(),
             message_receiver,
+            capabilities: Capabilities::default(),
+            glyph_theme: GlyphTheme::default(),
         }
     }
 
@@ -338,21 +365,81 @@ where
         });
     }
 
+    fn update_state<'bp>(
+        &mut self,
+        globals: &'bp Globals,
+        recipient: WidgetComponentId,
+        update: StateUpdate,
+        tree: &mut WidgetTree<'bp>,
+        states: &mut States,
+        attribute_storage: &mut AttributeStorage<'bp>,
+    ) {
+        match update {
+            StateUpdate::Replace(type_id, mut state) => {
+                let is_state_type = self
+                    .components
+                    .get_by_component_id(recipient)
+                    .and_then(|entry| states.get(entry.state_id))
+                    .is_some_and(|current| current.to_any_ref().type_id() == type_id);
+
+                if !is_state_type {
+                    return;
+                }
+
+                let mut scope = Scope::new();
+                rebind_component_state(
+                    globals,
+                    &self.factory,
+                    &mut scope,
+                    states,
+                    &mut self.component_registry,
+                    recipient,
+                    || state(),
+                    tree,
+                    attribute_storage,
+                    &mut self.floating_widgets,
+                    &mut self.components,
+                );
+            }
+            // Merging only changes values, which are applied to the tree
+            // along with all other changes.
+            StateUpdate::Merge(mut merge) => self
+                .components
+                .iter()
+                .filter(|entry| entry.component_id == recipient)
+                .for_each(|entry| {
+                    if let Some(state) = states.get_mut(entry.state_id) {
+                        merge(state);
+                    }
+                }),
+        }
+    }
+
     fn handle_messages<'bp>(
         &mut self,
         fps_now: Instant,
         sleep_micros: u128,
+        globals: &'bp Globals,
         tree: &mut WidgetTree<'bp>,
         states: &mut States,
         attribute_storage: &mut AttributeStorage<'bp>,
         assoc_events: &mut AssociatedEvents,
     ) -> Duration {
         while let Ok(msg) = self.message_receiver.try_recv() {
-            if let Some((widget_id, state_id)) = self
-                .components
-                .get_by_component_id(msg.recipient())
-                .map(|e| (e.widget_id, e.state_id))
-            {
+            let recipient = msg.recipient();
+            let payload = match msg.payload() {
+                Payload::Message(payload) => Some(payload),
+                Payload::State(update) => {
+                    self.update_state(globals, recipient, update, tree, states, attribute_storage);
+                    None
+                }
+            };
+
+            if let Some((payload, (widget_id, state_id))) = payload.zip(
+                self.components
+                    .get_by_component_id(recipient)
+                    .map(|e| (e.widget_id, e.state_id)),
+            ) {
                 tree.with_value_mut(widget_id, |path, widget, tree| {
                     let WidgetKind::Component(component) = widget else { return };
                     let state = states.get_mut(state_id);
@@ -373,11 +460,10 @@ where
                         parent,
                         strings: &mut self.document.strings,
                         assoc_functions: component.assoc_functions,
+                        repaints: &mut self.repaints,
                     };
 
-                    component
-                        .dyn_component
-                        .any_message(msg.payload(), state, elements, context);
+                    component.dyn_component.any_message(payload, state, elements, context);
                 });
             }
 
@@ -459,6 +545,7 @@ where
                     parent,
                     strings: &mut self.document.strings,
                     assoc_functions: component.assoc_functions,
+                    repaints: &mut self.repaints,
                 };
                 component.dyn_component.any_focus(state, elements, context);
             });
@@ -552,7 +639,15 @@ where
         // Pull and keep consuming events while there are events present
         // in the queu. The time used to pull events should be subtracted
         // from the poll duration of self.events.poll
-        let poll_duration = self.handle_messages(fps_now, sleep_micros, tree, states, attribute_storage, assoc_events);
+        let poll_duration = self.handle_messages(
+            fps_now,
+            sleep_micros,
+            globals,
+            tree,
+            states,
+            attribute_storage,
+            assoc_events,
+        );
 
         // Clear the text buffer
         self.string_storage.clear();
@@ -571,6 +666,7 @@ where
             &mut self.constraints,
             assoc_events,
             &self.document.strings,
+            &mut self.repaints,
         )?;
 
         // Call the `tick` function on all components
@@ -593,6 +689,7 @@ where
         // Q) What about floating widgets?
 
         self.apply_changes(globals, tree, states, attribute_storage);
+        self.repaints.flush(tree);
 
         // Cleanup removed attributes from widgets.
         // Not all widgets has attributes, only `Element`s.
@@ -615,6 +712,7 @@ where
             //       That doesn't have as much of an impact here
             //       as it will do when dealing with the floating widgets
             let mut layout_ctx = LayoutCtx::new(self.string_storage.new_session(), attribute_storage, &self.viewport);
+            layout_ctx.glyph_theme = self.glyph_theme;
             layout_widget(widget, children, values, self.constraints, &mut layout_ctx, true);
 
             // Position
@@ -645,6 +743,7 @@ where
                 let WidgetKind::Element(el) = widget else { unreachable!("this is always a floating widget") };
                 let mut layout_ctx =
                     LayoutCtx::new(self.string_storage.new_session(), attribute_storage, &self.viewport);
+                layout_ctx.glyph_theme = self.glyph_theme;
 
                 layout_widget(el, children, values, constraints, &mut layout_ctx, true);
 
@@ -703,6 +802,7 @@ where
                     parent,
                     strings: &mut self.document.strings,
                     assoc_functions: component.assoc_functions,
+                    repaints: &mut self.repaints,
                 };
 
                 component.dyn_component.any_tick(state, elements, context, dt);
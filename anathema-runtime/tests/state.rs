@@ -0,0 +1,146 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use anathema_backend::test::TestBackend;
+use anathema_backend::Backend;
+use anathema_geometry::Size;
+use anathema_runtime::Runtime;
+use anathema_state::{Map, Value};
+use anathema_store::tree::{Node, TreeValues};
+use anathema_templates::{Document, ToSourceKind};
+use anathema_widgets::components::events::Event;
+use anathema_widgets::components::Component;
+use anathema_widgets::layout::text::StringSession;
+use anathema_widgets::{AttributeStorage, Element, WidgetKind};
+
+type Frames = Rc<RefCell<Vec<String>>>;
+
+/// Records every rendered frame and stops the runtime after the first frame.
+struct FrameBackend {
+    inner: TestBackend,
+    frames: Frames,
+}
+
+impl Backend for FrameBackend {
+    fn size(&self) -> Size {
+        self.inner.size()
+    }
+
+    fn quit_test(&self, event: Event) -> bool {
+        self.inner.quit_test(event)
+    }
+
+    fn next_event(&mut self, _timeout: Duration) -> Option<Event> {
+        match self.frames.borrow().is_empty() {
+            true => None,
+            false => Some(Event::Stop),
+        }
+    }
+
+    fn resize(&mut self, new_size: Size) {
+        self.inner.resize(new_size)
+    }
+
+    fn paint<'bp>(
+        &mut self,
+        element: &mut Element<'bp>,
+        children: &[Node],
+        values: &mut TreeValues<WidgetKind<'bp>>,
+        text: &mut StringSession<'_>,
+        attribute_storage: &AttributeStorage<'bp>,
+        ignore_floats: bool,
+    ) {
+        self.inner
+            .paint(element, children, values, text, attribute_storage, ignore_floats)
+    }
+
+    fn render(&mut self) {
+        self.inner.render();
+        self.frames.borrow_mut().push(self.inner.output.clone());
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear()
+    }
+}
+
+struct Comp;
+
+impl Component for Comp {
+    type Message = ();
+    type State = Value<Map<i64>>;
+}
+
+fn state(values: &[(&str, i64)]) -> Value<Map<i64>> {
+    let mut map = Map::empty();
+    for (key, value) in values {
+        map.insert(*key, *value);
+    }
+    map
+}
+
+fn first_frame(f: impl FnOnce(&mut anathema_runtime::RuntimeBuilder<FrameBackend>)) -> String {
+    let frames = Frames::default();
+    let backend = FrameBackend {
+        inner: TestBackend::new((5, 2)),
+        frames: frames.clone(),
+    };
+
+    let mut builder = Runtime::builder(Document::new("@comp"), backend);
+    f(&mut builder);
+    builder.finish().unwrap().run();
+
+    let frames = frames.borrow();
+    frames[0].clone()
+}
+
+#[test]
+fn replace_component_state() {
+    let frame = first_frame(|builder| {
+        let id = builder
+            .register_component("comp", "text a ' ' b".to_template(), Comp, state(&[("a", 1), ("b", 2)]))
+            .unwrap();
+        builder
+            .emitter()
+            .replace_state(id, || state(&[("a", 3), ("b", 4)]))
+            .unwrap();
+    });
+
+    assert_eq!(frame, "3 4  \n     \n");
+}
+
+#[test]
+fn merge_component_state() {
+    let frame = first_frame(|builder| {
+        let id = builder
+            .register_component(
+                "comp",
+                "text a ' ' b ' ' c".to_template(),
+                Comp,
+                state(&[("a", 1), ("b", 2)]),
+            )
+            .unwrap();
+        builder
+            .emitter()
+            .merge_state(id, |state: &mut Value<Map<i64>>| {
+                state.to_mut().get_mut("b").unwrap().set(5);
+                state.insert("c", 6);
+            })
+            .unwrap();
+    });
+
+    assert_eq!(frame, "1 5 6\n     \n");
+}
+
+#[test]
+fn replace_state_of_another_type_is_ignored() {
+    let frame = first_frame(|builder| {
+        let id = builder
+            .register_component("comp", "text a ' ' b".to_template(), Comp, state(&[("a", 1), ("b", 2)]))
+            .unwrap();
+        builder.emitter().replace_state(id, || Value::new(9i64)).unwrap();
+    });
+
+    assert_eq!(frame, "1 2  \n     \n");
+}
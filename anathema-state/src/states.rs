@@ -41,6 +41,8 @@ pub trait AnyState: 'static {
     fn to_bool(&self) -> bool;
 
     fn count(&self) -> usize;
+
+    fn state_keys(&self) -> Vec<Rc<str>>;
 }
 
 impl AnyState for Box<dyn AnyState> {
@@ -75,6 +77,10 @@ impl AnyState for Box<dyn AnyState> {
     fn count(&self) -> usize {
         self.as_ref().count()
     }
+
+    fn state_keys(&self) -> Vec<Rc<str>> {
+        self.as_ref().state_keys()
+    }
 }
 
 impl<T: State> AnyState for T {
@@ -109,6 +115,10 @@ impl<T: State> AnyState for T {
     fn count(&self) -> usize {
         <Self as State>::count(self)
     }
+
+    fn state_keys(&self) -> Vec<Rc<str>> {
+        <Self as State>::state_keys(self)
+    }
 }
 
 pub trait State: 'static {
@@ -132,6 +142,12 @@ pub trait State: 'static {
         0
     }
 
+    /// The keys of the underlying map, e.g to spread the map onto an element.
+    /// If the state is not a map it should return no keys
+    fn state_keys(&self) -> Vec<Rc<str>> {
+        vec![]
+    }
+
     fn to_number(&self) -> Option<Number> {
         None
     }
@@ -167,6 +183,10 @@ impl State for Box<dyn State> {
     fn count(&self) -> usize {
         self.as_ref().count()
     }
+
+    fn state_keys(&self) -> Vec<Rc<str>> {
+        self.as_ref().state_keys()
+    }
 }
 
 impl<T: 'static + State> State for Value<T> {
@@ -193,6 +213,10 @@ impl<T: 'static + State> State for Value<T> {
     fn count(&self) -> usize {
         self.to_ref().count()
     }
+
+    fn state_keys(&self) -> Vec<Rc<str>> {
+        self.to_ref().state_keys()
+    }
 }
 
 impl Debug for dyn State {
@@ -371,4 +395,13 @@ impl States {
     pub fn remove(&mut self, state_id: StateId) -> Box<dyn AnyState> {
         self.inner.remove(state_id)
     }
+
+    /// Replace a given state, returning the previous state.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the state does not exist.
+    pub fn replace(&mut self, state_id: StateId, state: Box<dyn AnyState>) -> Box<dyn AnyState> {
+        self.inner.replace(state_id, state)
+    }
 }
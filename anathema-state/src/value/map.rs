@@ -59,6 +59,10 @@ impl<T: 'static + State> State for Map<T> {
         Some(value.to_pending())
     }
 
+    fn state_keys(&self) -> Vec<Rc<str>> {
+        self.inner.keys().cloned().collect()
+    }
+
     fn to_common(&self) -> Option<CommonVal<'_>> {
         None
     }
@@ -126,11 +126,30 @@ where
         self.0.try_remove(idx).map(|(_, v)| v)
     }
 
+    /// Remove every key-value pair that doesn't satisfy the predicate
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &V) -> bool) {
+        let indices = self
+            .0
+            .iter()
+            .filter(|(_, (k, v))| !f(k, v))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        for idx in indices {
+            self.0.try_remove(idx);
+        }
+    }
+
     /// Iterate over the key-value pairs of the map.
     pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
         self.0.iter().map(|(_, (k, v))| (k, v))
     }
 
+    /// Iterate over the index, key and a mutable value ref of the map.
+    pub fn iter_mut_with_index(&mut self) -> impl Iterator<Item = (SmallIndex, &K, &mut V)> + '_ {
+        self.0.iter_mut().map(|(i, (k, v))| (i, &*k, v))
+    }
+
     /// Get a value ref by the value index instead of the key
     pub fn get_with_index(&self, idx: SmallIndex) -> Option<&V> {
         self.0.get(idx).map(|(_, v)| v)
@@ -189,4 +208,16 @@ mod test {
         let idx = map.get_index("a").unwrap();
         assert_eq!(1, *map.get_mut_with_index(idx).unwrap());
     }
+
+    #[test]
+    fn retain() {
+        let mut map = SmallMap::<&str, u8>::empty();
+        map.set("a", 1);
+        map.set("b", 2);
+        map.set("c", 3);
+        map.retain(|_, v| *v != 2);
+        assert!(map.get("b").is_none());
+        assert_eq!(1, *map.get("a").unwrap());
+        assert_eq!(3, *map.get("c").unwrap());
+    }
 }
@@ -13,12 +13,21 @@ impl<'a, 'filter, T, Fil> TreeForEach<'a, 'filter, T, Fil> {
         Self { nodes, values, filter }
     }
 
+    /// Reborrow the nodes, so they can be iterated over more than once
+    pub fn reborrow(&mut self) -> TreeForEach<'_, 'filter, T, Fil> {
+        TreeForEach {
+            nodes: self.nodes,
+            values: self.values,
+            filter: self.filter,
+        }
+    }
+
     pub fn for_each<F>(&mut self, mut f: F)
     where
         F: FnMut(&mut Fil::Output, TreeForEach<'_, '_, T, Fil>) -> ControlFlow<()>,
         Fil: TreeFilter<Input = T>,
     {
-        self.inner_for_each(&mut f);
+        let _ = self.inner_for_each(&mut f);
     }
 
     pub fn first<F>(&mut self, f: &mut F)
@@ -27,7 +36,7 @@ impl<'a, 'filter, T, Fil> TreeForEach<'a, 'filter, T, Fil> {
         Fil: TreeFilter<Input = T>,
     {
         for node in self.nodes {
-            self.values.with_mut(node.value(), |(_, value), values| {
+            let _ = self.values.with_mut(node.value(), |(_, value), values| {
                 let filter = self.filter.filter(node.value(), value, node.children(), values);
 
                 match filter {
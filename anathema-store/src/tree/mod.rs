@@ -36,6 +36,17 @@ impl<T> Tree<T> {
         }
     }
 
+    /// Remove all nodes and values and reset the id allocation.
+    /// The ids of values inserted after a reset will follow
+    /// the same sequence as an empty tree.
+    ///
+    /// Returns the removed values.
+    pub fn reset(&mut self) -> TreeValues<T> {
+        self.layout = Nodes::empty();
+        self.removed_values.clear();
+        std::mem::replace(&mut self.values, TreeValues::empty())
+    }
+
     pub fn values(self) -> TreeValues<T> {
         self.values
     }
@@ -201,6 +212,38 @@ impl<T> Tree<T> {
         node.children.clear(&mut self.values, &mut self.removed_values);
     }
 
+    /// Move a child of a `Node` from one index to another, keeping the value (and the
+    /// values of all the children) intact. The paths of every sibling between the
+    /// two indices are updated.
+    pub fn move_child(&mut self, parent: &[u16], from: usize, to: usize) {
+        if from == to {
+            return;
+        }
+
+        self.layout.with_mut(parent, |siblings| {
+            if from >= siblings.len() || to >= siblings.len() {
+                return;
+            }
+
+            let node = siblings.remove(from);
+            siblings.inner.insert(to, node);
+
+            let start = from.min(to);
+            let end = from.max(to);
+            siblings.inner[start..=end]
+                .iter_mut()
+                .enumerate()
+                .for_each(|(offset, node)| {
+                    let (path, _) = self.values.get_mut(node.value).expect("every node has a value");
+                    path[path.len() - 1] = (start + offset) as u16;
+
+                    // Clone the path to drop the borrow of the tree
+                    let path = path.clone();
+                    node.reparent(&path, &mut self.values);
+                });
+        });
+    }
+
     pub fn for_each<'filter, F: TreeFilter>(&mut self, filter: &'filter mut F) -> TreeForEach<'_, 'filter, T, F> {
         TreeForEach {
             nodes: &self.layout,
@@ -287,7 +330,7 @@ impl<T> Tree<T> {
 
     /// Apply a node visitor, depth first
     pub fn apply_visitor<V: NodeVisitor<T>>(&mut self, visitor: &mut V) {
-        apply_visitor(&self.layout, &mut self.values, visitor);
+        let _ = apply_visitor(&self.layout, &mut self.values, visitor);
     }
 
     /// Split the tree giving access to the layout and the values.
@@ -379,7 +422,7 @@ impl Nodes {
     pub fn iter_with_values<'a, T>(
         &'a self,
         values: &'a TreeValues<T>,
-    ) -> impl Iterator<Item = (&Node, &Box<[u16]>, &T)> {
+    ) -> impl Iterator<Item = (&'a Node, &'a Box<[u16]>, &'a T)> {
         self.inner.iter().filter_map(|node| {
             let (path, value) = values.get(node.value)?;
             Some((node, path, value))
@@ -542,6 +585,36 @@ mod test {
         assert_eq!(*tree.get_ref_by_id(node_id).unwrap(), 123);
     }
 
+    #[test]
+    fn reset_ids() {
+        let mut tree = Tree::empty();
+        let first = tree.insert(root_node()).commit_child(1).unwrap();
+        tree.insert(root_node()).commit_child(2).unwrap();
+        tree.remove(&[0]);
+
+        tree.reset();
+        assert!(tree.get_ref_by_id(first).is_none());
+        let after_reset = tree.insert(root_node()).commit_child(3).unwrap();
+        assert_eq!(first, after_reset);
+    }
+
+    #[test]
+    fn move_child() {
+        let mut tree = Tree::empty();
+        let a = tree.insert(root_node()).commit_child('a').unwrap();
+        let b = tree.insert(root_node()).commit_child('b').unwrap();
+        let c = tree.insert(root_node()).commit_child('c').unwrap();
+        let a_child = tree.insert(&[0]).commit_child('x').unwrap();
+
+        tree.move_child(root_node(), 0, 2);
+
+        assert_eq!(tree.path_ref(b), &[0]);
+        assert_eq!(tree.path_ref(c), &[1]);
+        assert_eq!(tree.path_ref(a), &[2]);
+        assert_eq!(tree.path_ref(a_child), &[2, 0]);
+        assert_eq!(*tree.get_ref_by_path(&[2, 0]).unwrap(), 'x');
+    }
+
     #[test]
     fn insert_without_commit() {
         let mut tree = Tree::<()>::empty();
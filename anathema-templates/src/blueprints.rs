@@ -11,14 +11,59 @@ pub struct Single {
     pub ident: Rc<str>,
     pub children: Vec<Blueprint>,
     pub attributes: SmallMap<Rc<str>, Expression>,
+    /// The attributes before evaluation.
+    /// This is empty unless `Document::preserve_source` is set.
+    pub source_attributes: SmallMap<Rc<str>, Expression>,
+    pub events: Vec<EventBinding>,
+    /// Maps spread onto the element that are only known at runtime,
+    /// e.g `node [...state.attributes]`.
+    /// The keys of the maps are expanded into attributes when the element is evaluated,
+    /// but never override the attributes set in the template.
+    pub spread: Vec<Expression>,
     pub value: Option<Expression>,
+    /// The attributes, the value and all the children are constant,
+    /// so the size of the element never changes.
+    pub is_static: bool,
+}
+
+impl Single {
+    /// Mark the element as static if the attributes, the value
+    /// and all the children are constant.
+    pub(crate) fn update_static(&mut self) {
+        self.is_static = self.value.as_ref().is_none_or(Expression::is_constant)
+            && self.spread.is_empty()
+            && self.attributes.iter().all(|(_, expr)| expr.is_constant())
+            && self.children.iter().all(Blueprint::is_static);
+    }
+}
+
+/// An event bound to a handler using an `on_<event>` attribute.
+/// ```text
+/// @input [on_submit: save]
+/// @input [on_key: {enter: save, esc: cancel}]
+/// ```
+/// The first binding has no argument, while the `key` bindings
+/// have the arguments `enter` and `esc`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventBinding {
+    pub event: Rc<str>,
+    pub arg: Option<Rc<str>>,
+    pub handler: Rc<str>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct For {
     pub binding: Rc<str>,
+    /// The name of the index of the current iteration, e.g `i` in `for (i, item) in items`
+    pub index: Option<Rc<str>>,
+    /// Constant data is folded when the template is compiled,
+    /// anything else (e.g state) is resolved at runtime.
     pub data: Expression,
+    /// Used to match iterations to values when the collection changes
+    pub key: Option<Expression>,
     pub body: Vec<Blueprint>,
+    /// Rendered in place of the body when the collection is empty
+    pub empty_body: Vec<Blueprint>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -44,6 +89,10 @@ pub struct Component {
     pub id: WidgetComponentId,
     pub body: Vec<Blueprint>,
     pub attributes: SmallMap<Rc<str>, Expression>,
+    /// The attributes before evaluation.
+    /// This is empty unless `Document::preserve_source` is set.
+    pub source_attributes: SmallMap<Rc<str>, Expression>,
+    pub events: Vec<EventBinding>,
     pub state: Option<Rc<HashMap<Rc<str>, Expression>>>,
     pub assoc_functions: Vec<(StringId, StringId)>,
     pub parent: Option<WidgetComponentId>,
@@ -58,6 +107,17 @@ pub enum Blueprint {
     Component(Component),
 }
 
+impl Blueprint {
+    /// Only single elements can be static,
+    /// as loops, control flow and components depend on state.
+    pub fn is_static(&self) -> bool {
+        match self {
+            Self::Single(single) => single.is_static,
+            _ => false,
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! single {
     ($ident:expr) => {
@@ -65,15 +125,24 @@ macro_rules! single {
             ident: $ident.into(),
             children: vec![],
             attributes: SmallMap::empty(),
+            source_attributes: SmallMap::empty(),
+            events: vec![],
+            spread: vec![],
             value: None,
+            is_static: true,
         })
     };
-    ($ident:expr, $children:expr) => {
+    ($ident:expr, $children:expr) => {{
+        let children: Vec<$crate::blueprints::Blueprint> = $children;
         $crate::blueprints::Blueprint::Single(Single {
             ident: $ident.into(),
-            children: $children,
+            is_static: children.iter().all($crate::blueprints::Blueprint::is_static),
+            children,
             attributes: SmallMap::empty(),
+            source_attributes: SmallMap::empty(),
+            events: vec![],
+            spread: vec![],
             value: None,
         })
-    };
+    }};
 }
@@ -1,5 +1,7 @@
+use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
 use std::path::PathBuf;
+use std::rc::Rc;
 
 use anathema_store::slab::Index;
 use anathema_store::smallmap::SmallMap;
@@ -8,10 +10,11 @@ use anathema_store::storage::strings::{StringId, Strings};
 use anathema_store::storage::Storage;
 
 use crate::blueprints::Blueprint;
-use crate::error::{Error, Result};
+use crate::error::{Error, Result, Warning};
+use crate::expressions::Expression;
 use crate::statements::eval::Scope;
 use crate::statements::parser::Parser;
-use crate::statements::{Context, Statements};
+use crate::statements::{Context, Macro, Statements};
 use crate::token::Tokens;
 use crate::variables::Variables;
 use crate::Lexer;
@@ -72,6 +75,18 @@ impl From<&str> for SourceKind {
     }
 }
 
+/// What to do when a template uses a component that was never added.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum OnMissingComponent {
+    /// Fail to compile the template with `Error::MissingComponent`
+    #[default]
+    Error,
+    /// Leave the component out of the template
+    Skip,
+    /// Render the named component instead
+    Fallback(String),
+}
+
 pub(crate) enum ComponentSource {
     File { path: PathBuf, template: String },
     InMemory(String),
@@ -102,6 +117,16 @@ impl From<usize> for WidgetComponentId {
 pub(crate) struct ComponentTemplates {
     dependencies: Stack<WidgetComponentId>,
     components: Storage<WidgetComponentId, String, ComponentSource>,
+    pub(crate) on_missing: OnMissingComponent,
+    pub(crate) warnings: Vec<Warning>,
+    pub(crate) preserve_source: bool,
+    pub(crate) flags: HashSet<Rc<str>>,
+    // Attributes declared with `require` in each component
+    required: HashMap<WidgetComponentId, Vec<Rc<str>>>,
+    // Macros are shared by the document and every component
+    pub(crate) macros: SmallMap<StringId, Macro>,
+    // The macros that are currently being expanded
+    pub(crate) expanding_macros: Vec<StringId>,
 }
 
 impl ComponentTemplates {
@@ -109,6 +134,13 @@ impl ComponentTemplates {
         Self {
             dependencies: Stack::empty(),
             components: Storage::empty(),
+            on_missing: OnMissingComponent::Error,
+            warnings: vec![],
+            preserve_source: false,
+            flags: HashSet::new(),
+            required: HashMap::new(),
+            macros: SmallMap::empty(),
+            expanding_macros: vec![],
         }
     }
 
@@ -121,6 +153,25 @@ impl ComponentTemplates {
         self.components.insert(ident, template)
     }
 
+    /// Resolve a component that was never added, using the fallback if there is one.
+    /// Returns `None` if the component should be skipped.
+    pub(crate) fn resolve(&mut self, id: WidgetComponentId) -> Result<Option<WidgetComponentId>> {
+        let name = match self.components.get(id) {
+            Some((name, ComponentSource::Empty)) => name.clone(),
+            _ => return Ok(Some(id)),
+        };
+
+        let fallback = match &self.on_missing {
+            OnMissingComponent::Error => return Err(Error::MissingComponent(name)),
+            OnMissingComponent::Skip => None,
+            OnMissingComponent::Fallback(fallback) => Some(fallback.clone()),
+        };
+
+        let id = fallback.as_ref().map(|fallback| self.insert_id(fallback));
+        self.warnings.push(Warning::MissingComponent { name, fallback });
+        Ok(id)
+    }
+
     pub(crate) fn load(
         &mut self,
         parent_id: WidgetComponentId,
@@ -129,29 +180,29 @@ impl ComponentTemplates {
         strings: &mut Strings,
     ) -> Result<Vec<Blueprint>> {
         if self.dependencies.contains(&parent_id) {
-            return Err(Error::CircularDependency);
+            // The chain starts and ends with the component that includes itself
+            let chain = self
+                .dependencies
+                .iter()
+                .skip_while(|id| **id != parent_id)
+                .chain([&parent_id])
+                .map(|id| self.components.get_unchecked(*id).0.clone())
+                .collect();
+            return Err(Error::ComponentCycle(chain));
         }
 
+        // The template is copied rather than removed, so the component
+        // can still be found by name while its own template is compiled.
+        let template = match self.components.get(parent_id) {
+            Some((_, ComponentSource::File { template, .. } | ComponentSource::InMemory(template))) => template.clone(),
+            Some((key, ComponentSource::Empty)) => return Err(Error::MissingComponent(key.clone())),
+            None => unreachable!("a component entry exists if it's mentioned in the template, even if the component it self doesn't exist"),
+        };
+
         self.dependencies.push(parent_id);
+        self.required.remove(&parent_id);
 
-        let ret = match self.components.remove(parent_id) {
-            Some((key, component_src)) => {
-                let template = match &component_src {
-                    ComponentSource::File { template, .. } => template,
-                    ComponentSource::InMemory(template) => template,
-                    ComponentSource::Empty => return Err(Error::MissingComponent(key)),
-                };
-                let ret = self.compile(template, globals, slots, strings, parent_id);
-                // This will re-insert the component in the same location
-                // as it was removed from since nothing else has
-                // written to the component storage since the component
-                // was removed.
-                let new_id = self.components.insert(key, component_src);
-                assert_eq!(parent_id, new_id);
-                ret
-            }
-            _ => unreachable!("a component entry exists if it's mentioned in the template, even if the component it self doesn't exist"),
-        };
+        let ret = self.compile(&template, globals, slots, strings, parent_id);
 
         self.dependencies.pop();
 
@@ -177,6 +228,29 @@ impl ComponentTemplates {
         Scope::new(statements).eval(&mut context)
     }
 
+    pub(crate) fn require(&mut self, id: WidgetComponentId, attribute: Rc<str>) {
+        self.required.entry(id).or_default().push(attribute);
+    }
+
+    // Ensure every attribute required by the component is set
+    pub(crate) fn check_required(
+        &self,
+        id: WidgetComponentId,
+        attributes: &SmallMap<Rc<str>, Expression>,
+    ) -> Result<()> {
+        let Some(required) = self.required.get(&id) else { return Ok(()) };
+        match required
+            .iter()
+            .find(|attribute| attributes.get(&***attribute).is_none())
+        {
+            Some(attribute) => Err(Error::MissingRequiredAttribute {
+                component: self.components.get_unchecked(id).0.clone(),
+                attribute: attribute.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
     pub(crate) fn file_paths(&self) -> impl Iterator<Item = &PathBuf> {
         self.components.iter().filter_map(|(_, (_, src))| match src {
             ComponentSource::File { path, .. } => Some(path),
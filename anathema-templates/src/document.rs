@@ -1,15 +1,17 @@
 use std::fs::read_to_string;
 use std::path::PathBuf;
+use std::rc::Rc;
 
 use anathema_store::smallmap::SmallMap;
 use anathema_store::storage::strings::Strings;
 
 use crate::blueprints::Blueprint;
-use crate::components::{ComponentSource, ComponentTemplates, SourceKind};
-use crate::error::{Error, Result};
+use crate::components::{ComponentSource, ComponentTemplates, OnMissingComponent, SourceKind};
+use crate::error::{Error, Result, Warning};
+use crate::expressions::Expression;
 use crate::statements::eval::Scope;
-use crate::statements::parser::Parser;
-use crate::statements::{Context, Statements};
+use crate::statements::parser::{Parser, ParserScope};
+use crate::statements::{Context, Statement, Statements};
 use crate::token::Tokens;
 use crate::variables::Variables;
 use crate::{Globals, Lexer};
@@ -23,8 +25,20 @@ pub struct Document {
     template: String,
     pub strings: Strings,
     globals: Variables,
+    host_globals: SmallMap<Rc<str>, Expression>,
     components: ComponentTemplates,
     pub hot_reload: bool,
+    /// Keep the unevaluated attributes in the blueprints,
+    /// e.g for tooling that needs the original expressions.
+    pub preserve_source: bool,
+    // The statements parsed by `append_source`
+    chunks: Option<Chunks>,
+}
+
+#[derive(Default)]
+struct Chunks {
+    statements: Vec<Statement>,
+    scope: ParserScope,
 }
 
 impl Document {
@@ -34,9 +48,69 @@ impl Document {
             template,
             strings: Strings::empty(),
             globals: Variables::default(),
+            host_globals: SmallMap::empty(),
             components: ComponentTemplates::new(),
             hot_reload: true,
+            preserve_source: false,
+            chunks: None,
+        }
+    }
+
+    /// Parse a chunk of source and append the statements to the document,
+    /// e.g when generating a large template in parts.
+    ///
+    /// The parser continues from the scope of the previous chunk,
+    /// so an indented chunk is a child of the last node of the previous chunk.
+    /// The template of the document is parsed as the first chunk.
+    ///
+    /// An invalid chunk is reported right away and is not appended.
+    /// ```
+    /// # use anathema_templates::Document;
+    /// let mut doc = Document::new("vstack");
+    /// doc.append_source("    text 'a'").unwrap();
+    /// doc.append_source("    text 'b'").unwrap();
+    /// doc.compile().unwrap();
+    /// ```
+    pub fn append_source(&mut self, src: &str) -> Result<()> {
+        if self.chunks.is_none() {
+            self.strings = Strings::empty();
+            self.chunks = Some(Chunks::default());
+
+            let template = self.template.clone();
+            if let Err(e) = self.parse_chunk(&template) {
+                self.chunks = None;
+                return Err(e);
+            }
+        }
+
+        self.parse_chunk(src)
+    }
+
+    fn parse_chunk(&mut self, src: &str) -> Result<()> {
+        // A chunk without statements has no indent to continue from
+        if src.trim().is_empty() {
+            return Ok(());
+        }
+
+        let chunks = self.chunks.get_or_insert_with(Chunks::default);
+        let tokens = Lexer::new(src, &mut self.strings).collect::<Result<Vec<_>>>()?;
+        let tokens = Tokens::new(tokens, src.len());
+        let mut parser = Parser::resume(
+            tokens,
+            &mut self.strings,
+            src,
+            &mut self.components,
+            chunks.scope.clone(),
+        );
+
+        let mut statements = parser.by_ref().collect::<Result<Vec<_>>>()?;
+        if statements.last() == Some(&Statement::Eof) {
+            statements.pop();
         }
+
+        chunks.scope = parser.into_scope();
+        chunks.statements.append(&mut statements);
+        Ok(())
     }
 
     #[allow(private_bounds)]
@@ -55,15 +129,83 @@ impl Document {
         Ok(id.into())
     }
 
+    /// Set a global value from the host, accessible by every template.
+    /// Globals declared in the templates take precedence.
+    /// ```
+    /// # use anathema_templates::Document;
+    /// let mut doc = Document::new("text unicode");
+    /// doc.set_global("unicode", false);
+    /// ```
+    pub fn set_global(&mut self, ident: impl Into<Rc<str>>, value: impl Into<Expression>) {
+        self.host_globals.set(ident.into(), value.into());
+    }
+
+    /// Set what happens when the template uses a component that was never added.
+    /// By default this is an error.
+    /// ```
+    /// # use anathema_templates::{Document, OnMissingComponent, ToSourceKind};
+    /// let mut doc = Document::new("@plugin");
+    /// doc.add_component("placeholder", "text 'not installed'".to_template()).unwrap();
+    /// doc.on_missing_component(OnMissingComponent::Fallback("placeholder".into()));
+    /// doc.compile().unwrap();
+    /// assert_eq!(doc.warnings().len(), 1);
+    /// ```
+    pub fn on_missing_component(&mut self, on_missing: OnMissingComponent) {
+        self.components.on_missing = on_missing;
+    }
+
+    /// Set a flag, including every `#[if flag]` section of the templates.
+    /// ```
+    /// # use anathema_templates::Document;
+    /// let mut doc = Document::new("#[if debug]\n    text 'debug'");
+    /// doc.set_flag("debug");
+    /// ```
+    pub fn set_flag(&mut self, flag: impl Into<Rc<str>>) {
+        self.components.flags.insert(flag.into());
+    }
+
+    /// Unset a flag, excluding every `#[if flag]` section of the templates
+    pub fn unset_flag(&mut self, flag: &str) {
+        self.components.flags.remove(flag);
+    }
+
+    /// Warnings from the last compilation
+    pub fn warnings(&self) -> &[Warning] {
+        &self.components.warnings
+    }
+
     pub fn compile(&mut self) -> Result<(Blueprint, Globals)> {
-        self.strings = Strings::empty();
-        self.globals = Variables::default();
+        self.compile_with_pass(|_| {})
+    }
 
-        let tokens = Lexer::new(&self.template, &mut self.strings).collect::<Result<Vec<_>>>()?;
-        let tokens = Tokens::new(tokens, self.template.len());
-        let parser = Parser::new(tokens, &mut self.strings, &self.template, &mut self.components);
+    /// Compile the template and run a pass over the compiled blueprints,
+    /// e.g to hoist constants or inject debug attributes.
+    /// The first blueprint, after the pass, is the root.
+    pub fn compile_with_pass(&mut self, mut pass: impl FnMut(&mut Vec<Blueprint>)) -> Result<(Blueprint, Globals)> {
+        self.components.warnings.clear();
+        self.components.macros = SmallMap::empty();
+        self.components.preserve_source = self.preserve_source;
+        self.globals = Variables::default();
+        for (ident, value) in self.host_globals.iter() {
+            self.globals.declare(ident.clone(), value.clone());
+        }
 
-        let statements = parser.collect::<Result<Statements>>()?;
+        let statements = match &self.chunks {
+            // The strings of the appended statements are kept
+            Some(chunks) => chunks
+                .statements
+                .iter()
+                .cloned()
+                .chain(chunks.scope.finish())
+                .collect::<Statements>(),
+            None => {
+                self.strings = Strings::empty();
+                let tokens = Lexer::new(&self.template, &mut self.strings).collect::<Result<Vec<_>>>()?;
+                let tokens = Tokens::new(tokens, self.template.len());
+                let parser = Parser::new(tokens, &mut self.strings, &self.template, &mut self.components);
+                parser.collect::<Result<Statements>>()?
+            }
+        };
 
         let mut context = Context {
             globals: &mut self.globals,
@@ -74,6 +216,7 @@ impl Document {
         };
 
         let mut blueprints = Scope::new(statements).eval(&mut context)?;
+        pass(&mut blueprints);
         match blueprints.is_empty() {
             true => Err(Error::EmptyTemplate),
             false => Ok((blueprints.remove(0), self.globals.take().into())),
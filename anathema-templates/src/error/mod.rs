@@ -10,10 +10,38 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 #[derive(Debug)]
 pub enum Error {
     ParseError(ParseError),
-    CircularDependency,
+    /// Components that include each other.
+    /// The first and the last name are the same component.
+    ComponentCycle(Vec<String>),
     MissingComponent(String),
     EmptyTemplate,
     EmptyBody,
+    InvalidStatement(String),
+    /// A component is missing an attribute declared with `require`
+    MissingRequiredAttribute {
+        component: String,
+        attribute: String,
+    },
+    /// Attributes of a node reference each other in a cycle.
+    /// The keys are in the order they reference each other,
+    /// starting and ending with the same key.
+    CircularAttribute(Vec<String>),
+    /// The same attribute key is used twice on one node.
+    /// The spans are the line and column of both keys.
+    DuplicateAttribute {
+        key: String,
+        first: (usize, usize),
+        second: (usize, usize),
+    },
+    /// The state of a component is neither a map nor omitted,
+    /// e.g `@comp 5`. This holds the state.
+    InvalidComponentState(String),
+    /// An error caused by the component used on the given line and column
+    InComponent {
+        line: usize,
+        col: usize,
+        error: Box<Error>,
+    },
     Io(std::io::Error),
 }
 
@@ -21,10 +49,32 @@ impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Error::ParseError(err) => write!(f, "{err}"),
-            Error::CircularDependency => write!(f, "circular dependency"),
+            Error::ComponentCycle(chain) => {
+                let chain = chain.iter().map(|name| format!("`@{name}`")).collect::<Vec<_>>();
+                write!(f, "components include each other: {}", chain.join(" -> "))
+            }
             Error::MissingComponent(name) => write!(f, "`@{name}` is not a registered component"),
             Error::EmptyTemplate => write!(f, "empty template"),
             Error::EmptyBody => write!(f, "if or else node has no children"),
+            Error::InvalidStatement(msg) => write!(f, "invalid statement: {msg}"),
+            Error::MissingRequiredAttribute { component, attribute } => {
+                write!(f, "`@{component}` requires the attribute `{attribute}`")
+            }
+            Error::CircularAttribute(keys) => {
+                let cycle = keys.iter().map(|key| format!("`{key}`")).collect::<Vec<_>>();
+                write!(f, "attributes reference each other in a cycle: {}", cycle.join(" -> "))
+            }
+            Error::DuplicateAttribute { key, first, second } => write!(
+                f,
+                "duplicate attribute `{key}` on line {} (col {}), first set on line {} (col {})",
+                second.0, second.1, first.0, first.1
+            ),
+            Error::InvalidComponentState(state) => {
+                write!(f, "the state of a component has to be a map, found `{state}`")
+            }
+            Error::InComponent { line, col, error } => {
+                write!(f, "{error}, in the component on line {line} (col {col})")
+            }
             Error::Io(err) => write!(f, "{err}"),
         }
     }
@@ -32,6 +82,31 @@ impl Display for Error {
 
 impl StdError for Error {}
 
+/// Problems found while compiling a template that did not prevent
+/// the template from compiling.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// An unknown component was replaced by the fallback component,
+    /// or skipped if there is no fallback.
+    MissingComponent { name: String, fallback: Option<String> },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::MissingComponent {
+                name,
+                fallback: Some(fallback),
+            } => {
+                write!(f, "`@{name}` is not a registered component, using `@{fallback}`")
+            }
+            Warning::MissingComponent { name, fallback: None } => {
+                write!(f, "`@{name}` is not a registered component and was skipped")
+            }
+        }
+    }
+}
+
 impl From<ParseError> for Error {
     fn from(value: ParseError) -> Self {
         Self::ParseError(value)
@@ -66,6 +66,7 @@ impl Display for ParseError {
             ParseErrorKind::InvalidOperator(_op) => "invalid operator: {op}".into(),
             ParseErrorKind::UnexpectedToken(_msg) => "unexpected token: {msg}".into(),
             ParseErrorKind::InvalidKey => todo!(),
+            ParseErrorKind::InvalidFormatSpec(spec) => format!("unsupported format spec: `{spec}`"),
         };
 
         writeln!(f, "error on line {start_line}: {msg}")?;
@@ -101,4 +102,5 @@ pub enum ParseErrorKind {
     InvalidOperator(Operator),
     UnexpectedToken(String),
     InvalidKey,
+    InvalidFormatSpec(String),
 }
@@ -3,6 +3,7 @@ use std::rc::Rc;
 
 use anathema_store::storage::strings::Strings;
 
+use super::format::interpolate;
 use super::parser::Expr;
 use super::{Expression, Op};
 use crate::error::{ParseErrorKind, Result};
@@ -20,6 +21,10 @@ pub fn eval(expr: Expr, strings: &Strings) -> Result<Expression, ParseErrorKind>
             let string = strings.get_unchecked(string_id);
             Expression::Str(Rc::from(string))
         }
+        Expr::FormatStr(string_id) => {
+            let string = strings.get_unchecked(string_id);
+            interpolate(&string)?
+        }
         Expr::Array { lhs, index } => {
             let lhs = eval(*lhs, strings)?;
             let index = eval(*index, strings)?;
@@ -116,6 +121,11 @@ pub fn eval(expr: Expr, strings: &Strings) -> Result<Expression, ParseErrorKind>
                 args: args.into_boxed_slice(),
             }
         }
+        Expr::Conditional { cond, truthy, falsy } => Expression::Conditional {
+            cond: eval(*cond, strings)?.into(),
+            truthy: eval(*truthy, strings)?.into(),
+            falsy: eval(*falsy, strings)?.into(),
+        },
     };
 
     Ok(output)
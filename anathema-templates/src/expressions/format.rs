@@ -0,0 +1,246 @@
+use std::fmt::Display;
+use std::rc::Rc;
+
+use anathema_store::storage::strings::Strings;
+
+use super::parser::parse_expr;
+use super::Expression;
+use crate::error::{Error, ParseErrorKind, Result};
+use crate::lexer::Lexer;
+use crate::token::Tokens;
+
+/// Format spec for interpolated values: `[0][width][.precision]`
+/// ```text
+/// f"{value:.2}" // two decimals
+/// f"{n:04}"     // pad with zeros to a width of four
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct FormatSpec {
+    pub zero: bool,
+    pub width: usize,
+    pub precision: Option<usize>,
+}
+
+impl FormatSpec {
+    /// Parse a format spec, returning `None` if the spec is not supported.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (spec, zero) = match spec.strip_prefix('0') {
+            Some(spec) => (spec, true),
+            None => (spec, false),
+        };
+
+        let (width, precision) = match spec.split_once('.') {
+            Some((width, precision)) => (width, Some(parse_digits(precision)?)),
+            None => (spec, None),
+        };
+
+        let width = match width {
+            "" => 0,
+            width => parse_digits(width)?,
+        };
+
+        Some(Self { zero, width, precision })
+    }
+
+    pub fn format_int(&self, value: i64) -> String {
+        match self.precision {
+            Some(_) => self.format_float(value as f64),
+            None if self.zero => format!("{value:0width$}", width = self.width),
+            None => format!("{value:width$}", width = self.width),
+        }
+    }
+
+    pub fn format_float(&self, value: f64) -> String {
+        let width = self.width;
+        match (self.zero, self.precision) {
+            (true, Some(precision)) => format!("{value:0width$.precision$}"),
+            (false, Some(precision)) => format!("{value:width$.precision$}"),
+            (true, None) => format!("{value:0width$}"),
+            (false, None) => format!("{value:width$}"),
+        }
+    }
+
+    pub fn format_str(&self, value: &str) -> String {
+        format!("{value:width$}", width = self.width)
+    }
+}
+
+impl Display for FormatSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.zero {
+            write!(f, "0")?;
+        }
+        if self.width > 0 {
+            write!(f, "{}", self.width)?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ".{precision}")?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_digits(s: &str) -> Option<usize> {
+    match s.bytes().all(|b| b.is_ascii_digit()) {
+        true => s.parse().ok(),
+        false => None,
+    }
+}
+
+/// Split a format string literal, e.g `f"{value:.2} kg"`, into text and
+/// interpolated expressions. `{{` and `}}` are written as `{` and `}`.
+///
+/// Braces that can't start or end an interpolation, e.g a `{` without a closing
+/// brace, a lone `}` or `{}`, are kept as text.
+///
+/// A string without any interpolation is returned as `Expression::Str`.
+pub(crate) fn interpolate(src: &str) -> Result<Expression, ParseErrorKind> {
+    if !src.contains(['{', '}']) {
+        return Ok(Expression::Str(src.into()));
+    }
+
+    let mut fragments = vec![];
+    let mut text = String::new();
+    let mut chars = src.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '{' if chars.next_if(|(_, c)| *c == '{').is_some() => text.push('{'),
+            '}' if chars.next_if(|(_, c)| *c == '}').is_some() => text.push('}'),
+            '{' => {
+                let start = index + 1;
+                let end = match closing_brace(src, start) {
+                    Some(end) if !src[start..end].trim().is_empty() => end,
+                    _ => {
+                        text.push('{');
+                        continue;
+                    }
+                };
+                while chars.next_if(|(i, _)| *i <= end).is_some() {}
+
+                if !text.is_empty() {
+                    let text = std::mem::take(&mut text);
+                    fragments.push((Expression::Str(text.into()), None));
+                }
+                fragments.push(interpolated_value(&src[start..end])?);
+            }
+            c => text.push(c),
+        }
+    }
+
+    if fragments.is_empty() {
+        return Ok(Expression::Str(text.into()));
+    }
+
+    if !text.is_empty() {
+        fragments.push((Expression::Str(text.into()), None));
+    }
+
+    Ok(Expression::Format(Rc::from(fragments)))
+}
+
+// Find the brace closing the interpolation,
+// ignoring any braces in nested maps or strings.
+fn closing_brace(src: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+
+    for (index, c) in src[start..].char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => {}
+            ('{' | '[' | '(', None) => depth += 1,
+            ('}', None) if depth == 0 => return Some(start + index),
+            ('}' | ']' | ')', None) => depth -= 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+// Parse `expr` or `expr:spec`
+fn interpolated_value(src: &str) -> Result<(Expression, Option<FormatSpec>), ParseErrorKind> {
+    let (src, spec) = match top_level_colon(src) {
+        Some(index) => {
+            let spec = &src[index + 1..];
+            let spec = FormatSpec::parse(spec).ok_or_else(|| ParseErrorKind::InvalidFormatSpec(spec.into()))?;
+            (&src[..index], Some(spec))
+        }
+        None => (src, None),
+    };
+
+    let mut strings = Strings::empty();
+    let tokens = match Lexer::new(src, &mut strings).collect::<Result<_>>() {
+        Ok(tokens) => tokens,
+        Err(Error::ParseError(err)) => return Err(err.kind),
+        Err(_) => return Err(ParseErrorKind::InvalidToken { expected: "expression" }),
+    };
+    let mut tokens = Tokens::new(tokens, src.len());
+    let expr = parse_expr(&mut tokens, &strings)?;
+
+    Ok((expr, spec))
+}
+
+fn top_level_colon(src: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut colon = None;
+
+    for (index, c) in src.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => {}
+            ('{' | '[' | '(', None) => depth += 1,
+            ('}' | ']' | ')', None) => depth -= 1,
+            (':', None) if depth == 0 => colon = Some(index),
+            _ => {}
+        }
+    }
+
+    colon
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_spec() {
+        let spec = FormatSpec::parse("04").unwrap();
+        assert_eq!(spec.format_int(7), "0007");
+
+        let spec = FormatSpec::parse(".2").unwrap();
+        assert_eq!(spec.format_float(1.23456), "1.23");
+
+        let spec = FormatSpec::parse("06.1").unwrap();
+        assert_eq!(spec.format_float(2.75), "0002.8");
+        assert_eq!(spec.to_string(), "06.1");
+    }
+
+    #[test]
+    fn unsupported_spec() {
+        assert!(FormatSpec::parse("x").is_none());
+        assert!(FormatSpec::parse(".").is_none());
+        assert!(FormatSpec::parse(">4").is_none());
+
+        let err = interpolate("{a:x}").unwrap_err();
+        assert_eq!(err, ParseErrorKind::InvalidFormatSpec("x".into()));
+    }
+
+    #[test]
+    fn escaped_braces() {
+        let expr = interpolate("{{a}}").unwrap();
+        assert_eq!(expr, Expression::Str("{a}".into()));
+    }
+
+    #[test]
+    fn unmatched_braces_are_text() {
+        assert_eq!(interpolate("{").unwrap(), Expression::Str("{".into()));
+        assert_eq!(interpolate("{a").unwrap(), Expression::Str("{a".into()));
+        assert_eq!(interpolate("a}b").unwrap(), Expression::Str("a}b".into()));
+        assert_eq!(interpolate("{}").unwrap(), Expression::Str("{}".into()));
+    }
+}
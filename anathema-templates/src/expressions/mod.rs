@@ -2,9 +2,11 @@ use std::collections::HashMap;
 use std::fmt::Display;
 use std::rc::Rc;
 
+pub use self::format::FormatSpec;
 use crate::primitives::Primitive;
 
 pub(crate) mod eval;
+pub(crate) mod format;
 pub(crate) mod parser;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -33,6 +35,8 @@ pub enum Expression {
     // Value types
     Primitive(Primitive),
     Str(Rc<str>),
+    // Interpolated string, e.g `f"{value:.2} kg"`
+    Format(Rc<[(Self, Option<FormatSpec>)]>),
     List(Rc<[Self]>),
     Map(Rc<HashMap<Rc<str>, Self>>),
 
@@ -43,6 +47,16 @@ pub enum Expression {
     // Conditionals
     Equality(Box<Self>, Box<Self>, Equality),
 
+    // The value, or nothing if the value is false
+    Optional(Box<Self>),
+
+    // `cond ? truthy : falsy`
+    Conditional {
+        cond: Box<Self>,
+        truthy: Box<Self>,
+        falsy: Box<Self>,
+    },
+
     // Lookup
     Ident(Rc<str>),
     Index(Box<Self>, Box<Self>),
@@ -51,7 +65,10 @@ pub enum Expression {
     Op(Box<Self>, Box<Self>, Op),
 
     // Function call
-    Call { fun: Box<Self>, args: Box<[Self]> },
+    Call {
+        fun: Box<Self>,
+        args: Box<[Self]>,
+    },
 }
 
 impl From<Box<Expression>> for Expression {
@@ -72,14 +89,45 @@ impl From<&str> for Expression {
     }
 }
 
+impl Expression {
+    /// True if the expression evaluates to the same value every time,
+    /// i.e it does not contain any lookups or function calls.
+    pub fn is_constant(&self) -> bool {
+        match self {
+            Self::Primitive(_) | Self::Str(_) => true,
+            Self::Format(fragments) => fragments.iter().all(|(expr, _)| expr.is_constant()),
+            Self::List(list) => list.iter().all(Self::is_constant),
+            Self::Map(map) => map.values().all(Self::is_constant),
+            Self::Not(expr) | Self::Negative(expr) | Self::Optional(expr) => expr.is_constant(),
+            Self::Equality(lhs, rhs, _) | Self::Op(lhs, rhs, _) => lhs.is_constant() && rhs.is_constant(),
+            Self::Conditional { cond, truthy, falsy } => {
+                cond.is_constant() && truthy.is_constant() && falsy.is_constant()
+            }
+            Self::Ident(_) | Self::Index(..) | Self::Call { .. } => false,
+        }
+    }
+}
+
 impl Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Primitive(val) => write!(f, "{val}"),
             Self::Str(val) => write!(f, "{val}"),
+            Self::Format(fragments) => {
+                for (expr, spec) in fragments.iter() {
+                    match (expr, spec) {
+                        (Self::Str(s), None) => write!(f, "{}", s.replace('{', "{{").replace('}', "}}"))?,
+                        (expr, None) => write!(f, "{{{expr}}}")?,
+                        (expr, Some(spec)) => write!(f, "{{{expr}:{spec}}}")?,
+                    }
+                }
+                Ok(())
+            }
             Self::Ident(s) => write!(f, "{s}"),
             Self::Index(lhs, idx) => write!(f, "{lhs}[{idx}]"),
             Self::Not(expr) => write!(f, "!{expr}"),
+            Self::Optional(expr) => write!(f, "{expr}?"),
+            Self::Conditional { cond, truthy, falsy } => write!(f, "{cond} ? {truthy} : {falsy}"),
             Self::Negative(expr) => write!(f, "-{expr}"),
             Self::Op(lhs, rhs, op) => {
                 let op = match op {
@@ -240,3 +288,7 @@ pub fn and(lhs: Box<Expression>, rhs: Box<Expression>) -> Box<Expression> {
 pub fn or(lhs: Box<Expression>, rhs: Box<Expression>) -> Box<Expression> {
     Expression::Equality(lhs, rhs, Equality::Or).into()
 }
+
+pub fn conditional(cond: Box<Expression>, truthy: Box<Expression>, falsy: Box<Expression>) -> Box<Expression> {
+    Expression::Conditional { cond, truthy, falsy }.into()
+}
@@ -10,6 +10,7 @@ use crate::token::{Kind, Operator, Tokens, Value};
 
 pub(crate) mod prec {
     pub const INITIAL: u8 = 0;
+    pub const TERNARY: u8 = 1;
     pub const CONDITIONAL: u8 = 2;
     pub const EQUALITY: u8 = 3;
     pub const LOGICAL: u8 = 4;
@@ -31,6 +32,7 @@ fn get_precedence(op: Operator) -> u8 {
         }
         Operator::EqualEqual | Operator::NotEqual => prec::EQUALITY,
         Operator::Or | Operator::And => prec::CONDITIONAL,
+        Operator::Question => prec::TERNARY,
 
         _ => prec::INITIAL,
     }
@@ -50,6 +52,7 @@ pub(crate) enum Expr {
     Primitive(Primitive),
     Ident(StringId),
     Str(StringId),
+    FormatStr(StringId),
     Call {
         fun: Box<Expr>,
         args: Vec<Expr>,
@@ -60,6 +63,11 @@ pub(crate) enum Expr {
     },
     List(Vec<Expr>),
     Map(Vec<(Expr, Expr)>),
+    Conditional {
+        cond: Box<Expr>,
+        truthy: Box<Expr>,
+        falsy: Box<Expr>,
+    },
 }
 
 impl Display for Expr {
@@ -70,6 +78,7 @@ impl Display for Expr {
             Expr::Primitive(p) => write!(f, "{p}"),
             Expr::Ident(sid) => write!(f, "{sid}"),
             Expr::Str(sid) => write!(f, "\"{sid}\""),
+            Expr::FormatStr(sid) => write!(f, "f\"{sid}\""),
             Expr::Array { lhs, index } => write!(f, "{lhs}[{index}]"),
             Expr::List(list) => {
                 let s = list.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", ");
@@ -87,6 +96,7 @@ impl Display for Expr {
                 let s = args.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
                 write!(f, "{fun}({s})")
             }
+            Expr::Conditional { cond, truthy, falsy } => write!(f, "(? {cond} {truthy} {falsy})"),
         }
     }
 }
@@ -116,6 +126,7 @@ fn expr_bp(tokens: &mut Tokens, precedence: u8) -> Result<Expr, ParseErrorKind>
             Value::Primitive(val) => Expr::Primitive(val),
             Value::Ident(ident) => Expr::Ident(ident),
             Value::String(sid) => Expr::Str(sid),
+            Value::FormatString(sid) => Expr::FormatStr(sid),
         },
         Kind::Eof => panic!("unexpected eof"),
         // TODO: see panic
@@ -161,6 +172,20 @@ fn expr_bp(tokens: &mut Tokens, precedence: u8) -> Result<Expr, ParseErrorKind>
                 };
                 continue;
             }
+            Operator::Question => {
+                let truthy = expr_bp(tokens, prec::INITIAL)?;
+                let Kind::Op(Operator::Colon) = tokens.next_no_indent() else {
+                    return Err(ParseErrorKind::InvalidToken { expected: ":" });
+                };
+                // Right associative, `a ? b : c ? d : e` is `a ? b : (c ? d : e)`
+                let falsy = expr_bp(tokens, prec::INITIAL)?;
+                left = Expr::Conditional {
+                    cond: Box::new(left),
+                    truthy: Box::new(truthy),
+                    falsy: Box::new(falsy),
+                };
+                continue;
+            }
             _ => {}
         }
 
@@ -303,6 +328,18 @@ mod test {
         assert_eq!(parse(input), "(- 5 (/ 1 2))");
     }
 
+    #[test]
+    fn ternary() {
+        let input = "a ? b : c";
+        assert_eq!(parse(input), "(? <sid 0> <sid 1> <sid 2>)");
+
+        let input = "a == 1 ? b + 1 : c ? d : e";
+        assert_eq!(
+            parse(input),
+            "(? (== <sid 0> 1) (+ <sid 1> 1) (? <sid 2> <sid 3> <sid 4>))"
+        );
+    }
+
     #[test]
     fn brackets() {
         let input = "(5 + 1) * 2";
@@ -1,7 +1,7 @@
 use std::iter::Peekable;
 use std::str::CharIndices;
 
-use anathema_store::storage::strings::Strings;
+use anathema_store::storage::strings::{StringId, Strings};
 
 use crate::error::{ParseError, ParseErrorKind, Result};
 use crate::token::{Kind, Operator, Token, Value};
@@ -17,6 +17,8 @@ impl<'src, 'consts> Iterator for Lexer<'src, 'consts> {
     }
 }
 
+const BLOCK_DELIMITER: &str = "\"\"\"";
+
 pub struct Lexer<'src, 'strings> {
     pub(super) src: &'src str,
     pub(crate) strings: &'strings mut Strings,
@@ -93,6 +95,7 @@ impl<'src, 'strings> Lexer<'src, 'strings> {
             ('{', _) => Ok(Kind::Op(Operator::LCurly).to_token(index)),
             ('}', _) => Ok(Kind::Op(Operator::RCurly).to_token(index)),
             (':', _) => Ok(Kind::Op(Operator::Colon).to_token(index)),
+            ('?', _) => Ok(Kind::Op(Operator::Question).to_token(index)),
             (',', _) => Ok(Kind::Op(Operator::Comma).to_token(index)),
             ('.', _) => Ok(Kind::Op(Operator::Dot).to_token(index)),
             ('!', _) => Ok(Kind::Op(Operator::Not).to_token(index)),
@@ -108,6 +111,15 @@ impl<'src, 'strings> Lexer<'src, 'strings> {
             ('@', _) => Ok(Kind::Component.to_token(index)),
             ('$', _) => Ok(Kind::ComponentSlot.to_token(index)),
 
+            // -----------------------------------------------------------------------------
+            //     - Format string -
+            // -----------------------------------------------------------------------------
+            ('f', Some(quote @ ('"' | '\''))) => {
+                let _ = self.chars.next();
+                let string = self.take_string_id(quote, index + 1)?;
+                Ok(Kind::Value(Value::FormatString(string)).to_token(index))
+            }
+
             // -----------------------------------------------------------------------------
             //     - Ident -
             // -----------------------------------------------------------------------------
@@ -121,6 +133,7 @@ impl<'src, 'strings> Lexer<'src, 'strings> {
             // -----------------------------------------------------------------------------
             //     - String -
             // -----------------------------------------------------------------------------
+            ('"', Some('"')) if self.src[index..].starts_with(BLOCK_DELIMITER) => self.take_block_string(index),
             ('"' | '\'', _) => self.take_string(c, index),
 
             // -----------------------------------------------------------------------------
@@ -128,6 +141,11 @@ impl<'src, 'strings> Lexer<'src, 'strings> {
             // -----------------------------------------------------------------------------
             _ if c.is_whitespace() && c != '\n' => Ok(self.take_whitespace().to_token(index)),
 
+            // -----------------------------------------------------------------------------
+            //     - Directives -
+            // -----------------------------------------------------------------------------
+            ('#', Some('[')) => Ok(Kind::Directive.to_token(index)),
+
             // -----------------------------------------------------------------------------
             //     - Hex values -
             // -----------------------------------------------------------------------------
@@ -145,12 +163,16 @@ impl<'src, 'strings> Lexer<'src, 'strings> {
     }
 
     fn take_string(&mut self, start_char: char, start_index: usize) -> Result<Token> {
+        let string = self.take_string_id(start_char, start_index)?;
+        Ok(Kind::Value(Value::String(string)).to_token(start_index))
+    }
+
+    fn take_string_id(&mut self, start_char: char, start_index: usize) -> Result<StringId> {
         loop {
             let n = self.chars.next();
             match n {
                 Some((end, nc)) if nc == start_char => {
-                    let string = self.strings.push(self.src[start_index + 1..end].to_string());
-                    break Ok(Kind::Value(Value::String(string)).to_token(start_index));
+                    break Ok(self.strings.push(self.src[start_index + 1..end].to_string()));
                 }
                 Some((_, '\\')) => {
                     // escaping string terminator
@@ -173,6 +195,62 @@ impl<'src, 'strings> Lexer<'src, 'strings> {
         }
     }
 
+    // Preformatted text block:
+    // ```
+    // text """
+    //     fn main() {
+    //         print!(  "hello");
+    //     }
+    //     """
+    // ```
+    // All whitespace is preserved, except for the indentation shared by every line,
+    // the newline directly after the opening delimiter and the whitespace before the
+    // closing delimiter.
+    fn take_block_string(&mut self, start_index: usize) -> Result<Token> {
+        // Consume the remaining two quotes of the opening delimiter
+        self.chars.next();
+        self.chars.next();
+
+        let content_start = start_index + BLOCK_DELIMITER.len();
+        let Some(len) = self.src[content_start..].find(BLOCK_DELIMITER) else {
+            return Err(ParseError::new(
+                start_index..self.src.len(),
+                self.src,
+                ParseErrorKind::UnterminatedString,
+            )
+            .into());
+        };
+
+        let content_end = content_start + len;
+        while let Some((i, _)) = self.chars.peek() {
+            if *i >= content_end + BLOCK_DELIMITER.len() {
+                break;
+            }
+            self.chars.next();
+        }
+
+        let content = &self.src[content_start..content_end];
+        let content = content.strip_prefix('\n').unwrap_or(content);
+        let content = content.trim_end_matches(|c: char| c.is_whitespace() && c != '\n');
+        let content = content.strip_suffix('\n').unwrap_or(content);
+
+        let indent = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+
+        let string = content
+            .lines()
+            .map(|line| line.get(indent..).unwrap_or(line.trim_start()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let string = self.strings.push(string);
+        Ok(Kind::Value(Value::String(string)).to_token(start_index))
+    }
+
     fn take_number(&mut self, index: usize) -> Result<Token> {
         let mut end = index;
         let mut parse_float = &self.src[index..=index] == ".";
@@ -309,10 +387,16 @@ mod test {
         let mut lexer = Lexer::new(input, &mut strings);
         match lexer.next().unwrap().unwrap_err() {
             crate::error::Error::ParseError(err) => err.kind,
-            crate::error::Error::CircularDependency
+            crate::error::Error::ComponentCycle(_)
             | crate::error::Error::MissingComponent(_)
             | crate::error::Error::EmptyTemplate
             | crate::error::Error::EmptyBody
+            | crate::error::Error::InvalidStatement(_)
+            | crate::error::Error::CircularAttribute(_)
+            | crate::error::Error::MissingRequiredAttribute { .. }
+            | crate::error::Error::DuplicateAttribute { .. }
+            | crate::error::Error::InvalidComponentState(_)
+            | crate::error::Error::InComponent { .. }
             | crate::error::Error::Io(_) => panic!("invalid error"),
         }
     }
@@ -339,6 +423,7 @@ mod test {
             ("[", Kind::Op(Operator::LBracket)),
             ("]", Kind::Op(Operator::RBracket)),
             (":", Kind::Op(Operator::Colon)),
+            ("?", Kind::Op(Operator::Question)),
             (",", Kind::Op(Operator::Comma)),
             ("\n", Kind::Newline),
         ];
@@ -392,6 +477,24 @@ mod test {
         }
     }
 
+    #[test]
+    fn format_strings() {
+        let mut strings = Strings::empty();
+        let tokens = Lexer::new("f'{a:02}' f\"b\" f", &mut strings)
+            .filter_map(|token| match token.unwrap().0 {
+                Kind::Value(value) => Some(value),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let [Value::FormatString(a), Value::FormatString(b), Value::Ident(f)] = tokens[..] else {
+            panic!("invalid tokens: {tokens:?}")
+        };
+        assert_eq!(strings.get_unchecked(a), "{a:02}");
+        assert_eq!(strings.get_unchecked(b), "b");
+        assert_eq!(strings.get_unchecked(f), "f");
+    }
+
     #[test]
     fn strings() {
         let inputs = [
@@ -419,6 +522,27 @@ mod test {
         }
     }
 
+    #[test]
+    fn block_string() {
+        let input = "\"\"\"
+            fn  main() {
+                a   b
+            }
+            \"\"\"";
+        let mut strings = Strings::empty();
+        let Kind::Value(Value::String(string_id)) = Lexer::new(input, &mut strings).next().unwrap().unwrap().0 else {
+            panic!("invalid token")
+        };
+        let actual = strings.get_unchecked(string_id);
+        assert_eq!(actual, "fn  main() {\n    a   b\n}");
+    }
+
+    #[test]
+    fn unterminated_block_string() {
+        let actual = error_kind("\"\"\" unterminated \"\"");
+        assert_eq!(actual, ParseErrorKind::UnterminatedString);
+    }
+
     #[test]
     fn consume_whitespace() {
         let input = "   ";
@@ -489,6 +613,43 @@ mod test {
         assert_eq!(decl, Kind::Decl);
     }
 
+    #[test]
+    fn directive() {
+        let mut strings = Strings::empty();
+        let tokens = Lexer::new("#[if x]", &mut strings)
+            .map(|t| t.map(|t| t.0))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(tokens[0], Kind::Directive);
+        assert_eq!(tokens[1], Kind::Op(Operator::LBracket));
+        assert_eq!(tokens[2], Kind::If);
+    }
+
+    #[test]
+    fn require_is_ident() {
+        // `require` is only a keyword at the start of a statement
+        assert!(matches!(token_kind("require"), Kind::Value(Value::Ident(_))));
+    }
+
+    #[test]
+    fn match_case_are_idents() {
+        // `match` and `case` are only keywords at the start of a statement
+        assert!(matches!(token_kind("match"), Kind::Value(Value::Ident(_))));
+        assert!(matches!(token_kind("case"), Kind::Value(Value::Ident(_))));
+    }
+
+    #[test]
+    fn macro_is_ident() {
+        // `macro` is only a keyword at the start of a statement
+        assert!(matches!(token_kind("macro"), Kind::Value(Value::Ident(_))));
+    }
+
+    #[test]
+    fn enum_is_ident() {
+        // `enum` is only a keyword at the start of a statement
+        assert!(matches!(token_kind("enum"), Kind::Value(Value::Ident(_))));
+    }
+
     #[test]
     fn association() {
         let decl = token_kind("->");
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use super::Context;
-use crate::expressions::{Expression, Op};
+use crate::expressions::{Equality, Expression, Op};
 use crate::primitives::Primitive;
 
 // Evaluate the expression using `vars` as a backing store.
@@ -52,9 +52,47 @@ pub(crate) fn const_eval(expr: impl Into<Expression>, ctx: &Context<'_>) -> Expr
     let expr = expr.into();
     match expr {
         expr @ (E::Primitive(_) | E::Str(_)) => expr,
+        E::Format(fragments) => {
+            let fragments = fragments
+                .iter()
+                .map(|(expr, spec)| (const_eval(expr.clone(), ctx), *spec))
+                .collect::<Vec<_>>();
+
+            // Fold the string if every value is constant
+            let mut folded = String::new();
+            for (expr, spec) in &fragments {
+                match (expr, spec) {
+                    (E::Str(s), None) => folded.push_str(s),
+                    (E::Str(s), Some(spec)) => folded.push_str(&spec.format_str(s)),
+                    (E::Primitive(P::Int(n)), Some(spec)) => folded.push_str(&spec.format_int(*n)),
+                    (E::Primitive(P::Float(n)), Some(spec)) => folded.push_str(&spec.format_float(*n)),
+                    (E::Primitive(val), Some(spec)) => folded.push_str(&spec.format_str(&val.to_string())),
+                    (E::Primitive(val), None) => folded.push_str(&val.to_string()),
+                    _ => return E::Format(fragments.into()),
+                }
+            }
+            E::Str(folded.into())
+        }
         E::Not(expr) => E::Not(ce!(*expr)),
+        E::Optional(expr) => E::Optional(ce!(*expr)),
+        // Only the branch picked by a constant condition remains
+        E::Conditional { cond, truthy, falsy } => match const_eval(*cond, ctx) {
+            E::Primitive(P::Bool(true)) => const_eval(*truthy, ctx),
+            E::Primitive(P::Bool(false)) => const_eval(*falsy, ctx),
+            cond => E::Conditional {
+                cond: cond.into(),
+                truthy: ce!(*truthy),
+                falsy: ce!(*falsy),
+            },
+        },
         E::Negative(expr) => E::Negative(ce!(*expr)),
-        E::Equality(lhs, rhs, eq) => E::Equality(ce!(*lhs), ce!(*rhs), eq),
+        // Short-circuit `||` and `&&`: the right hand side is never
+        // evaluated if the left hand side decides the outcome.
+        E::Equality(lhs, rhs, eq) => match (const_eval(*lhs, ctx), eq) {
+            (E::Primitive(P::Bool(true)), Equality::Or) => E::Primitive(P::Bool(true)),
+            (E::Primitive(P::Bool(false)), Equality::And) => E::Primitive(P::Bool(false)),
+            (lhs, eq) => E::Equality(lhs.into(), ce!(*rhs), eq),
+        },
 
         E::Ident(_) => eval_path(&expr, ctx).map(|e| ce!(e)).unwrap_or(expr),
         E::Index(..) => eval_path(&expr, ctx).map(|e| ce!(e)).unwrap_or(expr),
@@ -90,9 +128,29 @@ pub(crate) fn const_eval(expr: impl Into<Expression>, ctx: &Context<'_>) -> Expr
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::expressions::{add, ident, index, list, map, num, strlit};
+    use crate::expressions::{add, and, boolean, conditional, ident, index, list, map, num, or, strlit};
     use crate::statements::with_context;
 
+    #[test]
+    fn fold_format_spec() {
+        with_context(|ctx| {
+            let expr = crate::expressions::format::interpolate("{3.14159:.2}").unwrap();
+            assert_eq!(*strlit("3.14"), const_eval(expr, &ctx));
+
+            let expr = crate::expressions::format::interpolate("{7:03}").unwrap();
+            assert_eq!(*strlit("007"), const_eval(expr, &ctx));
+        });
+    }
+
+    #[test]
+    fn fold_interpolated_global() {
+        with_context(|ctx| {
+            ctx.globals.declare("a", num(2));
+            let expr = crate::expressions::format::interpolate("a is {a:02}").unwrap();
+            assert_eq!(*strlit("a is 02"), const_eval(expr, &ctx));
+        });
+    }
+
     #[test]
     fn declare_twice_const_folding() {
         with_context(|ctx| {
@@ -196,4 +254,51 @@ mod test {
             );
         });
     }
+
+    #[test]
+    fn fold_conditional() {
+        with_context(|ctx| {
+            ctx.globals.declare("a", boolean(false));
+            let expr = conditional(ident("a"), num(1), num(2));
+            assert_eq!(*num(2), const_eval(expr, &ctx));
+        });
+    }
+
+    #[test]
+    fn conditional_dyn_eval() {
+        with_context(|ctx| {
+            ctx.globals.declare("a", num(1));
+            let expr = conditional(ident("some_state"), ident("a"), num(2));
+            let expected = *conditional(ident("some_state"), num(1), num(2));
+            assert_eq!(expected, const_eval(expr, &ctx));
+        });
+    }
+
+    #[test]
+    fn short_circuit_or() {
+        with_context(|ctx| {
+            let expr = or(boolean(true), ident("undefined_binding"));
+            let output = const_eval(expr, &ctx);
+            assert_eq!(*boolean(true), output);
+        });
+    }
+
+    #[test]
+    fn short_circuit_and() {
+        with_context(|ctx| {
+            let expr = and(boolean(false), ident("undefined_binding"));
+            let output = const_eval(expr, &ctx);
+            assert_eq!(*boolean(false), output);
+        });
+    }
+
+    #[test]
+    fn no_short_circuit() {
+        with_context(|ctx| {
+            ctx.globals.declare("a", num(1));
+            let expr = or(boolean(false), ident("a"));
+            let output = const_eval(expr, &ctx);
+            assert_eq!(*or(boolean(false), num(1)), output);
+        });
+    }
 }
@@ -1,15 +1,28 @@
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use anathema_store::smallmap::SmallMap;
 use anathema_store::storage::strings::StringId;
 
 use super::const_eval::const_eval;
-use super::{Context, Statement, Statements};
-use crate::blueprints::{Blueprint, Component, ControlFlow, Else, For, If, Single};
+use super::{Attribute, Context, Macro, Statement, Statements};
+use crate::blueprints::{Blueprint, Component, ControlFlow, Else, EventBinding, For, If, Single};
 use crate::error::{Error, Result};
-use crate::expressions::Expression;
+use crate::expressions::{Equality, Expression};
+use crate::primitives::Primitive;
 use crate::WidgetComponentId;
 
+const EVENT_PREFIX: &str = "on_";
+const STYLE: &str = "style";
+const CLASS: &str = "class";
+
+struct EvaluatedAttributes {
+    attributes: SmallMap<Rc<str>, Expression>,
+    source: SmallMap<Rc<str>, Expression>,
+    events: Vec<EventBinding>,
+    spread: Vec<Expression>,
+}
+
 pub(crate) struct Scope {
     statements: Statements,
 }
@@ -25,14 +38,40 @@ impl Scope {
         while let Some(statement) = self.statements.next() {
             match statement {
                 Statement::Node(ident) => output.push(self.eval_node(ident, ctx)?),
-                Statement::Component(component_id) => output.push(self.eval_component(component_id, ctx)?),
-                Statement::For { binding, data } => output.push(self.eval_for(binding, data, ctx)?),
+                Statement::Component { id, pos } => output.extend(self.eval_component(id, pos, ctx)?),
+                Statement::For {
+                    binding,
+                    index,
+                    data,
+                    key,
+                } => output.push(self.eval_for(binding, index, data, key, ctx)?),
                 Statement::If(cond) => output.push(self.eval_if(cond, ctx)?),
+                Statement::Match(value) => output.extend(self.eval_match(value, ctx)?),
+                Statement::IfFlag(flag) => {
+                    // Sections without the flag are never evaluated
+                    let scope = Scope::new(self.statements.take_scope());
+                    let flag = ctx.strings.get_unchecked(flag);
+                    if ctx.components.flags.contains(&*flag) {
+                        output.extend(scope.eval(ctx)?);
+                    }
+                }
                 Statement::Declaration { binding, value } => {
                     let value = const_eval(value, ctx);
                     let binding = ctx.strings.get_unchecked(binding);
                     ctx.globals.declare(binding, value);
                 }
+                Statement::Require(attribute) => {
+                    let Some(component_id) = ctx.component_parent() else {
+                        return Err(Error::InvalidStatement("`require` is only valid in a component".into()));
+                    };
+                    let attribute = ctx.strings.get_unchecked(attribute);
+                    ctx.components.require(component_id, attribute.into());
+                }
+                Statement::Macro { name, params } => {
+                    let body = self.statements.take_scope();
+                    ctx.components.macros.set(name, Macro { params, body });
+                }
+                Statement::MacroCall { name, args } => output.extend(self.eval_macro_call(name, args, ctx)?),
                 Statement::ComponentSlot(slot_id) => {
                     if let Some(bp) = ctx.slots.get(&slot_id).cloned() {
                         output.extend(bp);
@@ -44,11 +83,18 @@ impl Scope {
                 Statement::ScopeStart
                 | Statement::ScopeEnd
                 | Statement::LoadAttribute { .. }
+                | Statement::LoadConditionalAttribute { .. }
+                | Statement::SpreadAttributes(_)
                 | Statement::AssociatedFunction { .. }
                 | Statement::Else(_)
                 | Statement::LoadValue(_) => {
                     unreachable!("\"{statement:?}\" found: this is a bug in Anathema. Please open an issue")
                 }
+                Statement::Case { .. } | Statement::Default => {
+                    return Err(Error::InvalidStatement(
+                        "`case` and `default` are only valid in a `match`".into(),
+                    ))
+                }
                 Statement::Eof => break,
             }
         }
@@ -61,42 +107,186 @@ impl Scope {
         let value = self.statements.take_value().map(|v| const_eval(v, ctx));
         let children = self.consume_scope(ctx)?;
 
-        let node = Blueprint::Single(Single {
+        let mut single = Single {
             ident: ident.into(),
             children,
-            attributes,
+            attributes: attributes.attributes,
+            source_attributes: attributes.source,
+            events: attributes.events,
+            spread: attributes.spread,
             value,
-        });
-        Ok(node)
+            is_static: false,
+        };
+        single.update_static();
+        Ok(Blueprint::Single(single))
     }
 
-    fn eval_for(&mut self, binding: StringId, data: Expression, ctx: &mut Context<'_>) -> Result<Blueprint> {
-        let data = const_eval(data, ctx);
+    fn eval_for(
+        &mut self,
+        binding: StringId,
+        index: Option<StringId>,
+        data: Expression,
+        key: Option<Expression>,
+        ctx: &mut Context<'_>,
+    ) -> Result<Blueprint> {
+        let data = match const_eval(data, ctx) {
+            Expression::Map(map) => map_entries(&map),
+            data => data,
+        };
+        let key = key.map(|key| const_eval(key, ctx));
         let binding = ctx.strings.get_unchecked(binding);
+        let index = index.map(|index| ctx.strings.get_unchecked(index).into());
         let body = self.consume_scope(ctx)?;
+
+        let empty_body = match self.statements.next_else() {
+            Some(None) => self.consume_scope(ctx)?,
+            Some(Some(_)) => {
+                return Err(Error::InvalidStatement(
+                    "`else` after `for` can not have a condition".into(),
+                ))
+            }
+            None => vec![],
+        };
+
         let node = Blueprint::For(For {
             binding: binding.into(),
+            index,
             data,
+            key,
             body,
+            empty_body,
         });
         Ok(node)
     }
 
+    // Expand the body of the macro in place of the call.
+    // The parameters are declared in a scope of their own,
+    // so they don't leak out of the macro.
+    fn eval_macro_call(
+        &mut self,
+        name: StringId,
+        args: Vec<Expression>,
+        ctx: &mut Context<'_>,
+    ) -> Result<Vec<Blueprint>> {
+        let macro_name = || ctx.strings.get_unchecked(name);
+
+        let Some(Macro { params, body }) = ctx.components.macros.get(&name).cloned() else {
+            return Err(Error::InvalidStatement(format!("undefined macro `{}`", macro_name())));
+        };
+
+        if ctx.components.expanding_macros.contains(&name) {
+            return Err(Error::InvalidStatement(format!(
+                "macro `{}` expands itself",
+                macro_name()
+            )));
+        }
+
+        if params.len() != args.len() {
+            return Err(Error::InvalidStatement(format!(
+                "macro `{}` takes {} arguments but {} were given",
+                macro_name(),
+                params.len(),
+                args.len()
+            )));
+        }
+
+        // The arguments are evaluated before the parameters are declared
+        let args = args.into_iter().map(|arg| const_eval(arg, ctx)).collect::<Vec<_>>();
+
+        ctx.globals.push();
+        for (param, arg) in params.into_iter().zip(args) {
+            let param = ctx.strings.get_unchecked(param);
+            ctx.globals.declare(param, arg);
+        }
+
+        ctx.components.expanding_macros.push(name);
+        let output = Scope::new(body).eval(ctx);
+        ctx.components.expanding_macros.pop();
+        ctx.globals.pop();
+
+        output
+    }
+
     fn consume_scope(&mut self, ctx: &mut Context<'_>) -> Result<Vec<Blueprint>> {
         let scope = Scope::new(self.statements.take_scope());
         scope.eval(ctx)
     }
 
-    fn eval_attributes(&mut self, ctx: &mut Context<'_>) -> Result<SmallMap<Rc<str>, Expression>> {
+    // Evaluate the attributes, and keep the unevaluated attributes
+    // as well if the source is preserved.
+    fn eval_attributes(&mut self, ctx: &mut Context<'_>) -> Result<EvaluatedAttributes> {
         let mut hm = SmallMap::empty();
+        let mut source = SmallMap::empty();
+        let mut events = vec![];
+        let mut spread = vec![];
+        let preserve_source = ctx.components.preserve_source;
 
-        for (key, value) in self.statements.take_attributes() {
-            let value = const_eval(value, ctx);
-            let key = ctx.strings.get_unchecked(key);
-            hm.set(key.into(), value);
+        // Attributes are set in order, so later attributes
+        // override the values of a spread map and vice versa.
+        for attribute in self.statements.take_attributes() {
+            match attribute {
+                Attribute::Single(key, value) => {
+                    let key: Rc<str> = ctx.strings.get_unchecked(key).into();
+                    if let Some(event) = key.strip_prefix(EVENT_PREFIX) {
+                        events.extend(event_bindings(event, value)?);
+                        continue;
+                    }
+                    if preserve_source {
+                        source.set(key.clone(), value.clone());
+                    }
+                    let value = const_eval(value, ctx);
+                    hm.set(key, value);
+                }
+                Attribute::Conditional(key, value) => {
+                    let key = ctx.strings.get_unchecked(key);
+                    if preserve_source {
+                        source.set(key.clone().into(), Expression::Optional(value.clone().into()));
+                    }
+                    match const_eval(value, ctx) {
+                        // A constant value is either set or omitted right away
+                        Expression::Primitive(Primitive::Bool(false)) => continue,
+                        value @ (Expression::Primitive(_) | Expression::Str(_)) => _ = hm.set(key.into(), value),
+                        value => _ = hm.set(key.into(), Expression::Optional(value.into())),
+                    }
+                }
+                Attribute::Spread(value) => {
+                    // The source of a spread map literal is the unevaluated map,
+                    // anything else (e.g a global) is only known once evaluated.
+                    if let (true, Expression::Map(map)) = (preserve_source, &value) {
+                        for (key, value) in map.iter() {
+                            source.set(key.clone(), value.clone());
+                        }
+                    }
+
+                    // A map that only exists at runtime, e.g a state map,
+                    // is expanded when the element is evaluated
+                    let source_is_map = matches!(value, Expression::Map(_));
+                    let map = match const_eval(value, ctx) {
+                        Expression::Map(map) => map,
+                        value => {
+                            spread.push(value);
+                            continue;
+                        }
+                    };
+
+                    for (key, value) in map.iter() {
+                        if preserve_source && !source_is_map {
+                            source.set(key.clone(), value.clone());
+                        }
+                        hm.set(key.clone(), value.clone());
+                    }
+                }
+            }
         }
 
-        Ok(hm)
+        resolve_siblings(&mut hm, ctx)?;
+
+        Ok(EvaluatedAttributes {
+            attributes: hm,
+            source,
+            events,
+            spread,
+        })
     }
 
     fn eval_if(&mut self, cond: Expression, ctx: &mut Context<'_>) -> Result<Blueprint> {
@@ -109,6 +299,13 @@ impl Scope {
         let if_node = If { cond, body };
         let mut elses = vec![];
         while let Some(cond) = self.statements.next_else() {
+            // Nothing can follow an `else` without a condition
+            if let Some(Else { cond: None, .. }) = elses.last() {
+                return Err(Error::InvalidStatement(
+                    "`else` without a condition has to be the last branch of an `if`".into(),
+                ));
+            }
+
             let cond = cond.map(|v| const_eval(v, ctx));
             let body = self.consume_scope(ctx)?;
 
@@ -121,7 +318,75 @@ impl Scope {
         Ok(Blueprint::ControlFlow(ControlFlow { if_node, elses }))
     }
 
-    fn eval_component(&mut self, component_id: WidgetComponentId, ctx: &mut Context<'_>) -> Result<Blueprint> {
+    // A match is lowered into an if / else chain, where each case
+    // compares the value to the pattern, and the default is the final else.
+    fn eval_match(&mut self, value: Expression, ctx: &mut Context<'_>) -> Result<Option<Blueprint>> {
+        let value = const_eval(value, ctx);
+        let mut arms = self.statements.take_scope();
+        let mut branches = vec![];
+        let mut default = None;
+
+        while let Some(statement) = arms.next() {
+            let cond = match statement {
+                Statement::Case { .. } | Statement::Default if default.is_some() => {
+                    return Err(Error::InvalidStatement(
+                        "`default` has to be the last arm of a `match`".into(),
+                    ));
+                }
+                Statement::Case { pattern, guard } => {
+                    let pattern = const_eval(pattern, ctx);
+                    let cond = Expression::Equality(value.clone().into(), pattern.into(), Equality::Eq);
+                    match guard {
+                        Some(guard) => {
+                            let guard = const_eval(guard, ctx);
+                            Some(Expression::Equality(cond.into(), guard.into(), Equality::And))
+                        }
+                        None => Some(cond),
+                    }
+                }
+                Statement::Default => None,
+                Statement::Eof => break,
+                _ => {
+                    return Err(Error::InvalidStatement(
+                        "a `match` can only contain `case` and `default`".into(),
+                    ))
+                }
+            };
+
+            let body = Scope::new(arms.take_scope()).eval(ctx)?;
+            if body.is_empty() {
+                return Err(Error::EmptyBody);
+            }
+
+            match cond {
+                Some(cond) => branches.push(Else { cond: Some(cond), body }),
+                None => default = Some(Else { cond: None, body }),
+            }
+        }
+
+        branches.extend(default);
+        if branches.is_empty() {
+            return Ok(None);
+        }
+
+        let first = branches.remove(0);
+        let if_node = If {
+            cond: first.cond.unwrap_or(Expression::Primitive(Primitive::Bool(true))),
+            body: first.body,
+        };
+
+        Ok(Some(Blueprint::ControlFlow(ControlFlow {
+            if_node,
+            elses: branches,
+        })))
+    }
+
+    fn eval_component(
+        &mut self,
+        component_id: WidgetComponentId,
+        (line, col): (usize, usize),
+        ctx: &mut Context<'_>,
+    ) -> Result<Option<Blueprint>> {
         let parent = ctx.component_parent();
         let attributes = self.eval_attributes(ctx)?;
 
@@ -133,7 +398,7 @@ impl Scope {
         // State
         let state = match state {
             Some(Expression::Map(map)) => Some(map),
-            Some(_) => todo!("Invalid state: state has to be a map or nothing"),
+            Some(state) => return Err(Error::InvalidComponentState(state.to_string())),
             None => None,
         };
 
@@ -148,18 +413,245 @@ impl Scope {
             slots.set(slot_id, body);
         }
 
-        let body = ctx.load_component(component_id, slots)?;
+        let Some(component_id) = ctx.components.resolve(component_id)? else { return Ok(None) };
+        // Errors from loading the component point to where the component is used
+        let mut body = ctx
+            .load_component(component_id, slots)
+            .map_err(|error| Error::InComponent {
+                line,
+                col,
+                error: Box::new(error),
+            })?;
+        ctx.components.check_required(component_id, &attributes.attributes)?;
+
+        if let Some(Expression::Map(style)) = attributes.attributes.get(STYLE) {
+            apply_style(&mut body, style);
+        }
 
         let component = Component {
             id: component_id,
             body,
-            attributes,
+            attributes: attributes.attributes,
+            source_attributes: attributes.source,
+            events: attributes.events,
             state,
             assoc_functions,
             parent,
         };
 
-        Ok(Blueprint::Component(component))
+        Ok(Some(Blueprint::Component(component)))
+    }
+}
+
+// Turn a map into a list of `{key, value}` maps, ordered by key,
+// so `for entry in {a: 1}` can read `entry.key` and `entry.value`.
+fn map_entries(map: &HashMap<Rc<str>, Expression>) -> Expression {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|(key, _)| *key);
+
+    let entries = entries
+        .into_iter()
+        .map(|(key, value)| {
+            let entry = HashMap::from([
+                (Rc::from("key"), Expression::Str(key.clone())),
+                (Rc::from("value"), value.clone()),
+            ]);
+            Expression::Map(entry.into())
+        })
+        .collect::<Vec<_>>();
+
+    Expression::List(entries.into())
+}
+
+// Apply the `style` of a component to the elements in the body.
+// The style is a map of classes to attributes, e.g
+// `@comp [style: {title: {foreground: 'red'}}]`.
+// The attributes of the style replace the attributes of the element,
+// and the classes are applied in the order of the `class` attribute.
+//
+// The bodies of nested components are not styled,
+// as they have their own `style`.
+fn apply_style(body: &mut [Blueprint], style: &HashMap<Rc<str>, Expression>) {
+    for blueprint in body {
+        match blueprint {
+            Blueprint::Single(single) => {
+                let classes = match single.attributes.get(CLASS) {
+                    Some(Expression::Str(class)) => class.split_whitespace().map(Rc::from).collect(),
+                    Some(Expression::List(classes)) => classes
+                        .iter()
+                        .filter_map(|class| match class {
+                            Expression::Str(class) => Some(class.clone()),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => vec![],
+                };
+
+                for class in classes {
+                    let Some(Expression::Map(attributes)) = style.get(&class) else { continue };
+                    for (key, value) in attributes.iter() {
+                        single.attributes.set(key.clone(), value.clone());
+                    }
+                }
+
+                apply_style(&mut single.children, style);
+                single.update_static();
+            }
+            Blueprint::For(for_loop) => {
+                apply_style(&mut for_loop.body, style);
+                apply_style(&mut for_loop.empty_body, style);
+            }
+            Blueprint::ControlFlow(control_flow) => {
+                apply_style(&mut control_flow.if_node.body, style);
+                for else_node in &mut control_flow.elses {
+                    apply_style(&mut else_node.body, style);
+                }
+            }
+            Blueprint::Component(_) => {}
+        }
+    }
+}
+
+// The handler of an event is either an identifier,
+// or a map of arguments to identifiers.
+fn event_bindings(event: &str, value: Expression) -> Result<Vec<EventBinding>> {
+    let handler = |value: &Expression| match value {
+        Expression::Ident(handler) => Ok(handler.clone()),
+        _ => Err(Error::InvalidStatement(format!(
+            "the handler of `{EVENT_PREFIX}{event}` has to be an identifier"
+        ))),
+    };
+
+    match &value {
+        Expression::Map(map) => {
+            let mut bindings = map
+                .iter()
+                .map(|(arg, value)| {
+                    Ok(EventBinding {
+                        event: event.into(),
+                        arg: Some(arg.clone()),
+                        handler: handler(value)?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // The order of a map is not stable
+            bindings.sort_by(|a, b| a.arg.cmp(&b.arg));
+            Ok(bindings)
+        }
+        value => Ok(vec![EventBinding {
+            event: event.into(),
+            arg: None,
+            handler: handler(value)?,
+        }]),
+    }
+}
+
+// Replace references to other attributes of the same node with their values,
+// e.g `[width: 10, height: width / 2]`.
+//
+// Attributes are resolved in the order they are referenced.
+// An attribute referencing itself refers to the outer scope, e.g `[value: value]`,
+// and attributes referencing each other in a cycle is an error.
+fn resolve_siblings(attributes: &mut SmallMap<Rc<str>, Expression>, ctx: &Context<'_>) -> Result<()> {
+    let siblings = attributes
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut resolver = Siblings {
+        siblings: &siblings,
+        resolved: HashMap::new(),
+        stack: vec![],
+        ctx,
+    };
+
+    // Sort the keys so the same cycle is reported every time
+    let mut keys = siblings.keys().collect::<Vec<_>>();
+    keys.sort();
+
+    for key in keys {
+        let value = resolver.resolve(key)?;
+        attributes.set(key.clone(), value);
+    }
+
+    Ok(())
+}
+
+struct Siblings<'a, 'vars> {
+    siblings: &'a HashMap<Rc<str>, Expression>,
+    resolved: HashMap<Rc<str>, Expression>,
+    stack: Vec<Rc<str>>,
+    ctx: &'a Context<'vars>,
+}
+
+impl Siblings<'_, '_> {
+    fn resolve(&mut self, key: &Rc<str>) -> Result<Expression> {
+        if let Some(value) = self.resolved.get(key) {
+            return Ok(value.clone());
+        }
+
+        if let Some(start) = self.stack.iter().position(|k| k == key) {
+            let cycle = self.stack[start..]
+                .iter()
+                .chain(Some(key))
+                .map(|k| k.to_string())
+                .collect();
+            return Err(Error::CircularAttribute(cycle));
+        }
+
+        let siblings = self.siblings;
+        self.stack.push(key.clone());
+        let value = self.substitute(key, &siblings[key])?;
+        self.stack.pop();
+
+        let value = const_eval(value, self.ctx);
+        self.resolved.insert(key.clone(), value.clone());
+        Ok(value)
+    }
+
+    fn substitute(&mut self, key: &Rc<str>, expr: &Expression) -> Result<Expression> {
+        use Expression as E;
+
+        macro_rules! sub {
+            ($e:expr) => {
+                Box::new(self.substitute(key, $e)?)
+            };
+        }
+
+        let expr = match expr {
+            E::Ident(ident) if ident != key && self.siblings.contains_key(ident) => self.resolve(ident)?,
+            E::Primitive(_) | E::Str(_) | E::Ident(_) => expr.clone(),
+            E::Format(fragments) => E::Format(
+                fragments
+                    .iter()
+                    .map(|(expr, spec)| Ok((self.substitute(key, expr)?, *spec)))
+                    .collect::<Result<_>>()?,
+            ),
+            E::List(list) => E::List(list.iter().map(|e| self.substitute(key, e)).collect::<Result<_>>()?),
+            E::Map(map) => E::Map(
+                map.iter()
+                    .map(|(k, e)| Ok((k.clone(), self.substitute(key, e)?)))
+                    .collect::<Result<HashMap<_, _>>>()?
+                    .into(),
+            ),
+            E::Not(expr) => E::Not(sub!(expr)),
+            E::Negative(expr) => E::Negative(sub!(expr)),
+            E::Optional(expr) => E::Optional(sub!(expr)),
+            E::Conditional { cond, truthy, falsy } => E::Conditional {
+                cond: sub!(cond),
+                truthy: sub!(truthy),
+                falsy: sub!(falsy),
+            },
+            E::Equality(lhs, rhs, eq) => E::Equality(sub!(lhs), sub!(rhs), *eq),
+            E::Index(lhs, rhs) => E::Index(sub!(lhs), sub!(rhs)),
+            E::Op(lhs, rhs, op) => E::Op(sub!(lhs), sub!(rhs), *op),
+            E::Call { fun, args } => E::Call {
+                fun: fun.clone(),
+                args: args.iter().map(|e| self.substitute(key, e)).collect::<Result<_>>()?,
+            },
+        };
+
+        Ok(expr)
     }
 }
 
@@ -168,7 +660,9 @@ mod test {
 
     use super::*;
     use crate::document::Document;
-    use crate::{single, ToSourceKind};
+    use crate::error::Warning;
+    use crate::expressions::{add, ident, index, list, num, strlit};
+    use crate::{single, OnMissingComponent, ToSourceKind};
 
     #[test]
     fn eval_node() {
@@ -211,6 +705,129 @@ mod test {
         assert!(matches!(blueprint, Blueprint::For(For { .. })));
     }
 
+    #[test]
+    fn eval_for_map() {
+        let src = "
+            let colors = {red: #f00, blue: #00f}
+            for color in colors
+                node color.key
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::For(for_loop) = blueprint else { panic!("expected a for loop") };
+        let Expression::List(entries) = for_loop.data else { panic!("expected a list") };
+        assert_eq!(entries.len(), 2);
+
+        // The entries are ordered by key
+        let keys = entries
+            .iter()
+            .map(|entry| match entry {
+                Expression::Map(entry) => entry["key"].to_string(),
+                _ => panic!("expected a map"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["blue", "red"]);
+    }
+
+    #[test]
+    fn eval_for_keeps_state_expression() {
+        let src = "
+            for x in state.items
+                node x
+        ";
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::For(for_loop) = blueprint else { panic!("expected a for-loop") };
+        assert_eq!(for_loop.data, *index(ident("state"), strlit("items")));
+    }
+
+    #[test]
+    fn eval_for_folds_constant_list() {
+        let src = "
+            let b = 2
+            for x in [1, b, 1 + 2]
+                node x
+        ";
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::For(for_loop) = blueprint else { panic!("expected a for-loop") };
+        assert_eq!(for_loop.data, *list([1, 2, 3]));
+    }
+
+    #[test]
+    fn eval_keyed_for() {
+        let src = "
+            for x in items key x.id
+                node x
+        ";
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::For(for_loop) = blueprint else { panic!("expected a for-loop") };
+        assert!(for_loop.key.is_some());
+    }
+
+    #[test]
+    fn eval_for_else() {
+        let src = "
+            for x in items
+                node x
+            else
+                node 'empty'
+                node 'list'
+        ";
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::For(for_loop) = blueprint else { panic!("expected a for-loop") };
+        assert_eq!(for_loop.body.len(), 1);
+        assert_eq!(for_loop.empty_body.len(), 2);
+        assert!(matches!(&for_loop.empty_body[0], Blueprint::Single(Single { ident, .. }) if &**ident == "node"));
+    }
+
+    #[test]
+    fn eval_for_else_with_condition() {
+        let src = "
+            for x in items
+                node x
+            else if x
+                node
+        ";
+        let mut doc = Document::new(src);
+        assert!(matches!(doc.compile(), Err(Error::InvalidStatement(_))));
+    }
+
+    #[test]
+    fn eval_if_else_chain() {
+        let src = "
+            if a
+                node 1
+            else if b
+                node 2
+            else
+                node 3
+        ";
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::ControlFlow(control_flow) = blueprint else { panic!("expected control flow") };
+        assert_eq!(control_flow.elses.len(), 2);
+        assert_eq!(control_flow.elses[0].cond, Some(Expression::Ident("b".into())));
+        assert_eq!(control_flow.elses[1].cond, None);
+    }
+
+    #[test]
+    fn eval_else_before_else_if() {
+        let src = "
+            if a
+                node 1
+            else
+                node 2
+            else if b
+                node 3
+        ";
+        let mut doc = Document::new(src);
+        assert!(matches!(doc.compile(), Err(Error::InvalidStatement(_))));
+    }
+
     #[test]
     fn eval_component() {
         let src = "@comp {a: 1}";
@@ -222,6 +839,701 @@ mod test {
         assert!(matches!(blueprint, Blueprint::Component(Component { .. })));
     }
 
+    #[test]
+    fn eval_component_error_position() {
+        let mut doc = Document::new("node\n    @a");
+        doc.add_component("a", "node\n  @b".to_template()).unwrap();
+        doc.add_component("b", "@c 5".to_template()).unwrap();
+        doc.add_component("c", "node".to_template()).unwrap();
+        let err = doc.compile().unwrap_err();
+        let Error::InComponent { line, col, error } = &err else {
+            panic!("expected an error in a component")
+        };
+        assert_eq!((*line, *col), (2, 5));
+        assert!(matches!(**error, Error::InComponent { line: 2, col: 3, .. }));
+        assert_eq!(
+            err.to_string(),
+            "the state of a component has to be a map, found `5`, \
+             in the component on line 2 (col 3), in the component on line 2 (col 5)"
+        );
+    }
+
+    #[test]
+    fn eval_component_cycle() {
+        // Find the error that caused the chain of component errors
+        fn cause(err: Error) -> Error {
+            match err {
+                Error::InComponent { error, .. } => cause(*error),
+                err => err,
+            }
+        }
+
+        let mut doc = Document::new("@a");
+        doc.add_component("a", "node\n  @b".to_template()).unwrap();
+        doc.add_component("b", "@a".to_template()).unwrap();
+        let err = cause(doc.compile().unwrap_err());
+        assert!(matches!(&err, Error::ComponentCycle(chain) if chain == &["a", "b", "a"]));
+        assert_eq!(err.to_string(), "components include each other: `@a` -> `@b` -> `@a`");
+
+        let mut doc = Document::new("@a");
+        doc.add_component("a", "node\n  @a".to_template()).unwrap();
+        let err = cause(doc.compile().unwrap_err());
+        assert!(matches!(&err, Error::ComponentCycle(chain) if chain == &["a", "a"]));
+    }
+
+    #[test]
+    fn eval_component_invalid_state() {
+        let mut doc = Document::new("@comp 5");
+        doc.add_component("comp", "node".to_template()).unwrap();
+        let err = doc.compile().unwrap_err();
+        assert!(matches!(err, Error::InvalidComponentState(_)));
+        assert_eq!(err.to_string(), "the state of a component has to be a map, found `5`");
+    }
+
+    #[test]
+    fn eval_component_required_attribute() {
+        let comp_src = "
+            require title
+            node title
+        ";
+
+        let mut doc = Document::new("@comp [title: 'hello']");
+        doc.add_component("comp", comp_src.to_template()).unwrap();
+        let (blueprint, _) = doc.compile().unwrap();
+        assert!(matches!(blueprint, Blueprint::Component(Component { .. })));
+
+        let mut doc = Document::new("@comp [subtitle: 'hello']");
+        doc.add_component("comp", comp_src.to_template()).unwrap();
+        let err = doc.compile().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            Error::MissingRequiredAttribute {
+                component: "comp".into(),
+                attribute: "title".into(),
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn eval_require_outside_of_component() {
+        let mut doc = Document::new("require title\nnode");
+        assert!(matches!(doc.compile().unwrap_err(), Error::InvalidStatement(_)));
+    }
+
+    #[test]
+    fn eval_component_style() {
+        let src = "@comp [style: {title: {color: 'red'}, bold: {bold: true}}]";
+        let comp_src = "
+            vstack
+                text [class: 'title', color: 'white'] 'a'
+                if true
+                    text [class: 'title bold'] 'b'
+                text [class: 'body', color: 'white'] 'c'
+        ";
+
+        let mut doc = Document::new(src);
+        doc.add_component("comp", comp_src.to_template()).unwrap();
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Component(component) = blueprint else { panic!("expected a component") };
+        let Blueprint::Single(vstack) = &component.body[0] else { panic!("expected a vstack") };
+
+        // The style replaces the attributes of the element
+        let Blueprint::Single(title) = &vstack.children[0] else { panic!("expected text") };
+        assert_eq!(title.attributes.get("color"), Some(&Expression::Str("red".into())));
+
+        let Blueprint::ControlFlow(control_flow) = &vstack.children[1] else { panic!("expected an if") };
+        let Blueprint::Single(title) = &control_flow.if_node.body[0] else { panic!("expected text") };
+        assert_eq!(title.attributes.get("color"), Some(&Expression::Str("red".into())));
+        assert_eq!(title.attributes.get("bold"), Some(&Expression::from(true)));
+
+        // Unstyled classes keep their attributes
+        let Blueprint::Single(body) = &vstack.children[2] else { panic!("expected text") };
+        assert_eq!(body.attributes.get("color"), Some(&Expression::Str("white".into())));
+        assert_eq!(body.attributes.get("bold"), None);
+    }
+
+    #[test]
+    fn eval_component_spread_attributes() {
+        let src = "@comp [...{a: 1, b: 2}, b: 3]";
+        let comp_src = "node a + b";
+
+        let mut doc = Document::new(src);
+        doc.add_component("comp", comp_src.to_template()).unwrap();
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Component(component) = blueprint else { panic!("expected a component") };
+        assert_eq!(component.attributes.get("a"), Some(&Expression::from(1)));
+        assert_eq!(component.attributes.get("b"), Some(&Expression::from(3)));
+    }
+
+    #[test]
+    fn eval_match() {
+        let src = "
+            match x
+                case 1
+                    a
+                case 'two'
+                    b
+                default
+                    c
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::ControlFlow(ControlFlow { if_node, elses }) = blueprint else {
+            panic!("expected control flow")
+        };
+
+        let eq = |pattern: Expression| {
+            Expression::Equality(Expression::Ident("x".into()).into(), pattern.into(), Equality::Eq)
+        };
+        assert_eq!(if_node.cond, eq(1.into()));
+        assert_eq!(if_node.body, vec![single!("a")]);
+        assert_eq!(elses[0].cond, Some(eq("two".into())));
+        assert_eq!(elses[0].body, vec![single!("b")]);
+
+        // The default is the final else
+        assert_eq!(elses[1].cond, None);
+        assert_eq!(elses[1].body, vec![single!("c")]);
+    }
+
+    #[test]
+    fn eval_match_default_only() {
+        let src = "
+            match x
+                default
+                    a
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::ControlFlow(ControlFlow { if_node, elses }) = blueprint else {
+            panic!("expected control flow")
+        };
+        assert_eq!(if_node.cond, Expression::from(true));
+        assert_eq!(if_node.body, vec![single!("a")]);
+        assert!(elses.is_empty());
+    }
+
+    #[test]
+    fn eval_match_without_default() {
+        let src = "
+            match x
+                case 1
+                    a
+                case 2
+                    b
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::ControlFlow(ControlFlow { elses, .. }) = blueprint else {
+            panic!("expected control flow")
+        };
+
+        // Without a default every branch has a condition,
+        // so an unmatched value renders nothing
+        assert_eq!(elses.len(), 1);
+        assert!(elses.iter().all(|e| e.cond.is_some()));
+    }
+
+    #[test]
+    fn eval_match_guarded_case() {
+        let src = "
+            match x
+                case 1 if y
+                    a
+                case 1
+                    b
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::ControlFlow(ControlFlow { if_node, elses }) = blueprint else {
+            panic!("expected control flow")
+        };
+
+        let eq = Expression::Equality(
+            Expression::Ident("x".into()).into(),
+            Expression::from(1).into(),
+            Equality::Eq,
+        );
+        let guarded = Expression::Equality(eq.clone().into(), Expression::Ident("y".into()).into(), Equality::And);
+        assert_eq!(if_node.cond, guarded);
+        assert_eq!(if_node.body, vec![single!("a")]);
+        assert_eq!(elses[0].cond, Some(eq));
+        assert_eq!(elses[0].body, vec![single!("b")]);
+    }
+
+    #[test]
+    fn eval_match_errors() {
+        let src = "
+            match x
+                default
+                    a
+                case 1
+                    b
+        ";
+        assert!(matches!(Document::new(src).compile(), Err(Error::InvalidStatement(_))));
+
+        let src = "
+            match x
+                a
+        ";
+        assert!(matches!(Document::new(src).compile(), Err(Error::InvalidStatement(_))));
+
+        // Outside of a `match`, `case` is the name of a node
+        let src = "
+            case 1
+                a
+        ";
+        assert!(Document::new(src).compile().is_ok());
+    }
+
+    #[test]
+    fn eval_node_spread_attributes() {
+        let src = "node [a: 0, ...{a: 1, b: x}, c: 3]";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(single) = blueprint else { panic!("expected a single node") };
+        assert_eq!(single.attributes.get("a"), Some(&Expression::from(1)));
+        assert_eq!(single.attributes.get("b"), Some(&Expression::Ident("x".into())));
+        assert_eq!(single.attributes.get("c"), Some(&Expression::from(3)));
+    }
+
+    #[test]
+    fn eval_node_multiple_values() {
+        let src = "
+            let count = 3
+            root
+                node 'a' count f'b {count:02}'
+                node 'a' x
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(root) = blueprint else { panic!("expected a single node") };
+
+        // Constant values are concatenated into a single string
+        let Blueprint::Single(node) = &root.children[0] else { panic!("expected a single node") };
+        assert_eq!(node.value, Some(Expression::Str("a3b 03".into())));
+
+        // ... and anything else is concatenated at runtime
+        let Blueprint::Single(node) = &root.children[1] else { panic!("expected a single node") };
+        let expected = Expression::Format([(*strlit("a"), None), (*ident("x"), None)].into());
+        assert_eq!(node.value, Some(expected));
+    }
+
+    #[test]
+    fn eval_braces_without_format_prefix() {
+        // Only a string with the `f` prefix is interpolated,
+        // any other string is used as it is
+        let src = r#"
+            let name = 1
+            root
+                text '{a: b}'
+                text '{"json": 1}'
+                text 'x {{ y'
+                text '{name}'
+                text f'{name}'
+        "#;
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(root) = blueprint else { panic!("expected a single node") };
+
+        let values = root
+            .children
+            .iter()
+            .map(|child| match child {
+                Blueprint::Single(text) => text.value.clone().unwrap(),
+                _ => panic!("expected a single node"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            values,
+            [
+                Expression::Str("{a: b}".into()),
+                Expression::Str(r#"{"json": 1}"#.into()),
+                Expression::Str("x {{ y".into()),
+                Expression::Str("{name}".into()),
+                Expression::Str("1".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn eval_conditional_expression() {
+        let src = "
+            let flag = true
+            node [a: flag ? #f00 : #0f0, b: x ? 1 : 2]
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(single) = blueprint else { panic!("expected a single node") };
+        assert_eq!(single.attributes.get("a").unwrap().to_string(), "r: 255, g: 0, b: 0");
+        assert!(matches!(
+            single.attributes.get("b"),
+            Some(Expression::Conditional { .. })
+        ));
+    }
+
+    #[test]
+    fn eval_static() {
+        let src = "
+            let size = 3
+            root
+                outer [width: size]
+                    inner 'a {1 + 2}'
+                dynamic [width: x]
+                    inner 'a'
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(root) = blueprint else { panic!("expected a single node") };
+        assert!(!root.is_static);
+
+        let Blueprint::Single(outer) = &root.children[0] else { panic!("expected a single node") };
+        assert!(outer.is_static);
+
+        let Blueprint::Single(dynamic) = &root.children[1] else { panic!("expected a single node") };
+        assert!(!dynamic.is_static);
+        assert!(dynamic.children[0].is_static());
+    }
+
+    #[test]
+    fn eval_spread_attributes_from_global() {
+        let src = "
+            let defaults = {a: 1, b: 2}
+            node [b: 3, ...defaults]
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(single) = blueprint else { panic!("expected a single node") };
+        assert_eq!(single.attributes.get("a"), Some(&Expression::from(1)));
+        assert_eq!(single.attributes.get("b"), Some(&Expression::from(2)));
+    }
+
+    #[test]
+    fn eval_spread_runtime_map() {
+        let src = "node [a: 1, ...state.attributes]";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(single) = blueprint else { panic!("expected a single node") };
+        assert_eq!(single.attributes.get("a"), Some(&Expression::from(1)));
+        assert_eq!(single.spread.len(), 1);
+        assert!(!single.is_static);
+    }
+
+    #[test]
+    fn eval_conditional_attributes() {
+        let src = "node [a?: false, b?: 1, c?: x]";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(single) = blueprint else { panic!("expected a single node") };
+        assert!(single.attributes.get("a").is_none());
+        assert_eq!(single.attributes.get("b"), Some(&Expression::from(1)));
+        assert!(matches!(single.attributes.get("c"), Some(Expression::Optional(_))));
+    }
+
+    #[test]
+    fn eval_appended_source() {
+        let mut doc = Document::new("");
+        doc.append_source("vstack\n    text 'a'").unwrap();
+        doc.append_source("    text 'b'\nnode").unwrap();
+        let (blueprint, _) = doc.compile().unwrap();
+
+        let Blueprint::Single(vstack) = blueprint else { panic!("expected a single node") };
+        assert_eq!(&*vstack.ident, "vstack");
+        assert_eq!(vstack.children.len(), 2);
+    }
+
+    #[test]
+    fn eval_appended_source_continues_scope() {
+        let first = "
+        vstack
+            border
+                text 'a'
+            match x
+                case 1
+        ";
+        let second = "
+                    text 'one'
+                default
+                    text 'other'
+            text 'b'
+        ";
+
+        let mut doc = Document::new(first);
+        doc.append_source(second).unwrap();
+        let appended = doc.compile().unwrap().0;
+
+        let combined = Document::new(format!("{first}{second}")).compile().unwrap().0;
+        assert_eq!(appended, combined);
+    }
+
+    #[test]
+    fn eval_compile_with_pass() {
+        let mut doc = Document::new("node");
+        let (blueprint, _) = doc
+            .compile_with_pass(|blueprints| {
+                for blueprint in blueprints {
+                    if let Blueprint::Single(single) = blueprint {
+                        single.ident = "renamed".into();
+                    }
+                }
+            })
+            .unwrap();
+
+        let Blueprint::Single(single) = blueprint else { panic!("expected a single node") };
+        assert_eq!(&*single.ident, "renamed");
+    }
+
+    #[test]
+    fn eval_macro() {
+        let src = "
+        macro label(title, width)
+            border [width: width + 2]
+                text title
+        vstack
+            !label('a', 1)
+            !label('b', 2)
+        ";
+
+        let inlined = "
+        vstack
+            border [width: 3]
+                text 'a'
+            border [width: 4]
+                text 'b'
+        ";
+
+        let (expanded, globals) = Document::new(src).compile().unwrap();
+        let (expected, _) = Document::new(inlined).compile().unwrap();
+        assert_eq!(expanded, expected);
+
+        // The parameters are not globals
+        assert!(globals.get("title").is_none());
+    }
+
+    #[test]
+    fn eval_macro_param_shadows_global() {
+        let src = "
+        let title = 'global'
+        macro label(title)
+            text title
+        vstack
+            !label('a')
+            text title
+        ";
+
+        let inlined = "
+        vstack
+            text 'a'
+            text 'global'
+        ";
+
+        let (expanded, globals) = Document::new(src).compile().unwrap();
+        let (expected, _) = Document::new(inlined).compile().unwrap();
+        assert_eq!(expanded, expected);
+        assert_eq!(globals.get("title"), Some(&Expression::Str("global".into())));
+    }
+
+    #[test]
+    fn eval_macro_errors() {
+        let undefined = "
+        vstack
+            !label('a')
+        ";
+        assert!(Document::new(undefined).compile().is_err());
+
+        let arguments = "
+        macro label(title)
+            text title
+        !label('a', 'b')
+        ";
+        assert!(Document::new(arguments).compile().is_err());
+
+        let recursive = "
+        macro label(title)
+            !label(title)
+        !label('a')
+        ";
+        assert!(Document::new(recursive).compile().is_err());
+    }
+
+    #[test]
+    fn eval_append_invalid_source() {
+        let mut doc = Document::new("vstack");
+        assert!(doc.append_source("    text 'a").is_err());
+        assert!(doc.append_source("    text [").is_err());
+        assert!(doc.compile().is_ok());
+    }
+
+    #[test]
+    fn eval_flagged_section() {
+        let src = "
+        #[if debug]
+            debug
+        node
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        assert_eq!(blueprint, single!("node"));
+
+        doc.set_flag("debug");
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(debug) = blueprint else { panic!("expected a single node") };
+        assert_eq!(&*debug.ident, "debug");
+
+        doc.unset_flag("debug");
+        let (blueprint, _) = doc.compile().unwrap();
+        assert_eq!(blueprint, single!("node"));
+    }
+
+    #[test]
+    fn eval_sibling_attributes() {
+        let src = "node [height: width / 2, width: 10, value: value]";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(single) = blueprint else { panic!("expected a single node") };
+        assert_eq!(single.attributes.get("height"), Some(&Expression::from(5)));
+        assert_eq!(single.attributes.get("width"), Some(&Expression::from(10)));
+        // Referencing itself refers to the outer scope
+        assert_eq!(single.attributes.get("value"), Some(&*ident("value")));
+    }
+
+    #[test]
+    fn eval_circular_sibling_attributes() {
+        let src = "node [a: b + 1, b: a + 1]";
+
+        let mut doc = Document::new(src);
+        let err = doc.compile().unwrap_err();
+        let Error::CircularAttribute(keys) = err else { panic!("expected a circular attribute") };
+        assert_eq!(keys, ["a", "b", "a"]);
+
+        let src = "node [width: height, height: width]";
+
+        let mut doc = Document::new(src);
+        let err = doc.compile().unwrap_err();
+        let Error::CircularAttribute(keys) = err else { panic!("expected a circular attribute") };
+        assert_eq!(keys, ["height", "width", "height"]);
+    }
+
+    #[test]
+    fn eval_enum_member() {
+        let src = "
+        enum Placement {
+            Relative,
+            TopLeft,
+            Custom = 3
+        }
+        node [a: Placement.Relative, b: Placement.TopLeft, c: Placement.Custom]
+        ";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(single) = blueprint else { panic!("expected a single node") };
+        assert_eq!(single.attributes.get("a"), Some(&Expression::from("relative")));
+        assert_eq!(single.attributes.get("b"), Some(&Expression::from("top_left")));
+        assert_eq!(single.attributes.get("c"), Some(&Expression::from(3)));
+    }
+
+    #[test]
+    fn eval_missing_component_with_fallback() {
+        let src = "@missing [a: 1]";
+
+        let mut doc = Document::new(src);
+        let fallback = doc.add_component("fallback", "node".to_template()).unwrap();
+        doc.on_missing_component(OnMissingComponent::Fallback("fallback".into()));
+        let (blueprint, _) = doc.compile().unwrap();
+
+        let Blueprint::Component(component) = blueprint else { panic!("expected a component") };
+        assert_eq!(usize::from(component.id), fallback);
+        assert_eq!(component.body, vec![single!("node")]);
+        assert_eq!(
+            doc.warnings(),
+            &[Warning::MissingComponent {
+                name: "missing".into(),
+                fallback: Some("fallback".into())
+            }]
+        );
+    }
+
+    #[test]
+    fn eval_missing_component() {
+        let src = "
+            node
+                @missing
+        ";
+
+        let mut doc = Document::new(src);
+        assert!(matches!(doc.compile(), Err(Error::MissingComponent(_))));
+
+        doc.on_missing_component(OnMissingComponent::Skip);
+        let (blueprint, _) = doc.compile().unwrap();
+        assert_eq!(blueprint, single!("node"));
+        assert_eq!(doc.warnings().len(), 1);
+    }
+
+    #[test]
+    fn eval_preserve_source_attributes() {
+        let src = "node [a: 1 + 2]";
+
+        let mut doc = Document::new(src);
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(single) = blueprint else { panic!("expected a single node") };
+        assert!(single.source_attributes.get("a").is_none());
+
+        doc.preserve_source = true;
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Single(single) = blueprint else { panic!("expected a single node") };
+        assert_eq!(single.attributes.get("a"), Some(&Expression::from(3)));
+        assert_eq!(single.source_attributes.get("a"), Some(&*add(num(1), num(2))));
+    }
+
+    #[test]
+    fn eval_event_bindings() {
+        let src = "@input [on_key: {enter: submit, esc: cancel}, on_blur: save, a: 1]";
+
+        let mut doc = Document::new(src);
+        doc.add_component("input", "node".to_template()).unwrap();
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Component(component) = blueprint else { panic!("expected a component") };
+
+        let binding = |event: &str, arg: Option<&str>, handler: &str| EventBinding {
+            event: event.into(),
+            arg: arg.map(Into::into),
+            handler: handler.into(),
+        };
+
+        assert_eq!(
+            component.events,
+            vec![
+                binding("key", Some("enter"), "submit"),
+                binding("key", Some("esc"), "cancel"),
+                binding("blur", None, "save"),
+            ]
+        );
+        assert!(component.attributes.get("on_key").is_none());
+        assert_eq!(component.attributes.get("a"), Some(&Expression::from(1)));
+    }
+
+    #[test]
+    fn eval_invalid_event_handler() {
+        let mut doc = Document::new("node [on_key: 1]");
+        assert!(matches!(doc.compile(), Err(Error::InvalidStatement(_))));
+    }
+
     #[test]
     fn eval_component_slots() {
         let src = "
@@ -244,6 +1556,37 @@ mod test {
         assert!(matches!(blueprint, Blueprint::Component(Component { .. })));
     }
 
+    #[test]
+    fn eval_component_slot_with_header() {
+        let src = "
+            @card
+                $body
+                    text 'content'
+        ";
+
+        let card_src = "
+            vstack
+                text 'header'
+                $body
+        ";
+
+        let mut doc = Document::new(src);
+        doc.add_component("card", card_src.to_template()).unwrap();
+        let (blueprint, _) = doc.compile().unwrap();
+        let Blueprint::Component(card) = blueprint else { panic!("expected a component") };
+        let Blueprint::Single(vstack) = &card.body[0] else { panic!("expected a vstack") };
+
+        let values = vstack
+            .children
+            .iter()
+            .map(|child| match child {
+                Blueprint::Single(single) => single.value.as_ref().unwrap().to_string(),
+                _ => panic!("expected text"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, ["header", "content"]);
+    }
+
     #[test]
     fn eval_two_identical_components() {
         let src = "
@@ -57,26 +57,89 @@ impl Context<'_> {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Statement {
     LoadValue(Expression),
-    LoadAttribute { key: StringId, value: Expression },
-    AssociatedFunction { internal: StringId, external: StringId },
-    Component(WidgetComponentId),
+    LoadAttribute {
+        key: StringId,
+        value: Expression,
+    },
+    /// `key?: value`, only set if the value is neither null nor false
+    LoadConditionalAttribute {
+        key: StringId,
+        value: Expression,
+    },
+    SpreadAttributes(Expression),
+    AssociatedFunction {
+        internal: StringId,
+        external: StringId,
+    },
+    /// A component and the line and column of the `@`
+    Component {
+        id: WidgetComponentId,
+        pos: (usize, usize),
+    },
     ComponentSlot(StringId),
     Node(StringId),
-    For { binding: StringId, data: Expression },
-    Declaration { binding: StringId, value: Expression },
+    For {
+        binding: StringId,
+        index: Option<StringId>,
+        data: Expression,
+        key: Option<Expression>,
+    },
+    Declaration {
+        binding: StringId,
+        value: Expression,
+    },
+    /// An attribute the component requires, `require title`
+    Require(StringId),
+    /// `macro name(params)`, followed by the body as a scope
+    Macro {
+        name: StringId,
+        params: Vec<StringId>,
+    },
+    /// `!name(args)`
+    MacroCall {
+        name: StringId,
+        args: Vec<Expression>,
+    },
     If(Expression),
     Else(Option<Expression>),
+    /// `match value`, followed by the arms as a scope
+    Match(Expression),
+    /// `case pattern` or `case pattern if guard`, followed by the body as a scope
+    Case {
+        pattern: Expression,
+        guard: Option<Expression>,
+    },
+    /// `default`, the arm used when no case matches
+    Default,
+    /// A section that is only included if the flag is set on the document
+    IfFlag(StringId),
     ScopeStart,
     ScopeEnd,
     Eof,
 }
 
+/// An attribute, or a map of attributes spread into the attributes (`...map`)
 #[derive(Debug, PartialEq)]
+pub(crate) enum Attribute {
+    Single(StringId, Expression),
+    Conditional(StringId, Expression),
+    Spread(Expression),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Statements(Vec<Statement>);
 
+/// A macro is expanded in place of every call,
+/// with the parameters declared as the arguments of the call.
+#[derive(Debug, Clone)]
+pub(crate) struct Macro {
+    pub(crate) params: Vec<StringId>,
+    pub(crate) body: Statements,
+}
+
 impl From<Vec<Statement>> for Statements {
     fn from(value: Vec<Statement>) -> Self {
         Self(value)
@@ -107,11 +170,20 @@ impl Statements {
         }
     }
 
-    fn take_attributes(&mut self) -> Vec<(StringId, Expression)> {
+    fn take_attributes(&mut self) -> Vec<Attribute> {
         let mut v = vec![];
-        while matches!(&self.0.first(), Some(Statement::LoadAttribute { .. })) {
+        while matches!(
+            &self.0.first(),
+            Some(
+                Statement::LoadAttribute { .. }
+                    | Statement::LoadConditionalAttribute { .. }
+                    | Statement::SpreadAttributes(_)
+            )
+        ) {
             match self.0.remove(0) {
-                Statement::LoadAttribute { key, value } => v.push((key, value)),
+                Statement::LoadAttribute { key, value } => v.push(Attribute::Single(key, value)),
+                Statement::LoadConditionalAttribute { key, value } => v.push(Attribute::Conditional(key, value)),
+                Statement::SpreadAttributes(value) => v.push(Attribute::Spread(value)),
                 _ => unreachable!(),
             }
         }
@@ -222,8 +294,15 @@ mod test {
         }
     }
 
-    pub(crate) fn component(id: impl Into<WidgetComponentId>) -> Statement {
-        Statement::Component(id.into())
+    pub(crate) fn load_cond_attrib(key: impl Into<StringId>, expr: impl Into<Expression>) -> Statement {
+        Statement::LoadConditionalAttribute {
+            key: key.into(),
+            value: expr.into(),
+        }
+    }
+
+    pub(crate) fn component(id: impl Into<WidgetComponentId>, pos: (usize, usize)) -> Statement {
+        Statement::Component { id: id.into(), pos }
     }
 
     pub(crate) fn slot(id: impl Into<StringId>) -> Statement {
@@ -237,6 +316,10 @@ mod test {
         }
     }
 
+    pub(crate) fn spread_attribs(expr: impl Into<Expression>) -> Statement {
+        Statement::SpreadAttributes(expr.into())
+    }
+
     pub(crate) fn node(id: impl Into<StringId>) -> Statement {
         Statement::Node(id.into())
     }
@@ -244,7 +327,22 @@ mod test {
     pub(crate) fn for_loop(binding: impl Into<StringId>, data: impl Into<Expression>) -> Statement {
         Statement::For {
             binding: binding.into(),
+            index: None,
+            data: data.into(),
+            key: None,
+        }
+    }
+
+    pub(crate) fn keyed_for_loop(
+        binding: impl Into<StringId>,
+        data: impl Into<Expression>,
+        key: impl Into<Expression>,
+    ) -> Statement {
+        Statement::For {
+            binding: binding.into(),
+            index: None,
             data: data.into(),
+            key: Some(key.into()),
         }
     }
 
@@ -267,6 +365,24 @@ mod test {
         Statement::Else(None)
     }
 
+    pub(crate) fn match_stmt(value: impl Into<Expression>) -> Statement {
+        Statement::Match(value.into())
+    }
+
+    pub(crate) fn case(pattern: impl Into<Expression>) -> Statement {
+        Statement::Case {
+            pattern: pattern.into(),
+            guard: None,
+        }
+    }
+
+    pub(crate) fn guarded_case(pattern: impl Into<Expression>, guard: impl Into<Expression>) -> Statement {
+        Statement::Case {
+            pattern: pattern.into(),
+            guard: Some(guard.into()),
+        }
+    }
+
     pub(crate) fn scope_start() -> Statement {
         Statement::ScopeStart
     }
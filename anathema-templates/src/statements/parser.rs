@@ -1,13 +1,23 @@
+use std::collections::HashMap;
+
 use anathema_store::storage::strings::{StringId, Strings};
 
 use super::Statement;
 use crate::components::ComponentTemplates;
-use crate::error::{src_line_no, ParseError, ParseErrorKind, Result};
+use crate::error::{src_line_no, Error, ParseError, ParseErrorKind, Result};
 use crate::expressions::parser::parse_expr;
 use crate::expressions::Expression;
 use crate::token::{Kind, Operator, Tokens, Value};
 // use crate::variables::Visibility;
 
+const KEY: &str = "key";
+const MATCH: &str = "match";
+const CASE: &str = "case";
+const DEFAULT: &str = "default";
+const MACRO: &str = "macro";
+const REQUIRE: &str = "require";
+const ENUM: &str = "enum";
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum State {
     EnterScope,
@@ -26,6 +36,36 @@ enum State {
     Done,
 }
 
+// `TopLeft` -> `top_left`
+fn snake_case(name: &str) -> String {
+    let mut output = String::with_capacity(name.len());
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 && !output.ends_with('_') {
+            output.push('_');
+        }
+        output.extend(c.to_lowercase());
+    }
+    output
+}
+
+/// The scopes that are still open at the end of a chunk of source,
+/// so the next chunk can continue where the previous one left off.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ParserScope {
+    open_scopes: Vec<usize>,
+    match_scopes: Vec<usize>,
+    base_indent: Option<usize>,
+}
+
+impl ParserScope {
+    // Close the remaining scopes at the end of the last chunk
+    pub(crate) fn finish(&self) -> impl Iterator<Item = Statement> {
+        (0..self.open_scopes.len())
+            .map(|_| Statement::ScopeEnd)
+            .chain(std::iter::once(Statement::Eof))
+    }
+}
+
 // -----------------------------------------------------------------------------
 //     - Parser -
 // -----------------------------------------------------------------------------
@@ -37,7 +77,15 @@ pub(crate) struct Parser<'src, 'strings, 'components> {
     state: State,
     open_scopes: Vec<usize>,
     closed_scopes: Vec<usize>,
+    // The keys of the attributes of the current node,
+    // and the position of each key
+    attribute_keys: Vec<(StringId, usize)>,
+    // The scope depth of every open `match`
+    match_scopes: Vec<usize>,
     base_indent: usize,
+    // Leave the scopes open at the end of the source,
+    // as more source will follow
+    chunk: bool,
     done: bool,
 }
 
@@ -62,11 +110,40 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
             state: State::EnterScope,
             open_scopes: Vec::new(),
             closed_scopes: Vec::new(),
+            attribute_keys: Vec::new(),
+            match_scopes: Vec::new(),
             base_indent,
+            chunk: false,
             done: false,
         }
     }
 
+    /// Parse a chunk of source, continuing from the scope of the previous chunk.
+    /// The scopes that are still open at the end of the chunk are not closed,
+    /// instead they are returned by [`Parser::into_scope`].
+    pub(crate) fn resume(
+        tokens: Tokens,
+        strings: &'strings mut Strings,
+        src: &'src str,
+        components: &'view mut ComponentTemplates,
+        scope: ParserScope,
+    ) -> Self {
+        let mut parser = Self::new(tokens, strings, src, components);
+        parser.open_scopes = scope.open_scopes;
+        parser.match_scopes = scope.match_scopes;
+        parser.base_indent = scope.base_indent.unwrap_or(parser.base_indent);
+        parser.chunk = true;
+        parser
+    }
+
+    pub(crate) fn into_scope(self) -> ParserScope {
+        ParserScope {
+            open_scopes: self.open_scopes,
+            match_scopes: self.match_scopes,
+            base_indent: Some(self.base_indent),
+        }
+    }
+
     fn error(&self, kind: ParseErrorKind) -> ParseError {
         let (line, col) = src_line_no(self.tokens.previous().1, self.src);
         ParseError {
@@ -77,6 +154,41 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
         }
     }
 
+    // Returns true if there is nothing but whitespace
+    // from `n` tokens ahead to the end of the line
+    fn ends_line(&self, mut n: usize) -> bool {
+        loop {
+            match self.tokens.peek_nth(n) {
+                Kind::Indent(_) => n += 1,
+                Kind::Newline | Kind::Eof => break true,
+                _ => break false,
+            }
+        }
+    }
+
+    // The `count`th token after the current one, not counting indents
+    fn peek_ahead(&self, mut count: usize) -> Kind {
+        let mut n = 1;
+        loop {
+            match self.tokens.peek_nth(n) {
+                Kind::Indent(_) => n += 1,
+                _ if count > 1 => {
+                    count -= 1;
+                    n += 1;
+                }
+                kind => break kind,
+            }
+        }
+    }
+
+    // Returns true if the current line is an arm of a `match`,
+    // closing any `match` that is no longer in scope
+    fn in_match(&mut self) -> bool {
+        let depth = self.open_scopes.len();
+        self.match_scopes.retain(|&scope| scope < depth);
+        self.match_scopes.last().is_some_and(|&scope| scope + 1 == depth)
+    }
+
     fn read_ident(&mut self) -> Result<StringId, ParseError> {
         match self.tokens.next_no_indent() {
             Kind::Value(Value::Ident(ident)) => Ok(ident),
@@ -257,7 +369,28 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
 
         self.tokens.consume();
 
-        let binding = self.read_ident()?;
+        // Optional index binding, e.g `for (i, item) in items`
+        let (index, binding) = match self.tokens.peek_skip_indent() {
+            Kind::Op(Operator::LParen) => {
+                self.tokens.consume();
+                let index = self.read_ident()?;
+
+                if Kind::Op(Operator::Comma) != self.tokens.peek_skip_indent() {
+                    return Err(self.error(ParseErrorKind::InvalidToken { expected: "," }));
+                }
+                self.tokens.consume();
+
+                let binding = self.read_ident()?;
+
+                if Kind::Op(Operator::RParen) != self.tokens.peek_skip_indent() {
+                    return Err(self.error(ParseErrorKind::InvalidToken { expected: ")" }));
+                }
+                self.tokens.consume();
+
+                (Some(index), binding)
+            }
+            _ => (None, self.read_ident()?),
+        };
 
         if Kind::In != self.tokens.peek_skip_indent() {
             return Err(self.error(ParseErrorKind::InvalidToken { expected: "in" }));
@@ -270,11 +403,32 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
             Ok(data) => data,
             Err(e) => return Err(self.error(e)),
         };
+
+        // Optional key used to match iterations when the collection changes,
+        // e.g `for item in items key item.id`.
+        // `key` is not a keyword so it can still be used as an identifier elsewhere.
+        let key = match self.tokens.peek_skip_indent() {
+            Kind::Value(Value::Ident(ident)) if self.strings.get(ident) == Some(KEY) => {
+                self.tokens.consume();
+                match parse_expr(&mut self.tokens, self.strings) {
+                    Ok(key) => Some(key),
+                    Err(e) => return Err(self.error(e)),
+                }
+            }
+            _ => None,
+        };
+
         self.next_state();
-        Ok(Some(Statement::For { data, binding }))
+        Ok(Some(Statement::For {
+            data,
+            binding,
+            index,
+            key,
+        }))
     }
 
     fn parse_if(&mut self) -> Result<Option<Statement>, ParseError> {
+        let in_match = self.in_match();
         match self.tokens.peek_skip_indent() {
             Kind::Else => {
                 self.tokens.consume();
@@ -292,6 +446,47 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
                 self.next_state();
                 Ok(Some(Statement::If(cond)))
             }
+            Kind::Directive => {
+                self.tokens.consume();
+                let flag = self.parse_flag_directive()?;
+                self.next_state();
+                Ok(Some(Statement::IfFlag(flag)))
+            }
+            // `match`, `case` and `default` are not keywords, so they can still be used
+            // as identifiers elsewhere. `match` is followed by the value to match rather
+            // than attributes, and `case` and `default` are only arms in the scope
+            // directly below a `match`.
+            Kind::Value(Value::Ident(ident))
+                if self.strings.get(ident) == Some(MATCH)
+                    && !self.ends_line(1)
+                    && self.peek_ahead(1) != Kind::Op(Operator::LBracket) =>
+            {
+                self.tokens.consume();
+                let value = parse_expr(&mut self.tokens, self.strings).map_err(|e| self.error(e))?;
+                self.match_scopes.push(self.open_scopes.len());
+                self.next_state();
+                Ok(Some(Statement::Match(value)))
+            }
+            Kind::Value(Value::Ident(ident)) if in_match && self.strings.get(ident) == Some(CASE) => {
+                self.tokens.consume();
+                let pattern = parse_expr(&mut self.tokens, self.strings).map_err(|e| self.error(e))?;
+                let guard = match self.tokens.peek_skip_indent() {
+                    Kind::If => {
+                        self.tokens.consume();
+                        Some(parse_expr(&mut self.tokens, self.strings).map_err(|e| self.error(e))?)
+                    }
+                    _ => None,
+                };
+                self.next_state();
+                Ok(Some(Statement::Case { pattern, guard }))
+            }
+            Kind::Value(Value::Ident(ident))
+                if in_match && self.strings.get(ident) == Some(DEFAULT) && self.ends_line(1) =>
+            {
+                self.tokens.consume();
+                self.next_state();
+                Ok(Some(Statement::Default))
+            }
             _ => {
                 self.next_state();
                 Ok(None)
@@ -299,10 +494,76 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
         }
     }
 
+    // Parse the remainder of a directive: `#[if flag]`
+    fn parse_flag_directive(&mut self) -> Result<StringId, ParseError> {
+        if Kind::Op(Operator::LBracket) != self.tokens.next() {
+            return Err(self.error(ParseErrorKind::InvalidToken { expected: "[" }));
+        }
+
+        self.tokens.consume_indent();
+        if Kind::If != self.tokens.next() {
+            return Err(self.error(ParseErrorKind::InvalidToken { expected: "if" }));
+        }
+
+        let flag = self.read_ident()?;
+
+        self.tokens.consume_indent();
+        if Kind::Op(Operator::RBracket) != self.tokens.next() {
+            return Err(self.error(ParseErrorKind::InvalidToken { expected: "]" }));
+        }
+
+        Ok(flag)
+    }
+
     fn parse_declaration(&mut self) -> Result<Option<Statement>, ParseError> {
         // Check if it's a declaration otherwise move on
         match self.tokens.peek_skip_indent() {
             Kind::Decl => (),
+            // `enum` is not a keyword, so it can still be used as an identifier
+            // elsewhere, and only declares an enum when followed by a name and `{`.
+            Kind::Value(Value::Ident(ident))
+                if self.strings.get(ident) == Some(ENUM)
+                    && matches!(self.peek_ahead(1), Kind::Value(Value::Ident(_)))
+                    && self.peek_ahead(2) == Kind::Op(Operator::LCurly) =>
+            {
+                self.tokens.consume();
+                let statement = self.parse_enum()?;
+                self.next_state();
+                return Ok(Some(statement));
+            }
+            // `require` is not a keyword, so it can still be used as an identifier
+            // elsewhere, and only requires an attribute when followed by
+            // nothing but the name of the attribute.
+            Kind::Value(Value::Ident(ident))
+                if self.strings.get(ident) == Some(REQUIRE)
+                    && matches!(self.peek_ahead(1), Kind::Value(Value::Ident(_)))
+                    && matches!(self.peek_ahead(2), Kind::Newline | Kind::Eof) =>
+            {
+                self.tokens.consume();
+                let attribute = self.read_ident()?;
+                self.next_state();
+                return Ok(Some(Statement::Require(attribute)));
+            }
+            // `macro` is not a keyword, so it can still be used as an identifier
+            // elsewhere, and only declares a macro when followed by a name and parameters.
+            Kind::Value(Value::Ident(ident))
+                if self.strings.get(ident) == Some(MACRO)
+                    && matches!(self.peek_ahead(1), Kind::Value(Value::Ident(_)))
+                    && self.peek_ahead(2) == Kind::Op(Operator::LParen) =>
+            {
+                self.tokens.consume();
+                let name = self.read_ident()?;
+                let params = self.parse_macro_params()?;
+                self.next_state();
+                return Ok(Some(Statement::Macro { name, params }));
+            }
+            Kind::Op(Operator::Not) => {
+                self.tokens.consume();
+                let name = self.read_ident()?;
+                let args = self.parse_macro_args()?;
+                self.next_state();
+                return Ok(Some(Statement::MacroCall { name, args }));
+            }
             _ => {
                 self.next_state();
                 return Ok(None);
@@ -324,6 +585,109 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
         Ok(None)
     }
 
+    // An enum is declared as a map from the name of each member to its value:
+    // ```
+    // enum Placement { Relative, Absolute, Custom = 3 }
+    // ```
+    // Members without an explicit value resolve to their name in snake case,
+    // so `Placement.Relative` folds to `"relative"`.
+    fn parse_enum(&mut self) -> Result<Statement, ParseError> {
+        let binding = self.read_ident()?;
+
+        self.tokens.consume_all_whitespace();
+        if Kind::Op(Operator::LCurly) != self.tokens.next() {
+            return Err(self.error(ParseErrorKind::InvalidToken { expected: "{" }));
+        }
+
+        let mut members = HashMap::new();
+        loop {
+            self.tokens.consume_all_whitespace();
+            if Kind::Op(Operator::RCurly) == self.tokens.peek() {
+                self.tokens.consume();
+                break;
+            }
+
+            let ident = self.read_ident()?;
+            let name = self.strings.get_unchecked(ident);
+
+            self.tokens.consume_indent();
+            let value = match self.tokens.peek() {
+                Kind::Equal => {
+                    self.tokens.consume();
+                    self.tokens.consume_indent();
+                    parse_expr(&mut self.tokens, self.strings).map_err(|e| self.error(e))?
+                }
+                _ => Expression::Str(snake_case(&name).into()),
+            };
+            members.insert(name.into(), value);
+
+            self.tokens.consume_all_whitespace();
+            match self.tokens.next() {
+                Kind::Op(Operator::Comma) => continue,
+                Kind::Op(Operator::RCurly) => break,
+                _ => return Err(self.error(ParseErrorKind::InvalidToken { expected: "`,` or `}`" })),
+            }
+        }
+
+        let value = Expression::Map(members.into());
+        Ok(Statement::Declaration { binding, value })
+    }
+
+    // The parameters of a macro: `(a, b)`
+    fn parse_macro_params(&mut self) -> Result<Vec<StringId>, ParseError> {
+        self.tokens.consume_indent();
+        if Kind::Op(Operator::LParen) != self.tokens.next() {
+            return Err(self.error(ParseErrorKind::InvalidToken { expected: "(" }));
+        }
+
+        let mut params = vec![];
+        loop {
+            self.tokens.consume_all_whitespace();
+            if Kind::Op(Operator::RParen) == self.tokens.peek() {
+                self.tokens.consume();
+                break;
+            }
+
+            params.push(self.read_ident()?);
+
+            self.tokens.consume_all_whitespace();
+            match self.tokens.next() {
+                Kind::Op(Operator::Comma) => continue,
+                Kind::Op(Operator::RParen) => break,
+                _ => return Err(self.error(ParseErrorKind::InvalidToken { expected: "`,` or `)`" })),
+            }
+        }
+
+        Ok(params)
+    }
+
+    // The arguments of a macro call: `('a', 1 + 2)`
+    fn parse_macro_args(&mut self) -> Result<Vec<Expression>, ParseError> {
+        if Kind::Op(Operator::LParen) != self.tokens.next() {
+            return Err(self.error(ParseErrorKind::InvalidToken { expected: "(" }));
+        }
+
+        let mut args = vec![];
+        loop {
+            self.tokens.consume_all_whitespace();
+            if Kind::Op(Operator::RParen) == self.tokens.peek() {
+                self.tokens.consume();
+                break;
+            }
+
+            args.push(parse_expr(&mut self.tokens, self.strings).map_err(|e| self.error(e))?);
+
+            self.tokens.consume_all_whitespace();
+            match self.tokens.next() {
+                Kind::Op(Operator::Comma) => continue,
+                Kind::Op(Operator::RParen) => break,
+                _ => return Err(self.error(ParseErrorKind::InvalidToken { expected: "`,` or `)`" })),
+            }
+        }
+
+        Ok(args)
+    }
+
     fn parse_component(&mut self) -> Result<Option<Statement>, ParseError> {
         if Kind::Component != self.tokens.peek_skip_indent() {
             self.next_state();
@@ -331,6 +695,7 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
         }
 
         self.tokens.consume();
+        let pos = src_line_no(self.tokens.previous().1, self.src);
         self.tokens.consume_indent();
 
         let ident = self.read_ident()?;
@@ -345,7 +710,7 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
         // if not move to the next state
 
         self.next_state();
-        Ok(Some(Statement::Component(component_id)))
+        Ok(Some(Statement::Component { id: component_id, pos }))
     }
 
     fn parse_associated_functions(&mut self) -> Result<bool, ParseError> {
@@ -420,6 +785,7 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
         if Kind::Op(Operator::LBracket) == self.tokens.peek_skip_indent() {
             self.tokens.consume();
             self.tokens.consume_all_whitespace();
+            self.attribute_keys.clear();
             self.next_state();
             Ok(true)
         } else {
@@ -430,7 +796,7 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
     // -----------------------------------------------------------------------------
     //     - Stage 4: Parse single attribute -
     // -----------------------------------------------------------------------------
-    fn parse_attribute(&mut self) -> Result<Option<Statement>, ParseError> {
+    fn parse_attribute(&mut self) -> Result<Option<Statement>> {
         // Check for the closing bracket
         if Kind::Op(Operator::RBracket) == self.tokens.peek_skip_indent() {
             self.tokens.consume();
@@ -439,11 +805,44 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
         }
 
         self.tokens.consume_all_whitespace();
+
+        // Spread attributes: `...map`
+        if Kind::Op(Operator::Dot) == self.tokens.peek_skip_indent() {
+            for _ in 0..3 {
+                if Kind::Op(Operator::Dot) != self.tokens.peek() {
+                    return Err(self.error(ParseErrorKind::InvalidToken { expected: "..." }).into());
+                }
+                self.tokens.consume();
+            }
+
+            let value = parse_expr(&mut self.tokens, self.strings).map_err(|e| self.error(e))?;
+            self.consume_attribute_separator()?;
+            return Ok(Some(Statement::SpreadAttributes(value)));
+        }
+
         let key = self.read_ident()?;
+        let pos = self.tokens.previous().1;
+
+        // Spread attributes can be overridden, but a key can only be set once
+        if let Some((_, first)) = self.attribute_keys.iter().find(|(k, _)| *k == key) {
+            return Err(Error::DuplicateAttribute {
+                key: self.strings.get_unchecked(key),
+                first: src_line_no(*first, self.src),
+                second: src_line_no(pos, self.src),
+            });
+        }
+        self.attribute_keys.push((key, pos));
+
+        // Conditional attribute: `key?: value`
+        let conditional = Kind::Op(Operator::Question) == self.tokens.peek();
+        if conditional {
+            self.tokens.consume();
+        }
+
         self.tokens.consume_all_whitespace();
 
         if Kind::Op(Operator::Colon) != self.tokens.peek_skip_indent() {
-            return Err(self.error(ParseErrorKind::InvalidToken { expected: ":" }));
+            return Err(self.error(ParseErrorKind::InvalidToken { expected: ":" }).into());
         }
 
         // Consume `:`
@@ -451,10 +850,18 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
         self.tokens.consume_all_whitespace();
 
         let value = parse_expr(&mut self.tokens, self.strings).map_err(|e| self.error(e))?;
+        self.consume_attribute_separator()?;
+
+        match conditional {
+            true => Ok(Some(Statement::LoadConditionalAttribute { key, value })),
+            false => Ok(Some(Statement::LoadAttribute { key, value })),
+        }
+    }
 
+    // Consume the comma between attributes, or the closing bracket
+    fn consume_attribute_separator(&mut self) -> Result<(), ParseError> {
         self.tokens.consume_all_whitespace();
 
-        // Consume comma
         if Kind::Op(Operator::Comma) == self.tokens.peek() {
             self.tokens.consume();
             self.tokens.consume_all_whitespace();
@@ -465,7 +872,7 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
             return Err(self.error(ParseErrorKind::UnterminatedAttributes));
         }
 
-        Ok(Some(Statement::LoadAttribute { key, value }))
+        Ok(())
     }
 
     // -----------------------------------------------------------------------------
@@ -489,10 +896,21 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
             values.push(expression);
         }
 
+        // Multiple values are concatenated into one,
+        // e.g `text 'a' 'b'` is the same as `text 'ab'`
         let value = match values.len() {
             0 => panic!("invalid state"),
             1 => values.remove(0),
-            _ => Expression::List(values.into()),
+            _ => {
+                let fragments = values
+                    .into_iter()
+                    .flat_map(|value| match value {
+                        Expression::Format(fragments) => fragments.to_vec(),
+                        value => vec![(value, None)],
+                    })
+                    .collect::<Vec<_>>();
+                Expression::Format(fragments.into())
+            }
         };
 
         self.next_state();
@@ -508,7 +926,7 @@ impl<'src, 'strings, 'view> Parser<'src, 'strings, 'view> {
         let token = self.tokens.next();
 
         let ret = match token {
-            Kind::Eof if !self.open_scopes.is_empty() => {
+            Kind::Eof if !self.chunk && !self.open_scopes.is_empty() => {
                 self.open_scopes.pop();
                 return Ok(Some(Statement::ScopeEnd));
             }
@@ -554,11 +972,12 @@ impl Iterator for Parser<'_, '_, '_> {
 mod test {
     use super::*;
     use crate::error::Error;
-    use crate::expressions::{ident, list, map, num, strlit};
+    use crate::expressions::{ident, index, map, num, strlit};
     use crate::lexer::Lexer;
     use crate::statements::test::{
-        associated_fun, component, decl, else_stmt, eof, for_loop, if_else, if_stmt, load_attrib, load_value, node,
-        scope_end, scope_start, slot,
+        associated_fun, case, component, decl, else_stmt, eof, for_loop, guarded_case, if_else, if_stmt,
+        keyed_for_loop, load_attrib, load_cond_attrib, load_value, match_stmt, node, scope_end, scope_start, slot,
+        spread_attribs,
     };
 
     fn parse(src: &str) -> Vec<Result<Statement>> {
@@ -588,6 +1007,34 @@ mod test {
         assert_eq!(node(0), actual);
     }
 
+    #[test]
+    fn parse_spread_attributes() {
+        let src = "a [...b, c: d]";
+        let expected = vec![node(0), spread_attribs(ident("b")), load_attrib(2, ident("d")), eof()];
+
+        let actual = parse_ok(src);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn parse_conditional_attribute() {
+        let src = "a [b?: c]";
+        let expected = vec![node(0), load_cond_attrib(1, ident("c")), eof()];
+
+        let actual = parse_ok(src);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn duplicate_attribute() {
+        let src = "a [color: 1, color: 2]";
+        let err = parse(src).into_iter().collect::<Result<Vec<_>>>().unwrap_err();
+        let Error::DuplicateAttribute { key, first, second } = err else { panic!("invalid error kind") };
+        assert_eq!(key, "color");
+        assert_eq!(first, (1, 4));
+        assert_eq!(second, (1, 14));
+    }
+
     #[test]
     fn parse_attributes() {
         let src = "a [a: a]";
@@ -606,6 +1053,26 @@ mod test {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn parse_preformatted_text() {
+        let src = "
+            a \"\"\"
+                double  spaces
+                  and   indent
+                \"\"\"
+            b
+            ";
+        let expected = vec![
+            node(0),
+            load_value(strlit("double  spaces\n  and   indent")),
+            node(2),
+            eof(),
+        ];
+
+        let actual = parse_ok(src);
+        assert_eq!(expected, actual);
+    }
+
     #[test]
     fn parse_scopes() {
         let src = "
@@ -673,6 +1140,41 @@ mod test {
         assert_eq!(statements.remove(0), scope_end());
     }
 
+    #[test]
+    fn parse_keyed_for_loop() {
+        let src = "
+        for x in data key x.id
+            x
+        ";
+        let mut statements = parse_ok(src);
+
+        let key = Expression::Index(ident("x"), strlit("id"));
+        assert_eq!(statements.remove(0), keyed_for_loop(0, ident("data"), key));
+        assert_eq!(statements.remove(0), scope_start());
+        assert_eq!(statements.remove(0), node(0));
+        assert_eq!(statements.remove(0), scope_end());
+    }
+
+    #[test]
+    fn parse_indexed_for_loop() {
+        let src = "
+        for (i, x) in data
+            x
+        ";
+        let mut statements = parse_ok(src);
+
+        let expected = Statement::For {
+            binding: 1.into(),
+            index: Some(0.into()),
+            data: *ident("data"),
+            key: None,
+        };
+        assert_eq!(statements.remove(0), expected);
+        assert_eq!(statements.remove(0), scope_start());
+        assert_eq!(statements.remove(0), node(1));
+        assert_eq!(statements.remove(0), scope_end());
+    }
+
     #[test]
     fn parse_scopes_and_for() {
         let src = "
@@ -752,16 +1254,109 @@ mod test {
         assert_eq!(statements.remove(0), scope_end());
     }
 
+    #[test]
+    fn parse_match() {
+        let src = "
+        match data
+            case 1
+                x
+            default
+                y
+        default
+        ";
+        let mut statements = parse_ok(src);
+
+        assert_eq!(statements.remove(0), match_stmt(ident("data")));
+        assert_eq!(statements.remove(0), scope_start());
+        assert_eq!(statements.remove(0), case(num(1)));
+        assert_eq!(statements.remove(0), scope_start());
+        assert_eq!(statements.remove(0), node(3));
+        assert_eq!(statements.remove(0), scope_end());
+        assert_eq!(statements.remove(0), Statement::Default);
+        assert_eq!(statements.remove(0), scope_start());
+        assert_eq!(statements.remove(0), node(5));
+        assert_eq!(statements.remove(0), scope_end());
+        assert_eq!(statements.remove(0), scope_end());
+        // Outside of a `match`, `default` is the name of a node
+        assert_eq!(statements.remove(0), node(4));
+    }
+
+    #[test]
+    fn parse_match_case_as_idents() {
+        let src = "
+        match state.match
+            case state.case
+                case
+        match [match: state.match]
+        ";
+        let mut statements = parse_ok(src);
+
+        assert!(matches!(statements.remove(0), Statement::Match(_)));
+        assert_eq!(statements.remove(0), scope_start());
+        assert!(matches!(statements.remove(0), Statement::Case { guard: None, .. }));
+        assert_eq!(statements.remove(0), scope_start());
+        assert_eq!(statements.remove(0), node(2));
+        assert_eq!(statements.remove(0), scope_end());
+        assert_eq!(statements.remove(0), scope_end());
+        assert_eq!(statements.remove(0), node(0));
+        assert_eq!(
+            statements.remove(0),
+            load_attrib(0, index(ident("state"), strlit("match")))
+        );
+    }
+
+    #[test]
+    fn parse_guarded_case() {
+        let src = "
+        match data
+            case 1 if flag
+                x
+        ";
+        let mut statements = parse_ok(src);
+
+        assert_eq!(statements.remove(0), match_stmt(ident("data")));
+        assert_eq!(statements.remove(0), scope_start());
+        assert_eq!(statements.remove(0), guarded_case(num(1), ident("flag")));
+    }
+
+    #[test]
+    fn parse_default_as_ident() {
+        let src = "default [default: default]";
+        let mut statements = parse_ok(src);
+        assert_eq!(statements.remove(0), node(0));
+        assert_eq!(statements.remove(0), load_attrib(0, ident("default")));
+    }
+
+    #[test]
+    fn parse_flag_directive() {
+        let src = "
+        #[if debug]
+            x
+        ";
+        let mut statements = parse_ok(src);
+
+        assert_eq!(statements.remove(0), Statement::IfFlag(0.into()));
+        assert_eq!(statements.remove(0), scope_start());
+        assert_eq!(statements.remove(0), node(1));
+        assert_eq!(statements.remove(0), scope_end());
+    }
+
     #[test]
     fn parse_component() {
         let src = "@mycomp";
         let mut statements = parse_ok(src);
-        assert_eq!(statements.remove(0), component(0));
+        assert_eq!(statements.remove(0), component(0, (1, 1)));
 
         let src = "@mycomp state";
         let mut statements = parse_ok(src);
-        assert_eq!(statements.remove(0), component(0));
+        assert_eq!(statements.remove(0), component(0, (1, 1)));
         assert_eq!(statements.remove(0), load_value(ident("state")));
+
+        let src = "node\n    @mycomp";
+        let mut statements = parse_ok(src);
+        assert_eq!(statements.remove(0), node(0));
+        assert_eq!(statements.remove(0), scope_start());
+        assert_eq!(statements.remove(0), component(0, (2, 5)));
     }
 
     #[test]
@@ -814,7 +1409,8 @@ mod test {
         let src = "a 'a' 'b'";
         let mut statements = parse_ok(src);
         assert_eq!(statements.remove(0), node(0));
-        assert_eq!(statements.remove(0), load_value(list([strlit("a"), strlit("b")])));
+        let expected = Expression::Format([(*strlit("a"), None), (*strlit("b"), None)].into());
+        assert_eq!(statements.remove(0), load_value(expected));
     }
 
     #[test]
@@ -824,6 +1420,76 @@ mod test {
         assert_eq!(statements.remove(0), decl(0, num(1)));
     }
 
+    #[test]
+    fn parse_enum_declaration() {
+        let src = "enum Placement { Relative, Absolute = 1 }";
+        let mut statements = parse_ok(src);
+        let expected = map([("Relative", strlit("relative")), ("Absolute", num(1))]);
+        assert_eq!(statements.remove(0), decl(1, expected));
+    }
+
+    #[test]
+    fn parse_enum_as_ident() {
+        let src = "enum state.enum";
+        let mut statements = parse_ok(src);
+        assert_eq!(statements.remove(0), node(0));
+        assert_eq!(statements.remove(0), load_value(index(ident("state"), strlit("enum"))));
+    }
+
+    #[test]
+    fn parse_require() {
+        let src = "require title";
+        let mut statements = parse_ok(src);
+        assert_eq!(statements.remove(0), Statement::Require(1.into()));
+    }
+
+    #[test]
+    fn parse_require_as_ident() {
+        let src = "require state.require";
+        let mut statements = parse_ok(src);
+        assert_eq!(statements.remove(0), node(0));
+        assert_eq!(
+            statements.remove(0),
+            load_value(index(ident("state"), strlit("require")))
+        );
+    }
+
+    #[test]
+    fn parse_macro() {
+        let src = "
+        macro label(title, value)
+            text title
+        !label('a', 1)
+        ";
+        let statements = parse_ok(src);
+        assert_eq!(
+            statements,
+            vec![
+                Statement::Macro {
+                    name: 1.into(),
+                    params: vec![2.into(), 3.into()],
+                },
+                Statement::ScopeStart,
+                node(4),
+                load_value(ident("title")),
+                Statement::ScopeEnd,
+                Statement::MacroCall {
+                    name: 1.into(),
+                    args: vec![*strlit("a"), *num(1)],
+                },
+                Statement::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_macro_as_ident() {
+        let src = "macro state.macro";
+        let mut statements = parse_ok(src);
+        assert_eq!(statements.remove(0), node(0));
+        assert_eq!(statements.remove(0), load_value(index(ident("state"), strlit("macro"))));
+    }
+
     #[test]
     fn parse_invalid_declaration() {
         let src = "let x = let y = 1";
@@ -869,7 +1535,7 @@ mod test {
         let src = "@x (inner->outer,another -> out)";
         let mut statements = parse_ok(src);
 
-        assert_eq!(statements.remove(0), component(0));
+        assert_eq!(statements.remove(0), component(0, (1, 1)));
         assert_eq!(statements.remove(0), associated_fun(1, 2));
         assert_eq!(statements.remove(0), associated_fun(3, 4));
     }
@@ -882,7 +1548,7 @@ mod test {
         )";
         let mut statements = parse_ok(src);
 
-        assert_eq!(statements.remove(0), component(0));
+        assert_eq!(statements.remove(0), component(0, (1, 1)));
         assert_eq!(statements.remove(0), associated_fun(1, 2));
         assert_eq!(statements.remove(0), associated_fun(3, 4));
     }
@@ -34,6 +34,7 @@ pub enum Operator {
     Dot,
     Comma,
     Colon,
+    Question,
     Association,
 }
 
@@ -66,6 +67,7 @@ impl Display for Operator {
             Self::Dot => write!(f, "."),
             Self::Comma => write!(f, ","),
             Self::Colon => write!(f, ":"),
+            Self::Question => write!(f, "?"),
             Self::LCurly => write!(f, "{{"),
             Self::RCurly => write!(f, "}}"),
             Self::Association => write!(f, "->"),
@@ -77,6 +79,8 @@ impl Display for Operator {
 pub(crate) enum Value {
     Primitive(Primitive),
     String(StringId),
+    /// String with interpolated values, e.g `f"{value:.2} kg"`
+    FormatString(StringId),
     Ident(StringId),
 }
 
@@ -91,6 +95,7 @@ impl Display for Value {
         match self {
             Self::Primitive(val) => write!(f, "{val}"),
             Self::String(s) => write!(f, "\"{s}\""),
+            Self::FormatString(s) => write!(f, "f\"{s}\""),
             Self::Ident(id) => write!(f, "{id}"),
         }
     }
@@ -112,6 +117,7 @@ pub(crate) enum Kind {
     Op(Operator),
 
     Decl,
+    Directive,
 
     Eof,
 }
@@ -137,6 +143,7 @@ impl Display for Kind {
             Self::Value(v) => write!(f, "<value {v}>"),
             Self::Op(o) => write!(f, "<op {o}>"),
             Self::Decl => write!(f, "let"),
+            Self::Directive => write!(f, "#"),
             Self::Eof => write!(f, "<Eof>"),
         }
     }
@@ -227,6 +234,15 @@ impl Tokens {
             .0
     }
 
+    /// Peek at the token `n` tokens ahead, where zero is the next token
+    pub fn peek_nth(&self, n: usize) -> Kind {
+        self.inner
+            .get(self.index + n)
+            .copied()
+            .unwrap_or(Token(Kind::Eof, self.eof))
+            .0
+    }
+
     pub fn previous(&self) -> Token {
         assert!(self.index != 0);
         self.inner
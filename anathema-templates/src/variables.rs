@@ -45,6 +45,7 @@ impl From<VarId> for usize {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Variable {
     Static(Primitive),
@@ -71,7 +72,6 @@ pub struct ScopeId(Rc<[u16]>);
 
 impl ScopeId {
     // Create the next child id.
-    #[cfg(test)]
     fn next(&self, index: u16) -> Self {
         let mut scope_id = Vec::with_capacity(self.0.len() + 1);
         scope_id.extend_from_slice(&self.0);
@@ -80,7 +80,6 @@ impl ScopeId {
     }
 
     // Get the parent id as a slice.
-    #[cfg(test)]
     fn parent(&self) -> &[u16] {
         // Can't get the parent of the root
         debug_assert!(self.0.len() > 1);
@@ -226,7 +225,6 @@ impl Scope {
     // let next = current.next_scope(); // scope 0,0
     // let next = current.next_scope(); // scope 0,1
     // ```
-    #[cfg(test)]
     fn create_child(&mut self) -> ScopeId {
         let index = self.children.len();
         let id = self.id.next(index as u16);
@@ -328,7 +326,6 @@ impl Variables {
 
     /// Create a new child and set the new childs id as the `current` id.
     /// Any operations done from here on out are acting upon the new child scope.
-    #[cfg(test)]
     pub(crate) fn push(&mut self) {
         let parent = self.root.get_scope_mut(&self.current);
         self.current = parent.create_child();
@@ -339,7 +336,6 @@ impl Variables {
     ///
     /// E.e if the current id is `[0, 1, 2]` `pop` would result in a new
     /// id of `[0, 1]`.
-    #[cfg(test)]
     pub(crate) fn pop(&mut self) {
         // panic!("drain and insert phi");
         self.current = self.current.parent().into();
@@ -358,10 +354,12 @@ impl From<Variables> for HashMap<Rc<str>, Expression> {
     fn from(mut vars: Variables) -> Self {
         let mut hm = HashMap::new();
 
-        for (key, mut ids) in vars.declarations.0.into_iter() {
-            let (_, var_id) = ids
-                .pop()
-                .expect("there is always at least one var id associated with a key");
+        // Only the declarations in the root scope are globals,
+        // e.g the parameters of a macro are declared in a child scope
+        for (key, ids) in vars.declarations.0.into_iter() {
+            let Some((_, var_id)) = ids.into_iter().rev().find(|(scope, _)| scope.0.len() == 1) else {
+                continue;
+            };
             let val = vars.store.remove(var_id);
             hm.insert(key, val);
         }
@@ -370,6 +368,7 @@ impl From<Variables> for HashMap<Rc<str>, Expression> {
     }
 }
 
+#[allow(dead_code)]
 pub struct ScopeDebug<'a> {
     level: usize,
     scope: &'a Scope,
@@ -404,6 +403,7 @@ impl DebugWriter for ScopeDebug<'_> {
     }
 }
 
+#[allow(dead_code)]
 pub struct VariablesDebug<'a>(pub(crate) &'a Variables);
 
 impl DebugWriter for VariablesDebug<'_> {
@@ -1,4 +1,4 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::time::Duration;
@@ -11,8 +11,8 @@ use flume::SendError;
 
 use self::events::{Event, KeyEvent, MouseEvent};
 use crate::layout::Viewport;
-use crate::widget::Parent;
-use crate::Elements;
+use crate::widget::{Parent, RepaintQueue};
+use crate::{Elements, WidgetId};
 
 pub mod events;
 
@@ -114,8 +114,23 @@ impl<T> Clone for ComponentId<T> {
 
 impl<T> Copy for ComponentId<T> {}
 
+/// A change to the state of every mounted instance of a component.
+pub enum StateUpdate {
+    /// Replace the state and rebuild the body of the component.
+    /// The type id is that of the new state, which has to match the state
+    /// of the component.
+    Replace(TypeId, Box<dyn FnMut() -> Box<dyn AnyState> + Send>),
+    /// Update the state in place.
+    Merge(Box<dyn FnMut(&mut dyn AnyState) + Send>),
+}
+
+pub enum Payload {
+    Message(Box<dyn Any + Send + Sync>),
+    State(StateUpdate),
+}
+
 pub struct ViewMessage {
-    pub(super) payload: Box<dyn Any + Send + Sync>,
+    pub(super) payload: Payload,
     pub(super) recipient: WidgetComponentId,
 }
 
@@ -124,7 +139,7 @@ impl ViewMessage {
         self.recipient
     }
 
-    pub fn payload(self) -> Box<dyn Any + Send + Sync> {
+    pub fn payload(self) -> Payload {
         self.payload
     }
 }
@@ -145,7 +160,7 @@ impl Emitter {
         value: T,
     ) -> Result<(), SendError<ViewMessage>> {
         let msg = ViewMessage {
-            payload: Box::new(value),
+            payload: Payload::Message(Box::new(value)),
             recipient: component_id.0,
         };
         self.0.send(msg)
@@ -157,11 +172,60 @@ impl Emitter {
         value: T,
     ) -> Result<(), SendError<ViewMessage>> {
         let msg = ViewMessage {
-            payload: Box::new(value),
+            payload: Payload::Message(Box::new(value)),
             recipient: component_id.0,
         };
         self.0.send_async(msg).await
     }
+
+    /// Replace the state of every mounted instance of a component.
+    /// `state` is called once per instance, and the body of each instance
+    /// is rebuilt against the new state.
+    ///
+    /// The update is ignored if `S` is not the state type of the component.
+    pub fn replace_state<T, S>(
+        &self,
+        component_id: ComponentId<T>,
+        mut state: impl FnMut() -> S + Send + 'static,
+    ) -> Result<(), SendError<ViewMessage>>
+    where
+        S: 'static + State,
+    {
+        let update = StateUpdate::Replace(TypeId::of::<S>(), Box::new(move || Box::new(state())));
+        let msg = ViewMessage {
+            payload: Payload::State(update),
+            recipient: component_id.0,
+        };
+        self.0.send(msg)
+    }
+
+    /// Merge values into the state of every mounted instance of a component,
+    /// e.g by setting and inserting entries of a `Map`.
+    /// Only the values that change are updated in the widget tree.
+    ///
+    /// Set existing values in place rather than inserting over them,
+    /// as widgets are subscribed to the value that is already in the state.
+    ///
+    /// The update is ignored if `S` is not the state type of the component.
+    pub fn merge_state<T, S>(
+        &self,
+        component_id: ComponentId<T>,
+        mut f: impl FnMut(&mut S) + Send + 'static,
+    ) -> Result<(), SendError<ViewMessage>>
+    where
+        S: 'static + State,
+    {
+        let update = StateUpdate::Merge(Box::new(move |state: &mut dyn AnyState| {
+            if let Some(state) = state.to_any_mut().downcast_mut::<S>() {
+                f(state);
+            }
+        }));
+        let msg = ViewMessage {
+            payload: Payload::State(update),
+            recipient: component_id.0,
+        };
+        self.0.send(msg)
+    }
 }
 
 pub struct Context<'rt, T>(UntypedContext<'rt>, PhantomData<T>);
@@ -215,6 +279,12 @@ impl<'rt, T: 'static> Context<'rt, T> {
             .emit(recipient, value)
             .expect("this will not fail unless the runtime is droped")
     }
+
+    /// Request a widget to be laid out and painted again.
+    /// Repeated requests within the same frame result in one repaint.
+    pub fn request_repaint(&mut self, widget_id: WidgetId) {
+        self.repaints.request(widget_id);
+    }
 }
 
 impl<'rt, T> Deref for Context<'rt, T> {
@@ -239,6 +309,7 @@ pub struct UntypedContext<'rt> {
     pub parent: Option<Parent>,
     pub strings: &'rt Strings,
     pub assoc_functions: &'rt [(StringId, StringId)],
+    pub repaints: &'rt mut RepaintQueue,
 }
 
 pub struct AssociatedEvent {
@@ -1,17 +1,38 @@
 use anathema_geometry::{LocalPos, Pos, Size};
+use anathema_state::Number;
 
-use crate::layout::text::StringSession;
+use crate::layout::text::{StringSession, StringStorage};
 use crate::layout::{Constraints, LayoutCtx, PositionCtx};
-use crate::paint::{PaintCtx, Unsized};
+use crate::paint::{PaintCtx, SizePos, Unsized};
 use crate::widget::{AnyWidget, PositionChildren};
 use crate::{AttributeStorage, LayoutChildren, PaintChildren, WidgetId};
 
+const OPACITY: &str = "opacity";
+
+// The layout of a static element, reused until either the constraints,
+// the viewport or the attributes of the element have changed.
+// A change below the element invalidates the layout.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct StaticLayout {
+    constraints: Constraints,
+    viewport: Size,
+    attributes: usize,
+}
+
 #[derive(Debug)]
 pub struct Container {
     pub inner: Box<dyn AnyWidget>,
     pub id: WidgetId,
     pub size: Size,
     pub pos: Pos,
+    /// The element and everything below it is constant (see [`Single::is_static`])
+    ///
+    /// [`Single::is_static`]: anathema_templates::blueprints::Single::is_static
+    pub(crate) is_static: bool,
+    pub(crate) static_layout: Option<StaticLayout>,
+    // The text of a static element is kept between frames,
+    // as the text storage of the frame is cleared
+    pub(crate) static_text: StringStorage,
 }
 
 impl Container {
@@ -21,7 +42,23 @@ impl Container {
         constraints: Constraints,
         ctx: &mut LayoutCtx<'_, '_, 'bp>,
     ) -> Size {
-        self.size = self.inner.any_layout(children, constraints, self.id, ctx);
+        let static_layout = self.is_static.then(|| StaticLayout {
+            constraints,
+            viewport: ctx.viewport.size(),
+            attributes: ctx.attribs.get(self.id).version(),
+        });
+
+        match static_layout {
+            None => self.size = self.inner.any_layout(children, constraints, self.id, ctx),
+            Some(_) if static_layout == self.static_layout => {}
+            Some(_) => {
+                self.static_text.clear();
+                let mut ctx = ctx.with_text(self.static_text.new_session());
+                self.size = self.inner.any_layout(children, constraints, self.id, &mut ctx);
+                self.static_layout = static_layout;
+            }
+        }
+
         // Floating widgets always report a zero size
         // as they should not affect their parents
         match self.inner.any_floats() {
@@ -57,6 +94,10 @@ impl Container {
 
         let attrs = attribute_storage.get(self.id);
 
+        if let Some(opacity) = attrs.get::<Number>(OPACITY) {
+            ctx.apply_opacity(opacity.as_float() as f32);
+        }
+
         // Apply all attributes
         for y in 0..self.size.height as u16 {
             for x in 0..self.size.width as u16 {
@@ -65,6 +106,33 @@ impl Container {
             }
         }
 
-        self.inner.any_paint(children, self.id, attribute_storage, ctx, text)
+        match self.is_static {
+            // A static element without a layout has no text to paint
+            true if self.static_layout.is_none() => {}
+            true => {
+                let text = &mut self.static_text.new_session();
+                paint_inner(&mut *self.inner, self.id, children, ctx, text, attribute_storage)
+            }
+            false => paint_inner(&mut *self.inner, self.id, children, ctx, text, attribute_storage),
+        }
+    }
+}
+
+fn paint_inner<'bp>(
+    inner: &mut dyn AnyWidget,
+    id: WidgetId,
+    children: PaintChildren<'_, '_, 'bp>,
+    mut ctx: PaintCtx<'_, SizePos>,
+    text: &mut StringSession<'_>,
+    attribute_storage: &AttributeStorage<'bp>,
+) {
+    match ctx.next_paint_order() {
+        None => inner.any_paint(children, id, attribute_storage, ctx, text),
+        Some(order) => {
+            let (size, pos) = (ctx.local_size, ctx.global_pos);
+            let inner_ctx = ctx.to_unsized().into_sized(size, pos);
+            inner.any_paint(children, id, attribute_storage, inner_ctx, text);
+            ctx.place_glyphs(&order.to_string(), LocalPos::ZERO);
+        }
     }
 }
@@ -37,6 +37,21 @@ impl DebugWriter for EvalValueDebug<'_> {
             EvalValue::Op(_, _, _) => todo!(),
             EvalValue::Not(_) => todo!(),
             EvalValue::Equality(_, _, _) => todo!(),
+            EvalValue::Format(val, spec) => {
+                Self(val).write(output)?;
+                write!(output, ":{spec} ")
+            }
+            EvalValue::Optional(val) => {
+                Self(val).write(output)?;
+                write!(output, "? ")
+            }
+            EvalValue::Conditional(cond, truthy, falsy) => {
+                Self(cond).write(output)?;
+                write!(output, "? ")?;
+                Self(truthy).write(output)?;
+                write!(output, ": ")?;
+                Self(falsy).write(output)
+            }
         }
     }
 }
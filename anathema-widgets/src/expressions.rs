@@ -3,7 +3,7 @@ use std::ops::ControlFlow;
 use std::rc::Rc;
 
 use anathema_state::{register_future, CommonVal, Number, Path, PendingValue, SharedState, States, ValueRef};
-use anathema_templates::expressions::{Equality, Op};
+use anathema_templates::expressions::{Equality, FormatSpec, Op};
 use anathema_templates::{Expression, Globals};
 
 use crate::scope::{Scope, ScopeLookup};
@@ -97,6 +97,17 @@ pub enum EvalValue<'bp> {
     Not(Box<Self>),
     Equality(Box<Self>, Box<Self>, Equality),
 
+    // Interpolation
+    Format(Box<Self>, FormatSpec),
+
+    // Conditional attribute, absent if the value is null or false
+    Optional(Box<Self>),
+
+    // Either the second or the third value depending on the first.
+    // Both branches are kept so a change to the condition
+    // is reflected the next time the value is read.
+    Conditional(Box<Self>, Box<Self>, Box<Self>),
+
     Empty,
 }
 
@@ -128,6 +139,13 @@ impl<'bp> EvalValue<'bp> {
                 rhs.copy_with_sub(value_id).into(),
                 *eq,
             ),
+            Self::Format(val, spec) => Self::Format(val.copy_with_sub(value_id).into(), *spec),
+            Self::Optional(val) => Self::Optional(val.copy_with_sub(value_id).into()),
+            Self::Conditional(cond, truthy, falsy) => Self::Conditional(
+                cond.copy_with_sub(value_id).into(),
+                truthy.copy_with_sub(value_id).into(),
+                falsy.copy_with_sub(value_id).into(),
+            ),
             Self::Empty => Self::Empty,
         }
     }
@@ -138,6 +156,7 @@ impl<'bp> EvalValue<'bp> {
                 value.as_state().and_then(|state| state.state_get(path, value_id))?,
             )),
             EvalValue::Index(value, _) => value.get(path, value_id),
+            EvalValue::Conditional(..) => self.branch()?.get(path, value_id),
             EvalValue::Pending(_) => {
                 unreachable!("pending values are resolved by the scope and should never exist here")
             }
@@ -154,6 +173,8 @@ impl<'bp> EvalValue<'bp> {
             | EvalValue::Op(_, _, _)
             | EvalValue::Not(_)
             | EvalValue::Equality(_, _, _)
+            | EvalValue::Format(_, _)
+            | EvalValue::Optional(_)
             | EvalValue::Empty => None,
         }
     }
@@ -184,6 +205,13 @@ impl<'bp> EvalValue<'bp> {
                 let rhs = rhs.inner_downgrade().into();
                 Self::Equality(lhs, rhs, *eq)
             }
+            Self::Format(val, spec) => Self::Format(val.inner_downgrade().into(), *spec),
+            Self::Optional(val) => Self::Optional(val.inner_downgrade().into()),
+            Self::Conditional(cond, truthy, falsy) => Self::Conditional(
+                cond.inner_downgrade().into(),
+                truthy.inner_downgrade().into(),
+                falsy.inner_downgrade().into(),
+            ),
             Self::Empty => Self::Empty,
         }
     }
@@ -220,14 +248,57 @@ impl<'bp> EvalValue<'bp> {
                 let rhs = rhs.inner_upgrade(value_id).into();
                 Self::Equality(lhs, rhs, *eq)
             }
+            Self::Format(val, spec) => Self::Format(val.inner_upgrade(value_id).into(), *spec),
+            Self::Optional(val) => Self::Optional(val.inner_upgrade(value_id).into()),
+            Self::Conditional(cond, truthy, falsy) => Self::Conditional(
+                cond.inner_upgrade(value_id).into(),
+                truthy.inner_upgrade(value_id).into(),
+                falsy.inner_upgrade(value_id).into(),
+            ),
             Self::Empty => future_value(value_id),
         }
     }
 
+    // The branch of a conditional value picked by the current condition
+    fn branch(&self) -> Option<&Self> {
+        match self {
+            Self::Conditional(cond, truthy, falsy) => match cond.load_bool() {
+                true => Some(truthy),
+                false => Some(falsy),
+            },
+            _ => None,
+        }
+    }
+
     pub(crate) fn downgrade(&self) -> Downgraded<'bp> {
         Downgraded(self.inner_downgrade())
     }
 
+    /// Call the closure with the key and the value of every entry,
+    /// if the value is a map.
+    /// The keys of a state map are visited in order.
+    pub(crate) fn map_entries(&self, f: &mut impl FnMut(Rc<str>, Downgraded<'bp>)) {
+        match self {
+            EvalValue::Dyn(value) => {
+                let Some(state) = value.as_state() else { return };
+                let mut keys = state.state_keys();
+                keys.sort();
+                for key in keys {
+                    let Some(value) = state.state_lookup(Path::Key(&key)) else { continue };
+                    f(key, Downgraded(EvalValue::Pending(value)));
+                }
+            }
+            EvalValue::ExprMap(map) => map.iter().for_each(|(key, value)| f(key.clone(), value.downgrade())),
+            EvalValue::Index(value, _) => value.map_entries(f),
+            EvalValue::Conditional(..) => {
+                if let Some(value) = self.branch() {
+                    value.map_entries(f)
+                }
+            }
+            _ => {}
+        }
+    }
+
     pub fn str_for_each<F>(&self, mut f: F)
     where
         F: FnMut(&str),
@@ -237,10 +308,7 @@ impl<'bp> EvalValue<'bp> {
             ControlFlow::Continue(())
         };
 
-        match self.internal_str_iter(&mut wrapped_f) {
-            Some(control_flow) => control_flow,
-            None => ControlFlow::Break(()),
-        };
+        let _ = self.internal_str_iter(&mut wrapped_f);
     }
 
     pub fn str_iter<F>(&self, mut f: F) -> ControlFlow<()>
@@ -260,7 +328,7 @@ impl<'bp> EvalValue<'bp> {
         let val = match self {
             EvalValue::ExprList(list) => {
                 for value in list.iter() {
-                    value.internal_str_iter(f)?;
+                    let _ = value.internal_str_iter(f)?;
                 }
                 ControlFlow::Continue(())
             }
@@ -277,6 +345,16 @@ impl<'bp> EvalValue<'bp> {
                 f(s)
             }
             EvalValue::Index(val, _) => val.internal_str_iter(f)?,
+            EvalValue::Conditional(..) => self.branch()?.internal_str_iter(f)?,
+            EvalValue::Format(val, spec) => {
+                let val = val.load_common_val()?;
+                let s = match val.to_common()? {
+                    CommonVal::Int(n) => spec.format_int(n),
+                    CommonVal::Float(n) => spec.format_float(n),
+                    val => spec.format_str(val.to_common_str().as_ref()),
+                };
+                f(&s)
+            }
             _ => {
                 let val = self.load_common_val()?;
                 let val = val.to_common()?;
@@ -294,9 +372,20 @@ impl<'bp> EvalValue<'bp> {
             EvalValue::Static(val) => Some(Either::Static(*val)),
             EvalValue::Dyn(val) => Some(Either::Dyn(val.as_state()?)),
             EvalValue::Index(val, _) => val.load_common_val(),
+            EvalValue::Conditional(..) => self.branch()?.load_common_val(),
             EvalValue::Pending(_) => None,
             EvalValue::ExprMap(_) => None,
             EvalValue::ExprList(_) => None,
+            // A formatted value is an owned string and
+            // can only be read through `str_iter`
+            EvalValue::Format(..) => None,
+            EvalValue::Optional(val) => {
+                let value = val.load_common_val()?;
+                match value.to_common()? {
+                    CommonVal::Bool(false) => None,
+                    _ => Some(value),
+                }
+            }
 
             // Operations
             EvalValue::Negative(expr) => expr.load_number().map(|n| -n).map(Into::into),
@@ -375,6 +464,12 @@ impl<'bp> EvalValue<'bp> {
                 None => val.as_state()?.to_common()?.try_into().ok(),
             },
             EvalValue::Index(val, _) => val.load::<T>(),
+            EvalValue::Conditional(..) => self.branch()?.load::<T>(),
+            // Absent if the value is null or false
+            EvalValue::Optional(val) => match val.load_common_val()?.to_common()? {
+                CommonVal::Bool(false) => None,
+                _ => val.load::<T>(),
+            },
             EvalValue::Op(lhs, rhs, op) => {
                 let lhs = lhs.load_number()?;
                 let rhs = rhs.load_number()?;
@@ -400,6 +495,9 @@ impl<'bp> EvalValue<'bp> {
                 let val = CommonVal::Bool(s.load_bool());
                 T::try_from(val).ok()
             }
+            // An interpolated string is a list of fragments, and
+            // a formatted value is an owned string, neither is a typed value
+            EvalValue::ExprList(_) | EvalValue::Format(..) => None,
             EvalValue::Empty => None,
             e => panic!("{e:?}"),
         }
@@ -411,6 +509,9 @@ impl<'bp> EvalValue<'bp> {
         match self {
             Self::Index(..) => true,
             Self::ExprList(list) => list.iter().any(Self::contains_index),
+            Self::Conditional(cond, truthy, falsy) => {
+                cond.contains_index() || truthy.contains_index() || falsy.contains_index()
+            }
             Self::ExprMap(_) => todo!(),
             _ => false,
         }
@@ -564,6 +665,21 @@ impl<'bp> ValueResolver<'bp> {
             // -----------------------------------------------------------------------------
             E::Primitive(val) => V::Static((*val).into()),
             E::Str(s) => V::Static(CommonVal::Str(s)),
+            // The fragments are joined when the value is read as a string
+            E::Format(fragments) => {
+                let inner = fragments
+                    .iter()
+                    .map(|(expr, spec)| {
+                        let value = self.reset_offset().resolve(expr, scope, states);
+                        match spec {
+                            Some(spec) => V::Format(value.into(), *spec),
+                            None => value,
+                        }
+                    })
+                    .collect();
+                V::ExprList(inner)
+            }
+            E::Optional(expr) => V::Optional(self.resolve(expr, scope, states).into()),
             E::Map(map) => {
                 let inner = map
                     .iter()
@@ -588,6 +704,11 @@ impl<'bp> ValueResolver<'bp> {
             //   - Conditionals -
             // -----------------------------------------------------------------------------
             E::Not(expr) => V::Not(self.resolve(expr, scope, states).into()),
+            E::Conditional { cond, truthy, falsy } => V::Conditional(
+                self.reset_offset().resolve(cond, scope, states).into(),
+                self.reset_offset().resolve(truthy, scope, states).into(),
+                self.reset_offset().resolve(falsy, scope, states).into(),
+            ),
             E::Equality(lhs, rhs, eq) => V::Equality(
                 self.reset_offset().resolve(lhs, scope, states).into(),
                 self.reset_offset().resolve(rhs, scope, states).into(),
@@ -653,8 +774,8 @@ mod test {
 
     use anathema_state::{List, Map, Value};
     use anathema_templates::expressions::{
-        add, and, eq, greater_than, greater_than_equal, ident, index, less_than, less_than_equal, mul, neg, not, num,
-        or, strlit, sub,
+        add, and, conditional, eq, greater_than, greater_than_equal, ident, index, less_than, less_than_equal, mul,
+        neg, not, num, or, strlit, sub, Expression, FormatSpec,
     };
 
     use crate::testing::ScopedTest;
@@ -694,6 +815,22 @@ mod test {
             });
     }
 
+    #[test]
+    fn load_interpolated_string() {
+        let spec = FormatSpec::parse("03").unwrap();
+        let expr = Expression::Format([(*strlit("n: "), None), (*ident("a"), Some(spec))].into());
+        let mut t = ScopedTest::new().with_value("a", 7u32).with_expr(expr);
+
+        t.eval(|value| {
+            // A string is not a typed value
+            assert_eq!(value.load::<u32>(), None);
+
+            let mut s = String::new();
+            value.str_for_each(|frag| s.push_str(frag));
+            assert_eq!(s, "n: 007");
+        });
+    }
+
     #[test]
     fn simple_lookup() {
         let mut t = ScopedTest::new().with_value("a", 1u32).with_expr(ident("a"));
@@ -853,4 +990,16 @@ mod test {
                 assert!(b);
             });
     }
+
+    #[test]
+    fn dyn_conditional() {
+        ScopedTest::new()
+            .with_value("a", false)
+            .with_expr(conditional(ident("a"), strlit("yes"), strlit("no")))
+            .eval(|value| {
+                let mut s = String::new();
+                value.str_for_each(|v| s.push_str(v));
+                assert_eq!(s, "no");
+            });
+    }
 }
@@ -48,6 +48,30 @@ impl Constraints {
         }
     }
 
+    /// Subtract `width` from the max width and return the amount that was
+    /// actually subtracted. This is less than `width` if the max width was
+    /// clamped at zero.
+    pub fn sub_max_width_checked(&mut self, width: usize) -> usize {
+        if self.max_width == usize::MAX {
+            return width;
+        }
+        let subtracted = width.min(self.max_width);
+        self.sub_max_width(width);
+        subtracted
+    }
+
+    /// Subtract `height` from the max height and return the amount that was
+    /// actually subtracted. This is less than `height` if the max height was
+    /// clamped at zero.
+    pub fn sub_max_height_checked(&mut self, height: usize) -> usize {
+        if self.max_height == usize::MAX {
+            return height;
+        }
+        let subtracted = height.min(self.max_height);
+        self.sub_max_height(height);
+        subtracted
+    }
+
     /// Create a set of constraints with a given max width / height.
     /// If `None` is passed for either `max_width` and / or `max_height` then this is qualified as
     /// "unbounded" constraints.
@@ -66,6 +90,23 @@ impl Constraints {
         }
     }
 
+    /// Create tight constraints, where the min and max
+    /// are both the given size.
+    pub fn tight(size: Size) -> Self {
+        Self {
+            min_width: size.width,
+            min_height: size.height,
+            max_width: size.width,
+            max_height: size.height,
+        }
+    }
+
+    /// Create loose constraints, where the min is zero
+    /// and the max is the given size.
+    pub fn loose(max: Size) -> Self {
+        Self::new(max.width, max.height)
+    }
+
     /// Create unbounded constraints.
     pub fn unbounded() -> Self {
         Self {
@@ -187,7 +228,7 @@ impl Constraints {
 
 impl From<Size> for Constraints {
     fn from(value: Size) -> Self {
-        Self::new(value.width, value.height)
+        Self::loose(value)
     }
 }
 
@@ -196,3 +237,58 @@ impl Default for Constraints {
         Self::ZERO
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tight() {
+        let constraints = Constraints::tight(Size::new(3, 4));
+        assert_eq!(constraints.min_width, 3);
+        assert_eq!(constraints.min_height, 4);
+        assert_eq!(constraints.max_width(), 3);
+        assert_eq!(constraints.max_height(), 4);
+        assert!(constraints.is_width_tight());
+        assert!(constraints.is_height_tight());
+    }
+
+    #[test]
+    fn loose() {
+        let constraints = Constraints::loose(Size::new(3, 4));
+        assert_eq!(constraints.min_width, 0);
+        assert_eq!(constraints.min_height, 0);
+        assert_eq!(constraints.max_width(), 3);
+        assert_eq!(constraints.max_height(), 4);
+    }
+
+    #[test]
+    fn unbounded() {
+        let constraints = Constraints::unbounded();
+        assert_eq!(constraints.min_width, 0);
+        assert_eq!(constraints.min_height, 0);
+        assert!(constraints.is_unbounded());
+    }
+
+    #[test]
+    fn sub_max_width_checked() {
+        let mut constraints = Constraints::new(3, 3);
+        assert_eq!(constraints.sub_max_width_checked(2), 2);
+        assert_eq!(constraints.sub_max_width_checked(5), 1);
+        assert_eq!(constraints.max_width(), 0);
+    }
+
+    #[test]
+    fn sub_max_height_checked() {
+        let mut constraints = Constraints::new(3, 3);
+        assert_eq!(constraints.sub_max_height_checked(4), 3);
+        assert_eq!(constraints.max_height(), 0);
+    }
+
+    #[test]
+    fn sub_unbounded_checked() {
+        let mut constraints = Constraints::unbounded();
+        assert_eq!(constraints.sub_max_width_checked(5), 5);
+        assert!(constraints.is_width_unbounded());
+    }
+}
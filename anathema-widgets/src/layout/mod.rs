@@ -2,6 +2,7 @@ use std::ops::ControlFlow;
 
 use anathema_geometry::{Pos, Size};
 use anathema_store::tree::{Node, TreeFilter, TreeForEach, TreeValues};
+use anathema_templates::Document;
 
 pub use self::constraints::Constraints;
 pub use self::display::Display;
@@ -37,6 +38,121 @@ impl Viewport {
     }
 }
 
+/// Terminal capabilities, set by the host.
+///
+/// The capabilities are available to templates as the globals `truecolor` and `unicode`.
+/// ```
+/// # use anathema_widgets::layout::Capabilities;
+/// # use anathema_templates::Document;
+/// let mut doc = Document::new("if unicode\n    text '█'\nelse\n    text '#'");
+/// let capabilities = Capabilities { unicode: false, ..Default::default() };
+/// capabilities.set_globals(&mut doc);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub truecolor: bool,
+    pub unicode: bool,
+}
+
+impl Capabilities {
+    pub const TRUECOLOR: &'static str = "truecolor";
+    pub const UNICODE: &'static str = "unicode";
+
+    /// Expose the capabilities as globals in the document
+    pub fn set_globals(&self, document: &mut Document) {
+        document.set_global(Self::TRUECOLOR, self.truecolor);
+        document.set_global(Self::UNICODE, self.unicode);
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            truecolor: true,
+            unicode: true,
+        }
+    }
+}
+
+/// The glyphs that make up a box, e.g a border.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BoxGlyphs {
+    pub top_left: char,
+    pub top: char,
+    pub top_right: char,
+    pub right: char,
+    pub bottom_right: char,
+    pub bottom: char,
+    pub bottom_left: char,
+    pub left: char,
+}
+
+/// Glyphs used by widgets that draw boxes and lines,
+/// making it possible to switch every widget between unicode and ASCII.
+/// ```
+/// # use anathema_widgets::layout::GlyphTheme;
+/// let theme = GlyphTheme::ASCII;
+/// assert_eq!(theme.thin.top_left, '+');
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlyphTheme {
+    pub thin: BoxGlyphs,
+    pub thick: BoxGlyphs,
+}
+
+impl GlyphTheme {
+    pub const UNICODE: Self = Self {
+        thin: BoxGlyphs {
+            top_left: '┌',
+            top: '─',
+            top_right: '┐',
+            right: '│',
+            bottom_right: '┘',
+            bottom: '─',
+            bottom_left: '└',
+            left: '│',
+        },
+        thick: BoxGlyphs {
+            top_left: '╔',
+            top: '═',
+            top_right: '╗',
+            right: '║',
+            bottom_right: '╝',
+            bottom: '═',
+            bottom_left: '╚',
+            left: '║',
+        },
+    };
+    pub const ASCII: Self = Self {
+        thin: BoxGlyphs {
+            top_left: '+',
+            top: '-',
+            top_right: '+',
+            right: '|',
+            bottom_right: '+',
+            bottom: '-',
+            bottom_left: '+',
+            left: '|',
+        },
+        thick: BoxGlyphs {
+            top_left: '+',
+            top: '=',
+            top_right: '+',
+            right: '|',
+            bottom_right: '+',
+            bottom: '=',
+            bottom_left: '+',
+            left: '|',
+        },
+    };
+}
+
+impl Default for GlyphTheme {
+    fn default() -> Self {
+        Self::UNICODE
+    }
+}
+
 /// Filter out widgets that are excluded.
 /// This includes both `Show` and `Hide` as part of the layout.
 pub struct LayoutFilter<'frame, 'bp> {
@@ -96,6 +212,7 @@ pub struct LayoutCtx<'a, 'buf, 'bp> {
     pub text: StringSession<'buf>,
     pub attribs: &'a AttributeStorage<'bp>,
     pub viewport: &'a Viewport,
+    pub glyph_theme: GlyphTheme,
 }
 
 impl<'a, 'buf, 'bp> LayoutCtx<'a, 'buf, 'bp> {
@@ -104,6 +221,17 @@ impl<'a, 'buf, 'bp> LayoutCtx<'a, 'buf, 'bp> {
             text,
             attribs,
             viewport,
+            glyph_theme: GlyphTheme::default(),
+        }
+    }
+
+    /// The same context, but with text laid out in a different session.
+    pub fn with_text<'t>(&self, text: StringSession<'t>) -> LayoutCtx<'a, 't, 'bp> {
+        LayoutCtx {
+            text,
+            attribs: self.attribs,
+            viewport: self.viewport,
+            glyph_theme: self.glyph_theme,
         }
     }
 }
@@ -134,6 +262,32 @@ pub fn position_widget<'bp>(
     element.position(children, pos, attribute_storage);
 }
 
+/// The result of laying out a child: the size of the child and the
+/// constraints it was given.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ChildLayout {
+    pub size: Size,
+    pub constraints: Constraints,
+}
+
+impl ChildLayout {
+    /// The constraints left over once the width of the child is
+    /// subtracted from the max width.
+    pub fn remaining_horizontal(&self) -> Constraints {
+        let mut constraints = self.constraints;
+        constraints.sub_max_width(self.size.width);
+        constraints
+    }
+
+    /// The constraints left over once the height of the child is
+    /// subtracted from the max height.
+    pub fn remaining_vertical(&self) -> Constraints {
+        let mut constraints = self.constraints;
+        constraints.sub_max_height(self.size.height);
+        constraints
+    }
+}
+
 pub struct PositionCtx {
     pub inner_size: Size,
     pub pos: Pos,
@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::ops::{AddAssign, Deref};
+use std::rc::Rc;
 
 use anathema_geometry::Size;
 use anathema_state::CommonVal;
@@ -7,7 +9,7 @@ use anathema_store::tree::ValueId;
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Word wrapping strategy
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
 pub enum Wrap {
     /// Normal word wrapping. This will break text on hyphen and whitespace.
     /// Trailing whitespace is consumed if it would cause a line break.
@@ -78,6 +80,10 @@ pub enum ProcessResult {
 }
 
 /// A shared storage of byte, layout and line data for string layout.
+///
+/// The runtime clears its storage every frame. A widget that keeps the layout
+/// of its children between frames has to keep a storage of its own,
+/// as the text of children that are not laid out again would otherwise be lost.
 pub struct StringStorage {
     // All the bytes that makes up all the strings.
     bytes: Buffer<u8>,
@@ -115,6 +121,18 @@ impl StringStorage {
     }
 }
 
+impl Default for StringStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for StringStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StringStorage").finish_non_exhaustive()
+    }
+}
+
 /// A temporary session for text layout.
 pub struct StringSession<'buf> {
     bytes: Session<'buf, u8>,
@@ -431,10 +449,7 @@ impl<'buf> Strings<'_, 'buf> {
 
     /// Finalize the layout, converting entries to lines
     pub fn finish(mut self) -> (LayoutKey, Size) {
-        self.layout
-            .inner
-            .slice_mut(self.layout.key)
-            .sort_by(|a, b| a.0.cmp(&b.0));
+        self.layout.inner.slice_mut(self.layout.key).sort_by_key(|a| a.0);
 
         let slice = self.bytes.slice();
         let last_line = self.line(slice.len());
@@ -492,6 +507,198 @@ impl<'buf> Strings<'_, 'buf> {
     }
 }
 
+/// Measure the size of a string laid out within `max`.
+/// ```
+/// # use anathema_geometry::Size;
+/// # use anathema_widgets::layout::text::*;
+/// let size = measure("hello world", Size::new(5, 10), Wrap::Normal);
+/// assert_eq!(size, Size::new(5, 2));
+/// ```
+pub fn measure(s: &str, max: Size, wrap: Wrap) -> Size {
+    let mut string_storage = StringStorage::new();
+    let mut session = string_storage.new_session();
+    let mut strings = session.new_layout(max, wrap);
+    strings.add_str(s);
+    let (_, size) = strings.finish();
+    size
+}
+
+/// The start and end (exclusive) char index of a visual line
+/// produced by [`layout_lines`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LineSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Layout a string and return the char indices of each visual line.
+///
+/// Characters removed by the layout (newlines, whitespace causing a line break
+/// and characters too wide to ever fit) are not part of any span.
+/// ```
+/// # use anathema_widgets::layout::text::*;
+/// let spans = layout_lines("hello world", 5, Wrap::Normal);
+/// assert_eq!(spans, vec![LineSpan { start: 0, end: 5 }, LineSpan { start: 6, end: 11 }]);
+/// ```
+pub fn layout_lines(s: &str, width: usize, wrap: Wrap) -> Vec<LineSpan> {
+    layout_spans(&mut StringStorage::new(), s, width, wrap).lines
+}
+
+// Layout a string within `width` using the given storage,
+// returning the size, the char indices and the width of each visual line.
+fn layout_spans(string_storage: &mut StringStorage, s: &str, width: usize, wrap: Wrap) -> Measured {
+    string_storage.clear();
+    let mut session = string_storage.new_session();
+    let mut strings = session.new_layout(Size::new(width, usize::MAX), wrap);
+    strings.add_str(s);
+    let (key, size) = strings.finish();
+
+    let chars = s.chars().collect::<Vec<_>>();
+    let too_wide = |c: char| c.width().unwrap_or(0) > width;
+    let mut spans = vec![];
+    let mut widths = vec![];
+    let mut index = 0;
+
+    for line in session.lines(key) {
+        widths.push(line.width);
+
+        while index < chars.len() && too_wide(chars[index]) {
+            index += 1;
+        }
+
+        let start = index;
+        for segment in line.entries {
+            let Segment::Str(text) = segment else { continue };
+            for c in text.chars() {
+                while too_wide(chars[index]) {
+                    index += 1;
+                }
+                debug_assert_eq!(c, chars[index]);
+                index += 1;
+            }
+        }
+        spans.push(LineSpan { start, end: index });
+
+        // Skip the character consumed by the line break, if any
+        match chars.get(index) {
+            Some('\n') => index += 1,
+            Some(c) if c.is_whitespace() && line.width as usize + c.width().unwrap_or(0) > width => index += 1,
+            _ => {}
+        }
+    }
+
+    Measured {
+        size,
+        lines: spans,
+        widths,
+    }
+}
+
+/// The result of measuring a string, see [`MeasureCache`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Measured {
+    pub size: Size,
+    pub lines: Vec<LineSpan>,
+    /// The width of each line
+    pub widths: Vec<u16>,
+}
+
+// The number of measurements a `MeasureCache` holds before it's cleared
+const MEASURE_CACHE_CAPACITY: usize = 32;
+
+/// Cache the size and line spans of strings laid out at a given width,
+/// so static text isn't measured again every frame.
+///
+/// Entries are keyed by the string, so a changed string is measured again.
+/// Use [`MeasureCache::invalidate`] to drop the entries of a string that is no longer used.
+/// The cache is cleared once it holds 32 measurements, so it never grows unbounded.
+/// ```
+/// # use anathema_geometry::Size;
+/// # use anathema_widgets::layout::text::*;
+/// let mut cache = MeasureCache::new();
+/// let size = cache.measure("hello world", 5, Wrap::Normal).size;
+/// assert_eq!(size, Size::new(5, 2));
+/// ```
+#[derive(Debug, Default)]
+pub struct MeasureCache {
+    entries: HashMap<Rc<str>, HashMap<(usize, Wrap), Measured>>,
+    len: usize,
+    // Reused by every measurement
+    string_storage: StringStorage,
+    measurements: usize,
+    hits: usize,
+}
+
+impl MeasureCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure the string within `width`, or return the cached measurement
+    pub fn measure(&mut self, s: &str, width: usize, wrap: Wrap) -> &Measured {
+        let cached = self
+            .entries
+            .get(s)
+            .is_some_and(|entries| entries.contains_key(&(width, wrap)));
+
+        if cached {
+            self.hits += 1;
+        } else {
+            if self.len == MEASURE_CACHE_CAPACITY {
+                self.clear();
+            }
+
+            let measured = layout_spans(&mut self.string_storage, s, width, wrap);
+            self.measurements += 1;
+            self.len += 1;
+
+            if !self.entries.contains_key(s) {
+                self.entries.insert(s.into(), HashMap::new());
+            }
+            let entries = self.entries.get_mut(s).expect("the entry was just inserted");
+            entries.insert((width, wrap), measured);
+        }
+
+        &self.entries[s][&(width, wrap)]
+    }
+
+    /// Get a cached measurement without measuring the string
+    pub fn get(&self, s: &str, width: usize, wrap: Wrap) -> Option<&Measured> {
+        self.entries.get(s)?.get(&(width, wrap))
+    }
+
+    /// Remove every cached measurement of the string
+    pub fn invalidate(&mut self, s: &str) {
+        if let Some(entries) = self.entries.remove(s) {
+            self.len -= entries.len();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.len = 0;
+    }
+
+    /// The number of cached measurements
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of times a string was measured rather than read from the cache
+    pub fn measurements(&self) -> usize {
+        self.measurements
+    }
+
+    /// The number of times a measurement was read from the cache
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
 #[derive(Debug)]
 enum Chomper {
     Continuous(usize),
@@ -542,6 +749,44 @@ impl Chomper {
 mod test {
     use super::*;
 
+    #[test]
+    fn measure_cache_hit() {
+        let mut cache = MeasureCache::new();
+        let first = cache.measure("hello world", 5, Wrap::Normal).clone();
+        let second = cache.measure("hello world", 5, Wrap::Normal).clone();
+        assert_eq!(first, second);
+        assert_eq!(first.size, Size::new(5, 2));
+        assert_eq!(cache.measurements(), 1);
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.get("hello world", 5, Wrap::Normal), Some(&first));
+
+        // Different width, wrap or string
+        cache.measure("hello world", 6, Wrap::Normal);
+        cache.measure("hello world", 5, Wrap::WordBreak);
+        cache.measure("hello", 5, Wrap::Normal);
+        assert_eq!(cache.measurements(), 4);
+
+        cache.invalidate("hello world");
+        assert_eq!(cache.len(), 1);
+        cache.measure("hello world", 5, Wrap::Normal);
+        assert_eq!(cache.measurements(), 5);
+    }
+
+    #[test]
+    fn measure_cache_is_bounded() {
+        let mut cache = MeasureCache::new();
+        for width in 1..=MEASURE_CACHE_CAPACITY {
+            cache.measure("hello world", width, Wrap::Normal);
+        }
+        assert_eq!(cache.len(), MEASURE_CACHE_CAPACITY);
+
+        // The cache is cleared rather than growing past its capacity
+        cache.measure("hello", 5, Wrap::Normal);
+        assert_eq!(cache.len(), 1);
+        cache.measure("hello world", 1, Wrap::Normal);
+        assert_eq!(cache.measurements(), MEASURE_CACHE_CAPACITY + 2);
+    }
+
     fn test_layout(max: Size, input: &[&str], expected: &str, wrap: Wrap) {
         let mut string_storage = StringStorage::new();
         let mut session = string_storage.new_session();
@@ -633,4 +878,29 @@ mod test {
         strings.freeze();
         assert_eq!(strings.add_str("abc"), ProcessResult::Break);
     }
+
+    fn spans(spans: &[(usize, usize)]) -> Vec<LineSpan> {
+        spans.iter().map(|&(start, end)| LineSpan { start, end }).collect()
+    }
+
+    #[test]
+    fn word_wrap_line_spans() {
+        let actual = layout_lines("hello do the", 5, Wrap::Normal);
+        assert_eq!(actual, spans(&[(0, 5), (6, 9), (9, 12)]));
+
+        let actual = layout_lines("a\nb\n", 5, Wrap::Normal);
+        assert_eq!(actual, spans(&[(0, 1), (2, 3), (4, 4)]));
+
+        let actual = layout_lines("a  b", 1, Wrap::Normal);
+        assert_eq!(actual, spans(&[(0, 1), (2, 3), (3, 4)]));
+    }
+
+    #[test]
+    fn char_wrap_line_spans() {
+        let actual = layout_lines("123 4567", 5, Wrap::WordBreak);
+        assert_eq!(actual, spans(&[(0, 5), (5, 8)]));
+
+        let actual = layout_lines("🐇🐇🐇", 5, Wrap::WordBreak);
+        assert_eq!(actual, spans(&[(0, 2), (2, 3)]));
+    }
 }
@@ -2,11 +2,14 @@ pub use scope::{DebugScope, Scope};
 pub use values::ValueIndex;
 
 pub use crate::nodes::eval::EvalContext;
-pub use crate::nodes::{eval_blueprint, try_resolve_future_values, update_tree, Element, Stringify, WidgetKind};
+pub use crate::nodes::{
+    eval_blueprint, rebind_component_state, try_resolve_future_values, update_tree, Element, Stringify, WidgetKind,
+};
 pub use crate::values::{Value, Values};
 pub use crate::widget::{
-    AnyWidget, AttributeStorage, Attributes, ComponentParents, Components, Elements, Factory, FloatingWidgets,
-    LayoutChildren, PaintChildren, PositionChildren, Widget, WidgetId, WidgetRenderer, WidgetTree,
+    find_by_class, next_focus, AnyWidget, AttributeStorage, Attributes, ComponentParents, Components, Elements,
+    Factory, FloatingWidgets, LayoutChildren, PaintChildren, PositionChildren, RepaintQueue, Widget, WidgetId,
+    WidgetRenderer, WidgetTree,
 };
 
 pub mod components;
@@ -20,5 +23,6 @@ pub mod paint;
 mod scope;
 #[cfg(test)]
 mod testing;
+pub mod units;
 mod values;
 mod widget;
@@ -1,8 +1,10 @@
+use std::any::Any;
+
 use anathema_geometry::{Pos, Size};
 
 use crate::container::Container;
 use crate::layout::text::StringSession;
-use crate::layout::{Constraints, LayoutCtx};
+use crate::layout::{ChildLayout, Constraints, LayoutCtx};
 use crate::paint::{PaintCtx, Unsized};
 use crate::widget::{PaintChildren, PositionChildren};
 use crate::{AttributeStorage, LayoutChildren, WidgetId};
@@ -11,6 +13,7 @@ use crate::{AttributeStorage, LayoutChildren, WidgetId};
 pub struct Element<'bp> {
     pub ident: &'bp str,
     pub(crate) container: Container,
+    user_data: Option<Box<dyn Any>>,
 }
 
 impl<'bp> Element<'bp> {
@@ -19,7 +22,11 @@ impl<'bp> Element<'bp> {
     }
 
     pub(crate) fn new(ident: &'bp str, container: Container) -> Self {
-        Self { ident, container }
+        Self {
+            ident,
+            container,
+            user_data: None,
+        }
     }
 
     pub fn layout(
@@ -31,6 +38,18 @@ impl<'bp> Element<'bp> {
         self.container.layout(children, constraints, ctx)
     }
 
+    /// Layout the element and return both the size and the constraints,
+    /// so the remaining space can be passed on to the next sibling.
+    pub fn layout_with_remaining(
+        &mut self,
+        children: LayoutChildren<'_, '_, 'bp>,
+        constraints: Constraints,
+        ctx: &mut LayoutCtx<'_, '_, 'bp>,
+    ) -> ChildLayout {
+        let size = self.layout(children, constraints, ctx);
+        ChildLayout { size, constraints }
+    }
+
     pub fn paint(
         &mut self,
         children: PaintChildren<'_, '_, 'bp>,
@@ -51,6 +70,25 @@ impl<'bp> Element<'bp> {
         self.container.position(children, pos, attribute_storage);
     }
 
+    pub(crate) fn invalidate_layout(&mut self) {
+        self.container.static_layout = None;
+        self.container.inner.any_invalidate_layout();
+    }
+
+    /// The size the element would have within the constraints, see [`Widget::negotiate`]
+    ///
+    /// [`Widget::negotiate`]: crate::Widget::negotiate
+    pub fn negotiate(
+        &mut self,
+        children: LayoutChildren<'_, '_, 'bp>,
+        constraints: Constraints,
+        ctx: &mut LayoutCtx<'_, '_, 'bp>,
+    ) -> Size {
+        self.container
+            .inner
+            .any_negotiate(children, constraints, self.id(), ctx)
+    }
+
     pub fn size(&self) -> Size {
         self.container.size
     }
@@ -87,4 +125,25 @@ impl<'bp> Element<'bp> {
     pub fn get_pos(&self) -> Pos {
         self.container.pos
     }
+
+    /// Attach arbitrary data to the element, replacing any previous data.
+    /// The data lives as long as the element.
+    pub fn set_user_data<T: 'static>(&mut self, data: T) {
+        self.user_data = Some(Box::new(data));
+    }
+
+    /// Get a reference to the user data, if it's set and of the given type
+    pub fn user_data<T: 'static>(&self) -> Option<&T> {
+        self.user_data.as_ref()?.downcast_ref()
+    }
+
+    /// Get a mutable reference to the user data, if it's set and of the given type
+    pub fn user_data_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.user_data.as_mut()?.downcast_mut()
+    }
+
+    /// Remove the user data from the element
+    pub fn take_user_data(&mut self) -> Option<Box<dyn Any>> {
+        self.user_data.take()
+    }
 }
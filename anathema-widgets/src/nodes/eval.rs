@@ -7,12 +7,13 @@ use anathema_templates::blueprints::{Component, ControlFlow, Else, For, If, Sing
 use anathema_templates::{Globals, WidgetComponentId};
 
 use super::element::Element;
-use super::loops::{Iteration, LOOP_INDEX};
+use super::loops::Iteration;
 use super::{component, controlflow};
 use crate::components::{AnyComponent, ComponentKind, ComponentRegistry};
 use crate::container::Container;
 use crate::error::{Error, Result};
 use crate::expressions::{eval, eval_collection};
+use crate::layout::text::StringStorage;
 use crate::values::{ValueId, ValueIndex};
 use crate::widget::{Attributes, Components, FloatingWidgets, ValueKey};
 use crate::{eval_blueprint, AttributeStorage, Factory, Scope, WidgetKind, WidgetTree};
@@ -106,6 +107,13 @@ impl Evaluator for SingleEval {
             });
         }
 
+        for expr in &single.spread {
+            attributes.insert_with(ValueKey::Spread, |value_index| {
+                eval(expr, ctx.globals, ctx.scope, ctx.states, (widget_id, value_index))
+            });
+        }
+        attributes.expand_spread();
+
         let widget = ctx.factory.make(&single.ident, &attributes)?;
 
         // Is the widget a floating widget?
@@ -121,6 +129,9 @@ impl Evaluator for SingleEval {
             id: widget_id,
             pos: Pos::ZERO,
             size: Size::ZERO,
+            is_static: single.is_static,
+            static_layout: None,
+            static_text: StringStorage::new(),
         };
 
         // Widget
@@ -148,22 +159,27 @@ impl ForLoopEval {
         parent: &[u16],
         tree: &mut WidgetTree<'bp>,
     ) -> Result<()> {
+        let value_id = ValueId::from((tree.id(parent).ok_or(Error::TreeTransactionFailed)?, ValueIndex::ZERO));
+
         for index in 0..for_loop.collection.count() {
             ctx.scope.push();
             for_loop.scope_value(ctx.scope, index);
 
+            let key = for_loop.eval_key(ctx, value_id);
             let iter_id = tree
                 .insert(parent)
                 .commit_child(WidgetKind::Iteration(Iteration {
                     loop_index: Value::new(index as i64),
                     binding: for_loop.binding,
+                    index: for_loop.index,
+                    key,
                 }))
                 .ok_or(Error::TreeTransactionFailed)?;
 
             // Scope the iteration value
             tree.with_value(iter_id, |parent, widget, tree| {
                 let WidgetKind::Iteration(iter) = widget else { unreachable!() };
-                ctx.scope.scope_pending(LOOP_INDEX, iter.loop_index.to_pending());
+                iter.scope_index(ctx.scope);
 
                 for bp in for_loop.body {
                     eval_blueprint(bp, ctx, parent, tree)?;
@@ -191,10 +207,17 @@ impl Evaluator for ForLoopEval {
         let transaction = tree.insert(parent);
         let value_id = ValueId::from((transaction.node_id(), ValueIndex::ZERO));
 
+        let collection = eval_collection(&for_loop.data, ctx.globals, ctx.scope, ctx.states, value_id);
+        let showing_empty = collection.count() == 0 && !for_loop.empty_body.is_empty();
+
         let for_loop = super::loops::For {
             binding: &for_loop.binding,
-            collection: eval_collection(&for_loop.data, ctx.globals, ctx.scope, ctx.states, value_id),
+            index: for_loop.index.as_deref(),
+            collection,
+            key: for_loop.key.as_ref(),
             body: &for_loop.body,
+            empty_body: &for_loop.empty_body,
+            showing_empty,
         };
 
         let widget = WidgetKind::For(for_loop);
@@ -204,6 +227,9 @@ impl Evaluator for ForLoopEval {
         tree.with_value(for_loop_id, move |parent, widget, tree| {
             let WidgetKind::For(for_loop) = widget else { unreachable!() };
             self.eval_body(for_loop, ctx, parent, tree)?;
+            if for_loop.showing_empty {
+                for_loop.eval_empty_body(ctx, parent, tree)?;
+            }
             Ok(())
         })?;
 
@@ -4,7 +4,6 @@ use anathema_templates::Globals;
 
 use super::element::Element;
 use super::eval::EvalContext;
-use super::loops::LOOP_INDEX;
 use super::update::scope_value;
 use crate::components::ComponentRegistry;
 use crate::error::{Error, Result};
@@ -77,6 +76,7 @@ pub fn try_resolve_future_values<'bp>(
     };
 
     tree.apply_path_finder(path, res);
+    super::update::invalidate_layout(path, tree);
 }
 
 fn try_resolve_value<'bp>(
@@ -88,11 +88,8 @@ fn try_resolve_value<'bp>(
 ) -> Result<()> {
     match widget {
         WidgetKind::Element(Element { container, .. }) => {
-            let Some(val) = ctx
-                .attribute_storage
-                .get_mut(container.id)
-                .get_mut_with_index(value_id.index())
-            else {
+            let attributes = ctx.attribute_storage.get_mut(container.id);
+            let Some(val) = attributes.get_mut_with_index(value_id.index()) else {
                 return Ok(());
             };
 
@@ -100,6 +97,9 @@ fn try_resolve_value<'bp>(
                 let value = eval(expr, ctx.globals, ctx.scope, ctx.states, value_id);
                 *val = value;
             }
+
+            // A spread map might have been resolved
+            attributes.expand_spread();
         }
         WidgetKind::For(for_loop) => {
             // 1. Assign a new collection
@@ -115,6 +115,7 @@ fn try_resolve_value<'bp>(
             );
 
             tree.remove_children(path);
+            for_loop.showing_empty = false;
 
             let collection = &for_loop.collection;
             let binding = &for_loop.binding;
@@ -154,18 +155,21 @@ fn try_resolve_value<'bp>(
                     Collection::Future => {}
                 }
 
+                let key = for_loop.eval_key(ctx, value_id);
                 let iter_id = tree
                     .insert(parent)
                     .commit_child(WidgetKind::Iteration(super::loops::Iteration {
                         loop_index: anathema_state::Value::new(index as i64),
                         binding,
+                        index: for_loop.index,
+                        key,
                     }))
                     .ok_or(Error::TreeTransactionFailed)?;
 
                 // Scope the iteration value
                 tree.with_value(iter_id, |parent, widget, tree| {
                     let WidgetKind::Iteration(iter) = widget else { unreachable!() };
-                    ctx.scope.scope_pending(LOOP_INDEX, iter.loop_index.to_pending());
+                    iter.scope_index(ctx.scope);
 
                     for bp in body {
                         crate::eval_blueprint(bp, ctx, parent, tree)?;
@@ -175,6 +179,10 @@ fn try_resolve_value<'bp>(
 
                 ctx.scope.pop();
             }
+
+            if for_loop.collection.count() == 0 {
+                for_loop.showing_empty = for_loop.eval_empty_body(ctx, path, tree)?;
+            }
         }
         WidgetKind::If(widget) => {
             if let Some(expr) = widget.cond.expr {
@@ -1,13 +1,15 @@
 use anathema_state::Change;
-use anathema_store::tree::new_node_path;
+use anathema_store::tree::{new_node_path, Node, TreeValues};
 use anathema_templates::blueprints::Blueprint;
+use anathema_templates::Expression;
 
+use super::update::scope_value;
 use super::WidgetKind;
 use crate::error::{Error, Result};
-use crate::expressions::eval_collection;
+use crate::expressions::{eval, eval_collection};
 use crate::nodes::EvalContext;
 use crate::scope::Scope;
-use crate::values::{Collection, ValueId};
+use crate::values::{Collection, ValueId, ValueIndex};
 use crate::{eval_blueprint, Value, WidgetTree};
 
 pub(super) const LOOP_INDEX: &str = "loop";
@@ -15,8 +17,12 @@ pub(super) const LOOP_INDEX: &str = "loop";
 #[derive(Debug)]
 pub struct For<'bp> {
     pub(super) binding: &'bp str,
+    pub(super) index: Option<&'bp str>,
     pub(super) collection: Value<'bp, Collection<'bp>>,
+    pub(super) key: Option<&'bp Expression>,
     pub(super) body: &'bp [Blueprint],
+    pub(super) empty_body: &'bp [Blueprint],
+    pub(super) showing_empty: bool,
 }
 
 impl<'bp> For<'bp> {
@@ -28,6 +34,136 @@ impl<'bp> For<'bp> {
         self.collection.inner()
     }
 
+    /// Evaluate the key of the value currently scoped to the binding.
+    /// Returns `None` if the loop has no key, or the key has no value.
+    pub(super) fn eval_key(&self, ctx: &EvalContext<'_, '_, 'bp>, value_id: ValueId) -> Option<Box<str>> {
+        let expr = self.key?;
+        let value = eval(expr, ctx.globals, ctx.scope, ctx.states, value_id);
+        let key = (*value.load_common_val()?.to_common()?.to_common_str()).into();
+        Some(key)
+    }
+
+    /// Evaluate the `else` body of the loop directly under the loop node.
+    /// Only call this when the loop has no iterations.
+    ///
+    /// Returns `true` if anything was evaluated.
+    pub(super) fn eval_empty_body(
+        &self,
+        ctx: &mut EvalContext<'_, '_, 'bp>,
+        path: &[u16],
+        tree: &mut WidgetTree<'bp>,
+    ) -> Result<bool> {
+        for bp in self.empty_body {
+            eval_blueprint(bp, ctx, path, tree)?;
+        }
+        Ok(!self.empty_body.is_empty())
+    }
+
+    fn clear_empty_body(&mut self, path: &[u16], tree: &mut WidgetTree<'bp>) {
+        if self.showing_empty {
+            tree.remove_children(path);
+            self.showing_empty = false;
+        }
+    }
+
+    /// Insert a new iteration at `index` and evaluate the body.
+    /// The value has to be scoped to the binding before calling this.
+    fn insert_iteration(
+        &self,
+        ctx: &mut EvalContext<'_, '_, 'bp>,
+        path: &[u16],
+        index: usize,
+        key: Option<Box<str>>,
+        tree: &mut WidgetTree<'bp>,
+    ) -> Result<()> {
+        let insert_at = new_node_path(path, index as u16);
+        let iter_id = tree
+            .insert(&insert_at)
+            .commit_at(WidgetKind::Iteration(Iteration {
+                loop_index: anathema_state::Value::new(index as i64),
+                binding: self.binding,
+                index: self.index,
+                key,
+            }))
+            .ok_or(Error::TreeTransactionFailed)?;
+
+        tree.with_value(iter_id, |parent, iter_widget, tree| {
+            let WidgetKind::Iteration(iter) = iter_widget else { unreachable!() };
+            iter.scope_index(ctx.scope);
+
+            for bp in self.body {
+                eval_blueprint(bp, ctx, parent, tree)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Match the iterations to the values of the new collection by key.
+    /// Iterations with a matching key are moved into place and keep their
+    /// widgets (and any state), the rest are removed and new iterations
+    /// are created for values without a matching iteration.
+    fn update_keyed(
+        &mut self,
+        ctx: &mut EvalContext<'_, '_, 'bp>,
+        value_id: ValueId,
+        path: &[u16],
+        tree: &mut WidgetTree<'bp>,
+    ) -> Result<()> {
+        // The keys of the iterations, in the same order as the children of the loop
+        let mut keys = vec![];
+        tree.children_of(path, |node, values| {
+            if let Some((_, WidgetKind::Iteration(iter))) = values.get(node.value()) {
+                keys.push(iter.key.clone());
+            }
+        });
+
+        let count = self.collection.count();
+        for index in 0..count {
+            ctx.scope.push();
+            self.scope_value(ctx.scope, index);
+            let key = self.eval_key(ctx, value_id);
+
+            let existing = match key {
+                Some(_) => keys[index..].iter().position(|k| *k == key).map(|pos| pos + index),
+                None => None,
+            };
+
+            match existing {
+                Some(current) => {
+                    tree.move_child(path, current, index);
+                    let key = keys.remove(current);
+                    keys.insert(index, key);
+
+                    let iter_path = new_node_path(path, index as u16);
+                    if let Some(WidgetKind::Iteration(iter)) = tree.get_mut_by_path(&iter_path) {
+                        if *iter.loop_index.to_ref() != index as i64 {
+                            *iter.loop_index.to_mut() = index as i64;
+                        }
+                    }
+
+                    // The widgets still refer to the value of the replaced collection
+                    if let Some((node, values)) = tree.get_node_by_path(&iter_path) {
+                        rebind(std::slice::from_ref(node), values, ctx);
+                    }
+                }
+                None => {
+                    self.insert_iteration(ctx, path, index, key.clone(), tree)?;
+                    keys.insert(index, key);
+                }
+            }
+
+            ctx.scope.pop();
+        }
+
+        // Remove the iterations that didn't match any value
+        for index in (count..keys.len()).rev() {
+            tree.remove(&new_node_path(path, index as u16));
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn update(
         &mut self,
         ctx: &mut EvalContext<'_, '_, 'bp>,
@@ -45,67 +181,60 @@ impl<'bp> For<'bp> {
                 // 5. Scope new value
                 // 6. Eval body
 
+                self.clear_empty_body(path, tree);
+
+                // NOTE
+                // The value has to be scoped to the current binding and not
+                // the iteration, since the collection might've changed more than once
+                // and differ from what's represented by the tree.
+                //
+                // E.g
+                // Two inserts at 0 would result in scoping the same value twice:
+                // the current values in the collection at position 0.
+                //
+                // If the list starts out with ["a"]
+                // The tree will contain a ValueRef -> "a".
+                //
+                // If two values are added to the list:
+                // list.insert(0, "b");
+                // list.insert(0, "c");
+                //
+                // The change output will be Change::Insert(0, "b")
+                // The change output will be Change::Insert(0, "c")
+                //
+                // However the list is ["c", "b", "a"] before the first
+                // change is applied, which would lead to scoping `"c" to `0`
+                // twice.
                 ctx.scope.push();
                 ctx.scope.scope_pending(self.binding, *value);
 
-                let insert_at = new_node_path(path, *index as u16);
-                let iter_id = tree
-                    .insert(&insert_at)
-                    .commit_at(WidgetKind::Iteration(Iteration {
-                        loop_index: anathema_state::Value::new(*index as i64),
-                        binding: self.binding,
-                    }))
-                    .unwrap(); // TODO unwrap
+                let key = self.eval_key(ctx, value_id);
+                self.insert_iteration(ctx, path, *index as usize, key, tree)?;
 
                 // Bump the index for every subsequent sibling of the newly inserted node
+                let insert_at = new_node_path(path, *index as u16);
                 tree.children_after(&insert_at, |node, values| {
                     let iter_widget = values.get_mut(node.value());
                     let Some((_, WidgetKind::Iteration(iter))) = iter_widget else { unreachable!() };
                     *iter.loop_index.to_mut() += 1;
                 });
 
-                tree.with_value(iter_id, |parent, iter_widget, tree| {
-                    // NOTE
-                    // The value has to be scoped to the current binding and not
-                    // the iteration, since the collection might've changed more than once
-                    // and differ from what's represented by the tree.
-                    //
-                    // E.g
-                    // Two inserts at 0 would result in scoping the same value twice:
-                    // the current values in the collection at position 0.
-                    //
-                    // If the list starts out with ["a"]
-                    // The tree will contain a ValueRef -> "a".
-                    //
-                    // If two values are added to the list:
-                    // list.insert(0, "b");
-                    // list.insert(0, "c");
-                    //
-                    // The change output will be Change::Insert(0, "b")
-                    // The change output will be Change::Insert(0, "c")
-                    //
-                    // However the list is ["c", "b", "a"] before the first
-                    // change is applied, which would lead to scoping `"c" to `0`
-                    // twice.
-                    let WidgetKind::Iteration(iter) = iter_widget else { unreachable!() };
-                    ctx.scope.scope_pending(LOOP_INDEX, iter.loop_index.to_pending());
-
-                    for bp in self.body {
-                        eval_blueprint(bp, ctx, parent, tree)?;
-                    }
-
-                    Ok(())
-                })?;
-
                 ctx.scope.pop();
             }
             Change::Removed(index) => {
                 let child_to_remove = new_node_path(path, *index as u16);
                 tree.remove(&child_to_remove);
+
+                let is_empty = tree
+                    .get_node_by_path(path)
+                    .map(|(node, _)| node.children().is_empty())
+                    .unwrap_or(false);
+
+                if is_empty {
+                    self.showing_empty = self.eval_empty_body(ctx, path, tree)?;
+                }
             }
             Change::Dropped => {
-                tree.remove_children(path);
-
                 // TODO unwrap, ewww
                 self.collection = eval_collection(
                     self.collection.expr.unwrap(),
@@ -115,31 +244,45 @@ impl<'bp> For<'bp> {
                     value_id,
                 );
 
-                for index in 0..self.collection.count() {
-                    self.scope_value(ctx.scope, index);
-                    ctx.scope.push();
-
-                    let iter_id = tree
-                        .insert(path)
-                        .commit_child(WidgetKind::Iteration(Iteration {
-                            loop_index: anathema_state::Value::new(index as i64),
-                            binding: self.binding,
-                        }))
-                        .ok_or(Error::TreeTransactionFailed)?;
-
-                    // Scope the iteration value
-                    tree.with_value(iter_id, |parent, widget, tree| -> Result<()> {
-                        let WidgetKind::Iteration(iter) = widget else { unreachable!() };
-                        ctx.scope.scope_pending(LOOP_INDEX, iter.loop_index.to_pending());
-
-                        for bp in self.body {
-                            eval_blueprint(bp, ctx, parent, tree)?;
-                        }
-
-                        Ok(())
-                    })?;
+                if self.key.is_some() && !self.showing_empty {
+                    self.update_keyed(ctx, value_id, path, tree)?;
+                } else {
+                    tree.remove_children(path);
+                    self.showing_empty = false;
+
+                    for index in 0..self.collection.count() {
+                        self.scope_value(ctx.scope, index);
+                        ctx.scope.push();
+
+                        let key = self.eval_key(ctx, value_id);
+                        let iter_id = tree
+                            .insert(path)
+                            .commit_child(WidgetKind::Iteration(Iteration {
+                                loop_index: anathema_state::Value::new(index as i64),
+                                binding: self.binding,
+                                index: self.index,
+                                key,
+                            }))
+                            .ok_or(Error::TreeTransactionFailed)?;
+
+                        // Scope the iteration value
+                        tree.with_value(iter_id, |parent, widget, tree| -> Result<()> {
+                            let WidgetKind::Iteration(iter) = widget else { unreachable!() };
+                            iter.scope_index(ctx.scope);
+
+                            for bp in self.body {
+                                eval_blueprint(bp, ctx, parent, tree)?;
+                            }
+
+                            Ok(())
+                        })?;
+
+                        ctx.scope.pop();
+                    }
+                }
 
-                    ctx.scope.pop();
+                if self.collection.count() == 0 {
+                    self.showing_empty = self.eval_empty_body(ctx, path, tree)?;
                 }
             }
             Change::Changed => {
@@ -156,10 +299,80 @@ impl<'bp> For<'bp> {
     }
 }
 
+/// Re-evaluate the values of the widgets against the current scope,
+/// scoping the values of each widget for its children.
+fn rebind<'bp>(nodes: &[Node], values: &mut TreeValues<WidgetKind<'bp>>, ctx: &mut EvalContext<'_, '_, 'bp>) {
+    for node in nodes {
+        let Some((_, widget)) = values.get_mut(node.value()) else { continue };
+        let value_id = ValueId::from((node.value(), ValueIndex::ZERO));
+
+        match widget {
+            WidgetKind::Element(el) => {
+                let attributes = ctx.attribute_storage.get_mut(el.id());
+                attributes.reload_all(ctx.globals, ctx.scope, ctx.states);
+            }
+            WidgetKind::If(widget) => {
+                if let Some(expr) = widget.cond.expr {
+                    widget.cond = eval(expr, ctx.globals, ctx.scope, ctx.states, value_id);
+                }
+            }
+            WidgetKind::Else(el) => {
+                if let Some(val) = &mut el.cond {
+                    if let Some(expr) = val.expr {
+                        *val = eval(expr, ctx.globals, ctx.scope, ctx.states, value_id);
+                    }
+                }
+            }
+            WidgetKind::For(for_loop) => {
+                if let Some(expr) = for_loop.collection.expr {
+                    for_loop.collection = eval_collection(expr, ctx.globals, ctx.scope, ctx.states, value_id);
+                }
+            }
+            WidgetKind::Component(component) => {
+                if let Some(state) = &mut component.external_state {
+                    for ((_, index), value) in state.iter_mut() {
+                        if let Some(expr) = value.expr {
+                            let value_id = ValueId::from((node.value(), *index));
+                            *value = eval(expr, ctx.globals, ctx.scope, ctx.states, value_id);
+                        }
+                    }
+                }
+            }
+            WidgetKind::ControlFlow(_) | WidgetKind::Iteration(_) => {}
+        }
+
+        // A loop scopes a different value for each child
+        let is_loop = matches!(widget, WidgetKind::For(_));
+        for (index, child) in node.children().iter().enumerate() {
+            ctx.scope.push();
+            if let Some((_, widget)) = values.get(node.value()) {
+                let children: &[u16] = if is_loop { &[index as u16] } else { &[] };
+                scope_value(widget, ctx.scope, children);
+            }
+            rebind(std::slice::from_ref(child), values, ctx);
+            ctx.scope.pop();
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Iteration<'bp> {
     pub loop_index: anathema_state::Value<i64>,
     pub binding: &'bp str,
+    /// The name of the index, if the loop has one
+    pub index: Option<&'bp str>,
+    /// The key of the value, if the loop is keyed
+    pub key: Option<Box<str>>,
+}
+
+impl<'bp> Iteration<'bp> {
+    /// Scope the index of the iteration as `loop`, and as the index binding if there is one.
+    pub(super) fn scope_index(&self, scope: &mut Scope<'bp>) {
+        scope.scope_pending(LOOP_INDEX, self.loop_index.to_pending());
+        if let Some(index) = self.index {
+            scope.scope_pending(index, self.loop_index.to_pending());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -292,6 +505,109 @@ mod test {
         assert_eq!(expected.trim(), output.trim());
     }
 
+    #[test]
+    fn keyed_loop_keeps_iterations() {
+        let mut map = Map::<List<_>>::empty();
+        map.insert("a", List::from_iter([1u32, 2, 3]));
+
+        let tpl = "
+        for x in a key x
+            test x
+        ";
+
+        let (blueprint, globals) = Document::new(tpl).compile().unwrap();
+        let mut tree = WidgetTree::empty();
+        let mut attribute_storage = AttributeStorage::empty();
+        let mut floating_widgets = FloatingWidgets::empty();
+        let factory = setup_test_factory();
+        let mut component_registry = ComponentRegistry::new();
+        let mut components = Components::new();
+        let mut states = States::new();
+        let state_id = states.insert(Box::new(map));
+        let mut scope = Scope::new();
+        scope.insert_state(state_id);
+        let mut ctx = EvalContext::new(
+            &globals,
+            &factory,
+            &mut scope,
+            &mut states,
+            &mut component_registry,
+            &mut attribute_storage,
+            &mut floating_widgets,
+            &mut components,
+        );
+
+        eval_blueprint(&blueprint, &mut ctx, root_node(), &mut tree).unwrap();
+
+        let iteration_ids = |tree: &mut WidgetTree<'_>| {
+            let mut ids = vec![];
+            tree.children_of(&[0], |node, values| {
+                let Some((_, WidgetKind::Iteration(iter))) = values.get(node.value()) else { return };
+                ids.push((iter.key.clone().unwrap(), node.value()));
+            });
+            ids
+        };
+
+        let before = iteration_ids(&mut tree);
+
+        // Replace the list with the same values in a different order, and a new value
+        {
+            let map = states.get_mut(StateId::ZERO).unwrap();
+            let map = map
+                .to_any_mut()
+                .downcast_mut::<anathema_state::Value<Map<List<u32>>>>()
+                .unwrap();
+            map.insert("a", List::from_iter([3u32, 1, 2, 4]));
+        }
+
+        let mut changes = Changes::empty();
+        drain_changes(&mut changes);
+        changes.drain().rev().for_each(|(subs, change)| {
+            subs.with(|sub| {
+                let Some(path) = tree.try_path(sub) else { return };
+                let mut scope = Scope::with_capacity(10);
+                scope.insert_state(state_id);
+                update_tree(
+                    &globals,
+                    &factory,
+                    &mut scope,
+                    &mut states,
+                    &mut component_registry,
+                    &change,
+                    sub,
+                    &path,
+                    &mut tree,
+                    &mut attribute_storage,
+                    &mut floating_widgets,
+                    &mut components,
+                );
+            });
+        });
+
+        let mut stringify = Stringify::new(&attribute_storage);
+        tree.apply_visitor(&mut stringify);
+        let output = stringify.finish();
+
+        let expected = "
+<for>
+    <iter binding = x, index = 0>
+        test Int(3)
+    <iter binding = x, index = 1>
+        test Int(1)
+    <iter binding = x, index = 2>
+        test Int(2)
+    <iter binding = x, index = 3>
+        test Int(4)";
+        assert_eq!(expected.trim(), output.trim());
+
+        // The iterations of the values that are still present are the same
+        let after = iteration_ids(&mut tree);
+        assert_eq!(after[0], before[2]);
+        assert_eq!(after[1], before[0]);
+        assert_eq!(after[2], before[1]);
+        assert!(!before.iter().any(|(_, id)| *id == after[3].1));
+    }
+
     #[test]
     fn eval_for() {
         let mut list = List::empty();
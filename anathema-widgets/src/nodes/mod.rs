@@ -4,12 +4,13 @@ pub use self::element::Element;
 use self::eval::{ComponentEval, ControlFlowEval, EvalContext, Evaluator, ForLoopEval, SingleEval};
 pub use self::future::try_resolve_future_values;
 pub use self::stringify::Stringify;
-pub use self::update::update_tree;
+pub(crate) use self::update::invalidate_layout;
+pub use self::update::{rebind_component_state, update_tree};
 use crate::error::Result;
 use crate::WidgetTree;
 
 mod component;
-mod controlflow;
+pub(crate) mod controlflow;
 pub(crate) mod element;
 pub(crate) mod eval;
 mod future;
@@ -1,15 +1,14 @@
-use anathema_state::{Change, States};
+use anathema_state::{AnyState, Change, States};
 use anathema_store::tree::PathFinder;
-use anathema_templates::Globals;
+use anathema_templates::{Globals, WidgetComponentId};
 
 use super::element::Element;
 use super::eval::EvalContext;
-use super::loops::LOOP_INDEX;
 use crate::components::ComponentRegistry;
 use crate::error::Result;
 use crate::values::ValueId;
 use crate::widget::{Components, FloatingWidgets};
-use crate::{AttributeStorage, Factory, Scope, WidgetKind, WidgetTree};
+use crate::{eval_blueprint, AttributeStorage, Factory, Scope, WidgetKind, WidgetTree};
 
 struct UpdateTree<'a, 'b, 'bp> {
     globals: &'bp Globals,
@@ -78,6 +77,119 @@ pub fn update_tree<'bp>(
         components,
     };
     tree.apply_path_finder(path, update);
+    invalidate_layout(path, tree);
+}
+
+/// Invalidate the cached layout of every element above the node at the given path.
+pub(crate) fn invalidate_layout(path: &[u16], tree: &mut WidgetTree<'_>) {
+    for len in 1..path.len() {
+        if let Some(WidgetKind::Element(el)) = tree.get_mut_by_path(&path[..len]) {
+            el.invalidate_layout();
+        }
+    }
+}
+
+struct RebindComponent<'a, 'b, 'bp> {
+    globals: &'bp Globals,
+    factory: &'a Factory,
+    scope: &'b mut Scope<'bp>,
+    states: &'b mut States,
+    component_registry: &'b mut ComponentRegistry,
+    attribute_storage: &'b mut AttributeStorage<'bp>,
+    floating_widgets: &'b mut FloatingWidgets,
+    components: &'b mut Components,
+}
+
+impl<'a, 'b, 'bp> PathFinder<WidgetKind<'bp>> for RebindComponent<'a, 'b, 'bp> {
+    type Output = Result<()>;
+
+    fn apply(&mut self, node: &mut WidgetKind<'bp>, path: &[u16], tree: &mut WidgetTree<'bp>) -> Self::Output {
+        let WidgetKind::Component(component) = node else { return Ok(()) };
+        let body = component.body;
+
+        // The body is evaluated from scratch against the new state,
+        // the same way a for-loop rebuilds its iterations once the collection is dropped.
+        tree.remove_children(path);
+        scope_value(node, self.scope, &[]);
+
+        let mut ctx = EvalContext::new(
+            self.globals,
+            self.factory,
+            self.scope,
+            self.states,
+            self.component_registry,
+            self.attribute_storage,
+            self.floating_widgets,
+            self.components,
+        );
+
+        for bp in body {
+            eval_blueprint(bp, &mut ctx, path, tree)?;
+        }
+
+        Ok(())
+    }
+
+    fn parent(&mut self, parent: &WidgetKind<'bp>, children: &[u16]) {
+        scope_value(parent, self.scope, children);
+    }
+}
+
+/// Replace the state of every mounted instance of a component and rebuild the body
+/// of each instance.
+///
+/// The component is located by its component id, which is the id returned when
+/// the component was added to the document, and is stable across recompilation.
+/// `state` is called once per instance, as each instance owns its state.
+///
+/// Returns the previous states in tree order, or an empty list if no instance
+/// of the component is mounted.
+/// The runtime will perform a new layout of the subtrees on the next frame.
+pub fn rebind_component_state<'bp>(
+    globals: &'bp Globals,
+    factory: &Factory,
+    scope: &mut Scope<'bp>,
+    states: &mut States,
+    component_registry: &mut ComponentRegistry,
+    component_id: WidgetComponentId,
+    mut state: impl FnMut() -> Box<dyn AnyState>,
+    tree: &mut WidgetTree<'bp>,
+    attribute_storage: &mut AttributeStorage<'bp>,
+    floating_widgets: &mut FloatingWidgets,
+    components: &mut Components,
+) -> Vec<Box<dyn AnyState>> {
+    let instances = components
+        .iter()
+        .filter(|entry| entry.component_id == component_id)
+        .map(|entry| (entry.widget_id, entry.state_id))
+        .collect::<Vec<_>>();
+
+    let mut previous_states = Vec::with_capacity(instances.len());
+
+    for (widget_id, state_id) in instances {
+        let Some(path) = tree.try_path(widget_id) else { continue };
+        previous_states.push(states.replace(state_id, state()));
+
+        // The path finder scopes the values of every node along the path,
+        // so each instance gets a scope of its own.
+        scope.push();
+        let rebind = RebindComponent {
+            globals,
+            factory,
+            scope,
+            states,
+            component_registry,
+            attribute_storage,
+            floating_widgets,
+            components,
+        };
+        tree.apply_path_finder(&path, rebind);
+        scope.pop();
+
+        invalidate_layout(&path, tree);
+    }
+
+    previous_states
 }
 
 fn update_widget<'bp>(
@@ -99,6 +211,9 @@ fn update_widget<'bp>(
                 if let Some(value) = attributes.get_mut_with_index(value_id.index()) {
                     value.reload_val(value_id, ctx.globals, ctx.scope, ctx.states);
                 }
+
+                // The keys of a spread map might have changed
+                attributes.expand_spread();
             }
         }
         WidgetKind::For(for_loop) => for_loop.update(ctx, change, value_id, path, tree)?,
@@ -121,15 +236,19 @@ fn update_widget<'bp>(
 
 pub(super) fn scope_value<'bp>(widget: &WidgetKind<'bp>, scope: &mut Scope<'bp>, children: &[u16]) {
     match widget {
-        WidgetKind::For(for_loop) => match children {
-            [next, ..] => {
+        WidgetKind::For(for_loop) => {
+            // The children of an empty loop belong to the `else` body
+            if for_loop.showing_empty {
+                return;
+            }
+
+            if let [next, ..] = children {
                 let index = *next as usize;
                 for_loop.collection.scope(scope, for_loop.binding, index);
             }
-            [] => {}
-        },
+        }
         WidgetKind::Iteration(iter) => {
-            scope.scope_pending(LOOP_INDEX, iter.loop_index.to_pending());
+            iter.scope_index(scope);
         }
         WidgetKind::Component(component) => {
             if let Some(state) = &component.external_state {
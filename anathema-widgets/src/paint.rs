@@ -1,4 +1,6 @@
+use std::cell::Cell;
 use std::ops::{ControlFlow, Deref};
+use std::rc::Rc;
 
 use anathema_geometry::{LocalPos, Pos, Region, Size};
 use anathema_state::Hex;
@@ -21,6 +23,60 @@ pub trait CellAttributes {
     fn get_bool(&self, key: &str) -> bool;
 }
 
+const FOREGROUND: &str = "foreground";
+const BACKGROUND: &str = "background";
+
+// Blend the foreground towards the background.
+// Only hex colours can be blended, any other colour is left as is.
+struct Blended<'a> {
+    attribs: &'a dyn CellAttributes,
+    opacity: f32,
+}
+
+/// Blend a colour over another colour,
+/// where an `alpha` of one is only `over` and zero is only `under`.
+pub fn blend(over: Hex, under: Hex, alpha: f32) -> Hex {
+    let channel = |over: u8, under: u8| (under as f32 + (over as f32 - under as f32) * alpha).round() as u8;
+    Hex {
+        r: channel(over.r, under.r),
+        g: channel(over.g, under.g),
+        b: channel(over.b, under.b),
+    }
+}
+
+/// The colours of a cell on a surface.
+/// See [`WidgetRenderer::read_cell`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SurfaceCell {
+    pub foreground: Option<Hex>,
+    pub background: Option<Hex>,
+}
+
+impl CellAttributes for Blended<'_> {
+    fn with_str(&self, key: &str, f: &mut dyn FnMut(&str)) {
+        self.attribs.with_str(key, f)
+    }
+
+    fn get_i64(&self, key: &str) -> Option<i64> {
+        self.attribs.get_i64(key)
+    }
+
+    fn get_hex(&self, key: &str) -> Option<Hex> {
+        match key {
+            FOREGROUND => {
+                let fg = self.attribs.get_hex(FOREGROUND)?;
+                let bg = self.attribs.get_hex(BACKGROUND).unwrap_or(Hex::BLACK);
+                Some(blend(fg, bg, self.opacity))
+            }
+            _ => self.attribs.get_hex(key),
+        }
+    }
+
+    fn get_bool(&self, key: &str) -> bool {
+        self.attribs.get_bool(key)
+    }
+}
+
 pub struct PaintFilter<'frame, 'bp> {
     attributes: &'frame AttributeStorage<'bp>,
     ignore_floats: bool,
@@ -72,13 +128,62 @@ pub fn paint<'bp>(
     attribute_storage: &AttributeStorage<'bp>,
     text: &mut StringSession<'_>,
     ignore_floats: bool,
+) {
+    let ctx = PaintCtx::new(surface, None);
+    paint_with_ctx(ctx, element, children, values, attribute_storage, text, ignore_floats);
+}
+
+/// Same as [`paint`] but with a context created by the caller,
+/// e.g one that shows the paint order.
+pub fn paint_with_ctx<'bp>(
+    ctx: PaintCtx<'_, Unsized>,
+    element: &mut Element<'bp>,
+    children: &[Node],
+    values: &mut TreeValues<WidgetKind<'bp>>,
+    attribute_storage: &AttributeStorage<'bp>,
+    text: &mut StringSession<'_>,
+    ignore_floats: bool,
 ) {
     let filter = PaintFilter::new(ignore_floats, attribute_storage);
     let children = TreeForEach::new(children, values, &filter);
-    let ctx = PaintCtx::new(surface, None);
     element.paint(children, ctx, text, attribute_storage);
 }
 
+/// Counts the elements as they are painted.
+/// Used by the debug overlay that draws the paint order of each element
+/// into its top-left cell.
+///
+/// Clones share the same count, so the count carries over from
+/// the regular paint pass to the floating widgets.
+#[derive(Debug, Default, Clone)]
+pub struct PaintOrder(Rc<Cell<usize>>);
+
+impl PaintOrder {
+    /// Start counting from zero again, e.g at the start of a new frame.
+    pub fn reset(&self) {
+        self.0.set(0);
+    }
+
+    fn next(&self) -> usize {
+        let order = self.0.get();
+        self.0.set(order + 1);
+        order
+    }
+}
+
+/// Set if a glyph was rejected for being outside of the region
+/// it was painted in, or outside of the clipping region.
+/// See [`PaintCtx::track_overflow`].
+#[derive(Debug, Default, Clone)]
+pub struct PaintOverflow(Rc<Cell<bool>>);
+
+impl PaintOverflow {
+    /// Returns `true` if any glyph was rejected.
+    pub fn overflowed(&self) -> bool {
+        self.0.get()
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Unsized;
 
@@ -104,6 +209,9 @@ impl SizePos {
 pub struct PaintCtx<'surface, Size> {
     surface: &'surface mut dyn WidgetRenderer,
     pub clip: Option<Region>,
+    opacity: f32,
+    paint_order: Option<PaintOrder>,
+    overflow: Option<PaintOverflow>,
     pub(crate) state: Size,
 }
 
@@ -120,6 +228,9 @@ impl<'surface> PaintCtx<'surface, Unsized> {
         Self {
             surface,
             clip,
+            opacity: 1.0,
+            paint_order: None,
+            overflow: None,
             state: Unsized,
         }
     }
@@ -129,14 +240,64 @@ impl<'surface> PaintCtx<'surface, Unsized> {
         PaintCtx {
             surface: self.surface,
             clip: self.clip,
+            opacity: self.opacity,
+            paint_order: self.paint_order,
+            overflow: self.overflow,
             state: SizePos::new(size, global_pos),
         }
     }
 }
 
+impl<'surface, S> PaintCtx<'surface, S> {
+    /// The opacity, between zero and one, applied to the foreground of every cell.
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Multiply the current opacity by `opacity`.
+    /// Any context created from this one inherits the opacity.
+    pub fn apply_opacity(&mut self, opacity: f32) {
+        self.opacity *= opacity.clamp(0.0, 1.0);
+    }
+
+    /// Draw the paint order of every element into its top-left cell,
+    /// on top of the content of the element.
+    /// If elements share an origin the outermost element is drawn last.
+    /// Any context created from this one inherits the setting.
+    pub fn show_paint_order(&mut self, paint_order: PaintOrder) {
+        self.paint_order = Some(paint_order);
+    }
+
+    pub(crate) fn next_paint_order(&self) -> Option<usize> {
+        self.paint_order.as_ref().map(PaintOrder::next)
+    }
+
+    /// Track if any glyph painted with this context, or any context created
+    /// from it, is rejected for being outside of its region.
+    /// This replaces any overflow tracked by a parent.
+    pub fn track_overflow(&mut self) -> PaintOverflow {
+        let overflow = PaintOverflow::default();
+        self.overflow = Some(overflow.clone());
+        overflow
+    }
+
+    fn mark_overflow(&self) {
+        if let Some(overflow) = self.overflow.as_ref() {
+            overflow.0.set(true);
+        }
+    }
+}
+
 impl<'screen> PaintCtx<'screen, SizePos> {
     pub fn to_unsized(&mut self) -> PaintCtx<'_, Unsized> {
-        PaintCtx::new(self.surface, self.clip)
+        PaintCtx {
+            surface: self.surface,
+            clip: self.clip,
+            opacity: self.opacity,
+            paint_order: self.paint_order.clone(),
+            overflow: self.overflow.clone(),
+            state: Unsized,
+        }
     }
 
     pub fn update(&mut self, new_size: Size, new_pos: Pos) {
@@ -175,6 +336,18 @@ impl<'screen> PaintCtx<'screen, SizePos> {
         (pos.x as usize) + width <= self.local_size.width && (pos.y as usize) < self.local_size.height
     }
 
+    /// Returns `true` if a glyph placed at the local position would end up on the
+    /// surface, meaning it's inside the local region, the clipping region and the surface.
+    pub fn is_visible(&self, pos: LocalPos) -> bool {
+        if let Some(clip) = self.clip.as_ref() {
+            if !self.clip(pos, clip) {
+                return false;
+            }
+        }
+
+        self.pos_inside_local_region(pos, 1) && self.translate_to_global(pos).is_some()
+    }
+
     // Translate local coordinates to screen coordinates.
     // Will return `None` if the coordinates are outside the screen bounds
     pub fn translate_to_global(&self, local: LocalPos) -> Option<Pos> {
@@ -201,6 +374,14 @@ impl<'screen> PaintCtx<'screen, SizePos> {
         }
     }
 
+    /// Read a cell previously painted to the surface.
+    /// Returns `None` if the position is outside of the surface,
+    /// or the surface can't be read.
+    pub fn read_cell(&self, pos: LocalPos) -> Option<SurfaceCell> {
+        let pos = self.translate_to_global(pos)?;
+        self.surface.read_cell(pos)
+    }
+
     pub fn place_glyphs(&mut self, s: &str, mut pos: LocalPos) -> Option<LocalPos> {
         for c in s.chars() {
             let p = self.place_glyph(c, pos)?;
@@ -222,7 +403,16 @@ impl<'screen> PaintCtx<'screen, SizePos> {
             None => return,
         };
 
-        self.surface.set_attributes(attrs, screen_pos);
+        match self.opacity < 1.0 {
+            true => {
+                let attrs = Blended {
+                    attribs: attrs,
+                    opacity: self.opacity,
+                };
+                self.surface.set_attributes(&attrs, screen_pos);
+            }
+            false => self.surface.set_attributes(attrs, screen_pos),
+        }
     }
 
     // Place a char on the screen buffer, return the next cursor position in local space.
@@ -232,6 +422,15 @@ impl<'screen> PaintCtx<'screen, SizePos> {
     //
     // The `output_pos` is the same as the `input_pos` unless clipping has been applied.
     pub fn place_glyph(&mut self, c: char, input_pos: LocalPos) -> Option<LocalPos> {
+        self.place_cluster(c.encode_utf8(&mut [0; 4]), input_pos)
+    }
+
+    /// Place a cluster of characters in a single cell, e.g a character followed
+    /// by combining marks, return the next cursor position in local space.
+    /// The width of the cluster is the width of the first character.
+    /// See [`WidgetRenderer::draw_cluster`].
+    pub fn place_cluster(&mut self, cluster: &str, input_pos: LocalPos) -> Option<LocalPos> {
+        let c = cluster.chars().next()?;
         let width = c.width().unwrap_or(0);
         let next = LocalPos {
             x: input_pos.x + width as u16,
@@ -241,6 +440,7 @@ impl<'screen> PaintCtx<'screen, SizePos> {
         // Ensure that the position is inside provided clipping region
         if let Some(clip) = self.clip.as_ref() {
             if !self.clip(input_pos, clip) {
+                self.mark_overflow();
                 return Some(next);
             }
         }
@@ -252,6 +452,7 @@ impl<'screen> PaintCtx<'screen, SizePos> {
 
         // 2. Check if the char can be placed
         if !self.pos_inside_local_region(input_pos, width) {
+            self.mark_overflow();
             return None;
         }
 
@@ -260,16 +461,176 @@ impl<'screen> PaintCtx<'screen, SizePos> {
             Some(pos) => pos,
             None => return Some(next),
         };
-        self.surface.draw_glyph(c, screen_pos);
 
-        // 4. Advance the cursor (which might trigger another newline)
-        if input_pos.x >= self.local_size.width as u16 {
+        // A wide glyph covers the next cell as well, so clear
+        // whatever was there before drawing the glyph
+        if width == 2 {
+            let trailing = LocalPos::new(input_pos.x + 1, input_pos.y);
+            let clipped = self.clip.as_ref().is_some_and(|clip| !self.clip(trailing, clip));
+            if let (false, Some(pos)) = (clipped, self.translate_to_global(trailing)) {
+                self.surface.draw_glyph(' ', pos);
+            }
+        }
+
+        self.surface.draw_cluster(cluster, screen_pos);
+
+        // 4. Advance the cursor, moving to the next line
+        //    once the end of the line is reached
+        if next.x as usize >= self.local_size.width {
             self.newline(input_pos)
         } else {
-            Some(LocalPos {
-                x: input_pos.x + width as u16,
-                y: input_pos.y,
-            })
+            Some(next)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Colors(Hex, Hex);
+
+    impl CellAttributes for Colors {
+        fn with_str(&self, _: &str, _: &mut dyn FnMut(&str)) {}
+
+        fn get_i64(&self, _: &str) -> Option<i64> {
+            None
+        }
+
+        fn get_hex(&self, key: &str) -> Option<Hex> {
+            match key {
+                FOREGROUND => Some(self.0),
+                BACKGROUND => Some(self.1),
+                _ => None,
+            }
+        }
+
+        fn get_bool(&self, _: &str) -> bool {
+            false
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        foreground: Option<Hex>,
+    }
+
+    impl WidgetRenderer for Recorder {
+        fn draw_glyph(&mut self, _: char, _: Pos) {}
+
+        fn set_attributes(&mut self, attribs: &dyn CellAttributes, _: Pos) {
+            self.foreground = attribs.get_hex(FOREGROUND);
+        }
+
+        fn size(&self) -> Size {
+            Size::new(1, 1)
+        }
+    }
+
+    #[test]
+    fn blend_foreground_by_opacity() {
+        let mut recorder = Recorder::default();
+        let mut ctx = PaintCtx::new(&mut recorder, None);
+        ctx.apply_opacity(0.5);
+        let mut ctx = ctx.into_sized(Size::new(1, 1), Pos::ZERO);
+
+        let attribs = Colors(Hex::from((200, 100, 0)), Hex::from((0, 0, 100)));
+        ctx.set_attributes(&attribs, LocalPos::ZERO);
+
+        assert_eq!(recorder.foreground, Some(Hex::from((100, 50, 50))));
+    }
+
+    #[test]
+    fn inherit_opacity() {
+        let mut recorder = Recorder::default();
+        let mut ctx = PaintCtx::new(&mut recorder, None);
+        ctx.apply_opacity(0.5);
+        let mut ctx = ctx.into_sized(Size::new(1, 1), Pos::ZERO);
+        let mut child = ctx.to_unsized();
+        child.apply_opacity(0.5);
+
+        assert_eq!(child.opacity(), 0.25);
+    }
+
+    struct Grid {
+        width: usize,
+        cells: Vec<char>,
+    }
+
+    impl Grid {
+        fn new(size: Size) -> Self {
+            Self {
+                width: size.width,
+                cells: vec![' '; size.width * size.height],
+            }
+        }
+
+        fn rows(&self) -> Vec<String> {
+            self.cells.chunks(self.width).map(|row| row.iter().collect()).collect()
+        }
+    }
+
+    impl WidgetRenderer for Grid {
+        fn draw_glyph(&mut self, c: char, pos: Pos) {
+            self.cells[pos.y as usize * self.width + pos.x as usize] = c;
+        }
+
+        fn set_attributes(&mut self, _: &dyn CellAttributes, _: Pos) {}
+
+        fn size(&self) -> Size {
+            Size::new(self.width, self.cells.len() / self.width)
         }
     }
+
+    #[test]
+    fn wide_glyph_clears_trailing_cell() {
+        let mut grid = Grid::new(Size::new(2, 2));
+        let ctx = PaintCtx::new(&mut grid, None);
+        let mut ctx = ctx.into_sized(Size::new(2, 2), Pos::ZERO);
+
+        ctx.place_glyphs("xx", LocalPos::ZERO);
+        let next = ctx.place_glyph('あ', LocalPos::ZERO);
+
+        assert_eq!(next, Some(LocalPos::new(0, 1)));
+        assert_eq!(grid.rows(), ["あ ", "  "]);
+    }
+
+    #[test]
+    fn wrap_at_the_end_of_the_line() {
+        let mut grid = Grid::new(Size::new(3, 2));
+        let ctx = PaintCtx::new(&mut grid, None);
+        let mut ctx = ctx.into_sized(Size::new(3, 2), Pos::ZERO);
+
+        let next = ctx.place_glyphs("abcde", LocalPos::ZERO);
+
+        assert_eq!(next, Some(LocalPos::new(2, 1)));
+        assert_eq!(grid.rows(), ["abc", "de "]);
+    }
+
+    #[test]
+    fn wrap_after_wide_glyph() {
+        let mut grid = Grid::new(Size::new(4, 2));
+        let ctx = PaintCtx::new(&mut grid, None);
+        let mut ctx = ctx.into_sized(Size::new(4, 2), Pos::ZERO);
+
+        // The wide glyph fills the last two columns
+        let next = ctx.place_glyph('あ', LocalPos::new(2, 0));
+        assert_eq!(next, Some(LocalPos::new(0, 1)));
+
+        let next = ctx.place_glyph('a', next.unwrap());
+        assert_eq!(next, Some(LocalPos::new(1, 1)));
+        assert_eq!(grid.rows(), ["  あ ", "a   "]);
+    }
+
+    #[test]
+    fn no_wrap_past_the_last_line() {
+        let mut grid = Grid::new(Size::new(3, 1));
+        let ctx = PaintCtx::new(&mut grid, None);
+        let mut ctx = ctx.into_sized(Size::new(3, 1), Pos::ZERO);
+
+        let next = ctx.place_glyphs("abcde", LocalPos::ZERO);
+
+        assert_eq!(next, None);
+        assert_eq!(grid.rows(), ["abc"]);
+    }
 }
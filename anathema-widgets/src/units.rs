@@ -0,0 +1,174 @@
+//! Parsing of attribute values with units, e.g `"200ms"` or `"3ch"`.
+use std::fmt::Display;
+use std::time::Duration;
+
+use anathema_geometry::Size;
+
+#[derive(Debug, PartialEq)]
+pub enum UnitError {
+    /// The value did not start with a number
+    InvalidNumber(String),
+    /// The unit is not supported
+    UnknownUnit(String),
+}
+
+impl Display for UnitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnitError::InvalidNumber(val) => write!(f, "`{val}` is not a valid number"),
+            UnitError::UnknownUnit(unit) => write!(f, "unknown unit `{unit}`"),
+        }
+    }
+}
+
+impl std::error::Error for UnitError {}
+
+/// Split a value into the number and the unit.
+/// `"200ms"` becomes `(200.0, "ms")`.
+fn split_unit(value: &str) -> Result<(f64, &str), UnitError> {
+    let value = value.trim();
+    let index = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .unwrap_or(value.len());
+
+    let (number, unit) = value.split_at(index);
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| UnitError::InvalidNumber(value.into()))?;
+
+    Ok((number, unit.trim()))
+}
+
+/// Parse a duration.
+/// Supported units are `ms` and `s`.
+/// ```
+/// # use std::time::Duration;
+/// # use anathema_widgets::units::parse_duration;
+/// assert_eq!(parse_duration("200ms").unwrap(), Duration::from_millis(200));
+/// ```
+pub fn parse_duration(value: &str) -> Result<Duration, UnitError> {
+    let (number, unit) = split_unit(value)?;
+    if number < 0.0 {
+        return Err(UnitError::InvalidNumber(value.into()));
+    }
+
+    match unit {
+        "ms" => Ok(Duration::from_secs_f64(number / 1000.0)),
+        "s" => Ok(Duration::from_secs_f64(number)),
+        unit => Err(UnitError::UnknownUnit(unit.into())),
+    }
+}
+
+/// Parse a size in cells.
+/// Supported units are `ch` and `cells`, and a number without a unit.
+/// ```
+/// # use anathema_widgets::units::parse_cells;
+/// assert_eq!(parse_cells("3ch").unwrap(), 3);
+/// ```
+pub fn parse_cells(value: &str) -> Result<usize, UnitError> {
+    let (number, unit) = split_unit(value)?;
+    if number < 0.0 {
+        return Err(UnitError::InvalidNumber(value.into()));
+    }
+
+    match unit {
+        "" | "ch" | "cells" => Ok(number as usize),
+        unit => Err(UnitError::UnknownUnit(unit.into())),
+    }
+}
+
+/// Parse a size in cells, where the value can be relative to the viewport.
+/// In addition to the units supported by [`parse_cells`], this supports
+/// `vw` and `vh`: a percentage of the viewport width and height.
+/// ```
+/// # use anathema_geometry::Size;
+/// # use anathema_widgets::units::parse_viewport_cells;
+/// let viewport = Size::new(20, 10);
+/// assert_eq!(parse_viewport_cells("50vw", viewport).unwrap(), 10);
+/// assert_eq!(parse_viewport_cells("50vh", viewport).unwrap(), 5);
+/// ```
+pub fn parse_viewport_cells(value: &str, viewport: Size) -> Result<usize, UnitError> {
+    let (number, unit) = split_unit(value)?;
+    if number < 0.0 {
+        return Err(UnitError::InvalidNumber(value.into()));
+    }
+
+    match unit {
+        "vw" => Ok((number / 100.0 * viewport.width as f64) as usize),
+        "vh" => Ok((number / 100.0 * viewport.height as f64) as usize),
+        _ => parse_cells(value),
+    }
+}
+
+/// Parse a size in cells, where the value can be a percentage of `total`.
+/// In addition to the units supported by [`parse_cells`], this supports `%`.
+/// ```
+/// # use anathema_widgets::units::parse_relative_cells;
+/// assert_eq!(parse_relative_cells("25%", 8).unwrap(), 2);
+/// assert_eq!(parse_relative_cells("3ch", 8).unwrap(), 3);
+/// ```
+pub fn parse_relative_cells(value: &str, total: usize) -> Result<usize, UnitError> {
+    let (number, unit) = split_unit(value)?;
+    if number < 0.0 {
+        return Err(UnitError::InvalidNumber(value.into()));
+    }
+
+    match unit {
+        "%" => Ok((number / 100.0 * total as f64) as usize),
+        _ => parse_cells(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn duration() {
+        assert_eq!(parse_duration("200ms").unwrap(), Duration::from_millis(200));
+        assert_eq!(parse_duration("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn cells() {
+        assert_eq!(parse_cells("3ch").unwrap(), 3);
+        assert_eq!(parse_cells("4cells").unwrap(), 4);
+        assert_eq!(parse_cells("5").unwrap(), 5);
+    }
+
+    #[test]
+    fn viewport_cells() {
+        let viewport = Size::new(20, 10);
+        assert_eq!(parse_viewport_cells("50vw", viewport).unwrap(), 10);
+        assert_eq!(parse_viewport_cells("100vh", viewport).unwrap(), 10);
+        assert_eq!(parse_viewport_cells("3ch", viewport).unwrap(), 3);
+        assert_eq!(
+            parse_viewport_cells("3%", viewport).unwrap_err(),
+            UnitError::UnknownUnit("%".into())
+        );
+    }
+
+    #[test]
+    fn relative_cells() {
+        assert_eq!(parse_relative_cells("50%", 8).unwrap(), 4);
+        assert_eq!(parse_relative_cells("100%", 8).unwrap(), 8);
+        assert_eq!(parse_relative_cells("2", 8).unwrap(), 2);
+        assert!(matches!(
+            parse_relative_cells("-50%", 8),
+            Err(UnitError::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_unit() {
+        assert_eq!(parse_duration("2h").unwrap_err(), UnitError::UnknownUnit("h".into()));
+        assert_eq!(parse_cells("3px").unwrap_err(), UnitError::UnknownUnit("px".into()));
+    }
+
+    #[test]
+    fn invalid_number() {
+        assert!(matches!(parse_cells("ch"), Err(UnitError::InvalidNumber(_))));
+        assert!(matches!(parse_duration("-1s"), Err(UnitError::InvalidNumber(_))));
+    }
+}
@@ -1,14 +1,23 @@
 use std::ops::Deref;
+use std::time::Duration;
 
-use anathema_state::{CommonVal, PendingValue};
+use anathema_geometry::{Pos, Size};
+use anathema_state::{CommonVal, PendingValue, States};
 use anathema_store::slab::{Gen, SecondaryMap};
 use anathema_store::smallmap::SmallIndex;
+use anathema_templates::expressions::Op;
+use anathema_templates::{Expression, Globals, Primitive};
 
-use crate::expressions::EvalValue;
+use crate::expressions::{eval, EvalValue};
 use crate::paint::CellAttributes;
+use crate::units::{parse_cells, parse_duration, parse_viewport_cells, UnitError};
 use crate::values::Values;
 use crate::widget::ValueKey;
-use crate::{Value, WidgetId};
+use crate::{Scope, Value, WidgetId};
+
+const CLASS: &str = "class";
+const WIDTH: &str = "width";
+const HEIGHT: &str = "height";
 
 #[derive(Debug)]
 pub struct AttributeStorage<'bp>(SecondaryMap<WidgetId, (Gen, Attributes<'bp>)>);
@@ -22,11 +31,16 @@ impl<'bp> AttributeStorage<'bp> {
         self.0.get(id).map(|(_, a)| a).expect("every element has attributes")
     }
 
+    /// Get the attributes for modification.
+    /// This changes the [`version`](Attributes::version) of the attributes.
     pub fn get_mut(&mut self, id: WidgetId) -> &mut Attributes<'bp> {
-        self.0
+        let attributes = self
+            .0
             .get_mut(id)
             .map(|(_, a)| a)
-            .expect("every element has attributes")
+            .expect("every element has attributes");
+        attributes.version = attributes.version.wrapping_add(1);
+        attributes
     }
 
     pub fn insert(&mut self, widget_id: WidgetId, attribs: Attributes<'bp>) {
@@ -38,11 +52,38 @@ impl<'bp> AttributeStorage<'bp> {
     }
 }
 
+fn eval_sized(expr: &Expression, size: Size) -> Option<i64> {
+    let value = match expr {
+        Expression::Primitive(Primitive::Int(int)) => *int,
+        Expression::Ident(ident) => match &**ident {
+            WIDTH => i64::try_from(size.width).unwrap_or(i64::MAX),
+            HEIGHT => i64::try_from(size.height).unwrap_or(i64::MAX),
+            _ => return None,
+        },
+        Expression::Negative(expr) => eval_sized(expr, size)?.saturating_neg(),
+        Expression::Op(lhs, rhs, op) => {
+            let lhs = eval_sized(lhs, size)?;
+            let rhs = eval_sized(rhs, size)?;
+            match op {
+                Op::Add => lhs.saturating_add(rhs),
+                Op::Sub => lhs.saturating_sub(rhs),
+                Op::Mul => lhs.saturating_mul(rhs),
+                Op::Div => lhs.checked_div(rhs)?,
+                Op::Mod => lhs.checked_rem(rhs)?,
+            }
+        }
+        _ => return None,
+    };
+
+    Some(value)
+}
+
 #[derive(Debug)]
 pub struct Attributes<'bp> {
     pub(crate) values: Values<'bp>,
     pub(crate) value: Option<SmallIndex>,
     widget_id: WidgetId,
+    version: usize,
 }
 
 impl<'bp> Attributes<'bp> {
@@ -52,9 +93,16 @@ impl<'bp> Attributes<'bp> {
             values: Values::empty(),
             value: None,
             widget_id,
+            version: 0,
         }
     }
 
+    /// The version changes every time the attributes are accessed mutably
+    /// through the [`AttributeStorage`].
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
     /// Set the value
     pub fn set(&mut self, key: &'bp str, value: impl Into<CommonVal<'bp>>) {
         let value = value.into().into();
@@ -124,8 +172,13 @@ impl<'bp> Attributes<'bp> {
     }
 
     pub fn get_val(&self, key: &'bp str) -> Option<&Value<'bp, EvalValue<'bp>>> {
-        let key = ValueKey::Attribute(key);
-        self.values.get(&key)
+        match self.values.get(&ValueKey::Attribute(key)) {
+            Some(value) => Some(value),
+            None => self.values.iter().find_map(|(k, value)| match k {
+                ValueKey::SpreadAttribute(name) if &**name == key => Some(value),
+                _ => None,
+            }),
+        }
     }
 
     /// Get an integer regardless of how the value was stored.
@@ -140,6 +193,154 @@ impl<'bp> Attributes<'bp> {
             .and_then(|e| e.load_number().map(|n| n.as_int()))
     }
 
+    /// Get a size from two integers, e.g `width` and `height`.
+    ///
+    /// Returns `None` unless both values are present and non-negative.
+    /// ```
+    /// # use anathema_geometry::Size;
+    /// # use anathema_widgets::{Attributes, WidgetId};
+    /// let mut attributes = Attributes::empty(WidgetId::ZERO);
+    /// attributes.set("width", 4);
+    /// attributes.set("height", 2);
+    /// assert_eq!(attributes.get_size("width", "height"), Some(Size::new(4, 2)));
+    /// ```
+    pub fn get_size(&self, width_key: &'bp str, height_key: &'bp str) -> Option<Size> {
+        let width = usize::try_from(self.get_int(width_key)?).ok()?;
+        let height = usize::try_from(self.get_int(height_key)?).ok()?;
+        Some(Size::new(width, height))
+    }
+
+    /// Get a position from two integers, e.g `x` and `y`.
+    ///
+    /// Returns `None` unless both values are present.
+    /// ```
+    /// # use anathema_geometry::Pos;
+    /// # use anathema_widgets::{Attributes, WidgetId};
+    /// let mut attributes = Attributes::empty(WidgetId::ZERO);
+    /// attributes.set("x", 4);
+    /// attributes.set("y", -2);
+    /// assert_eq!(attributes.get_pos("x", "y"), Some(Pos::new(4, -2)));
+    /// ```
+    pub fn get_pos(&self, x_key: &'bp str, y_key: &'bp str) -> Option<Pos> {
+        let x = i32::try_from(self.get_int(x_key)?).ok()?;
+        let y = i32::try_from(self.get_int(y_key)?).ok()?;
+        Some(Pos::new(x, y))
+    }
+
+    /// Get an integer that can reference the size of the widget.
+    ///
+    /// This is intended to be called after layout, once the size is known.
+    /// If the value could not be resolved during evaluation, the expression is
+    /// resolved again where `width` and `height` are the given `size`.
+    ///
+    /// Only integers, `width`, `height` and arithmetic operations are supported
+    /// in the expression, e.g `left: width / 2`.
+    pub fn get_int_sized(&self, key: &'bp str, size: Size) -> Option<i64> {
+        if let Some(int) = self.get_int(key) {
+            return Some(int);
+        }
+
+        let expr = self.get_val(key)?.expr?;
+        eval_sized(expr, size)
+    }
+
+    /// Get a duration, e.g `"200ms"` or `"2s"`.
+    /// A number without a unit is treated as milliseconds.
+    ///
+    /// Returns `Ok(None)` if the value is missing and an error if the unit is unknown.
+    pub fn get_duration(&self, key: &'bp str) -> Result<Option<Duration>, UnitError> {
+        match self.get_int(key) {
+            Some(ms) => Ok(Some(Duration::from_millis(ms.max(0) as u64))),
+            None => self.try_unit(key, parse_duration).transpose(),
+        }
+    }
+
+    /// Get a size in cells, e.g `"3ch"`, `"3cells"` or `3`.
+    ///
+    /// Returns `Ok(None)` if the value is missing and an error if the unit is unknown.
+    pub fn get_cells(&self, key: &'bp str) -> Result<Option<usize>, UnitError> {
+        match self.get_int(key) {
+            Some(cells) => Ok(Some(cells.max(0) as usize)),
+            None => self.try_unit(key, parse_cells).transpose(),
+        }
+    }
+
+    /// Get a size in cells that can be relative to the viewport,
+    /// e.g `"50vw"`, `"25vh"`, `"3ch"` or `3`.
+    ///
+    /// Returns `Ok(None)` if the value is missing and an error if the value
+    /// is negative or the unit is unknown.
+    pub fn get_viewport_cells(&self, key: &'bp str, viewport: Size) -> Result<Option<usize>, UnitError> {
+        match self.get_int(key) {
+            Some(cells) => usize::try_from(cells)
+                .map(Some)
+                .map_err(|_| UnitError::InvalidNumber(cells.to_string())),
+            None => self
+                .try_unit(key, |value| parse_viewport_cells(value, viewport))
+                .transpose(),
+        }
+    }
+
+    /// Parse a string value with a unit.
+    /// Returns `None` if the value is missing or isn't a string.
+    pub fn try_unit<T, F>(&self, key: &'bp str, f: F) -> Option<Result<T, UnitError>>
+    where
+        F: FnOnce(&str) -> Result<T, UnitError>,
+    {
+        let value = self.get_val(key)?.load_common_val()?;
+        match value.to_common()? {
+            CommonVal::Str(s) => Some(f(s)),
+            _ => None,
+        }
+    }
+
+    /// Re-evaluate every value that was created from an expression.
+    pub(crate) fn reload_all(&mut self, globals: &'bp Globals, scope: &Scope<'bp>, states: &States) {
+        let widget_id = self.widget_id;
+        for (index, _, value) in self.values.iter_mut_with_index() {
+            let Some(expr) = value.expr else { continue };
+            *value = eval(expr, globals, scope, states, (widget_id, index));
+        }
+        self.expand_spread();
+    }
+
+    /// Expand the keys of every map spread onto the element into attributes,
+    /// replacing the attributes of the previous expansion.
+    ///
+    /// The attributes set in the template are never overridden by a spread map,
+    /// and the keys of a later map override the keys of an earlier one.
+    pub(crate) fn expand_spread(&mut self) {
+        if !self.values.iter().any(|(key, _)| *key == ValueKey::Spread) {
+            return;
+        }
+
+        self.values
+            .retain(|key, _| !matches!(key, ValueKey::SpreadAttribute(_)));
+
+        let mut entries = vec![];
+        for (_, map) in self.values.iter().filter(|(key, _)| **key == ValueKey::Spread) {
+            map.map_entries(&mut |key, value| {
+                entries.retain(|(k, _)| *k != key);
+                entries.push((key, value));
+            });
+        }
+
+        let widget_id = self.widget_id;
+        for (key, value) in entries {
+            let is_set = self.values.iter().any(|(k, _)| match k {
+                ValueKey::Attribute(name) => **name == *key,
+                _ => false,
+            });
+            if is_set {
+                continue;
+            }
+
+            self.values.insert_with(ValueKey::SpreadAttribute(key), |index| {
+                Value::new(value.upgrade((widget_id, index).into()), None)
+            });
+        }
+    }
+
     pub(crate) fn get_mut_with_index(&mut self, index: SmallIndex) -> Option<&mut Value<'bp, EvalValue<'bp>>> {
         self.values.get_mut_with_index(index)
     }
@@ -154,15 +355,24 @@ impl<'bp> Attributes<'bp> {
     /// This will skip the value
     pub fn iter(&self) -> impl Iterator<Item = (&ValueKey<'_>, &Value<'_, EvalValue<'_>>)> {
         self.values.iter().filter(|(key, _)| match key {
-            ValueKey::Value => false,
-            ValueKey::Attribute(_) => true,
+            ValueKey::Value | ValueKey::Spread => false,
+            ValueKey::Attribute(_) | ValueKey::SpreadAttribute(_) => true,
         })
     }
 
+    /// Returns true if the `class` attribute contains the given class.
+    /// The `class` attribute is either a space separated string
+    /// or a list of strings, e.g `class: "a b"` or `class: ["a", "b"]`.
+    pub fn has_class(&self, class: &str) -> bool {
+        let Some(value) = self.get_val(CLASS) else { return false };
+        let mut found = false;
+        value.str_for_each(|s| found |= s.split_whitespace().any(|c| c == class));
+        found
+    }
+
     /// Returns true if the attributes contains the key
     pub fn contains(&self, key: &'bp str) -> bool {
-        let key = ValueKey::Attribute(key);
-        self.values.get(&key).is_some()
+        self.get_val(key).is_some()
     }
 }
 
@@ -196,6 +406,44 @@ impl CellAttributes for Attributes<'_> {
 mod test {
     use super::*;
 
+    #[test]
+    fn sized_int() {
+        let expr = Expression::Op(
+            Box::new(Expression::Ident(WIDTH.into())),
+            Box::new(Expression::Primitive(Primitive::Int(2))),
+            Op::Div,
+        );
+        let mut attributes = Attributes::empty(WidgetId::ZERO);
+        attributes.insert_with(ValueKey::Attribute("left"), |_| {
+            Value::new(EvalValue::Empty, Some(&expr))
+        });
+        attributes.set("top", 3u32);
+
+        let size = Size::new(10, 4);
+        assert_eq!(5, attributes.get_int_sized("left", size).unwrap());
+        assert_eq!(3, attributes.get_int_sized("top", size).unwrap());
+        assert!(attributes.get_int_sized("right", size).is_none());
+    }
+
+    #[test]
+    fn sized_int_saturates() {
+        let int = |i| Box::new(Expression::Primitive(Primitive::Int(i)));
+        let width = || Box::new(Expression::Ident(WIDTH.into()));
+        let size = Size::new(usize::MAX, 1);
+
+        let expr = Expression::Op(width(), int(i64::MAX), Op::Mul);
+        assert_eq!(eval_sized(&expr, size), Some(i64::MAX));
+
+        let expr = Expression::Op(int(i64::MIN), width(), Op::Sub);
+        assert_eq!(eval_sized(&expr, size), Some(i64::MIN));
+
+        let expr = Expression::Negative(int(i64::MIN));
+        assert_eq!(eval_sized(&expr, size), Some(i64::MAX));
+
+        let expr = Expression::Op(int(i64::MIN), int(-1), Op::Div);
+        assert_eq!(eval_sized(&expr, size), None);
+    }
+
     #[test]
     fn read_attribute() {
         let mut attributes = Attributes::empty(WidgetId::ZERO);
@@ -223,6 +471,68 @@ mod test {
         assert!(attributes.get::<u32>("num").is_none());
     }
 
+    #[test]
+    fn size_attribute() {
+        let mut attributes = Attributes::empty(WidgetId::ZERO);
+        attributes.set("width", 3);
+        assert!(attributes.get_size("width", "height").is_none());
+
+        attributes.set("height", 2u32);
+        assert_eq!(attributes.get_size("width", "height"), Some(Size::new(3, 2)));
+
+        attributes.set("height", -2);
+        assert!(attributes.get_size("width", "height").is_none());
+    }
+
+    #[test]
+    fn pos_attribute() {
+        let mut attributes = Attributes::empty(WidgetId::ZERO);
+        attributes.set("x", 3);
+        assert!(attributes.get_pos("x", "y").is_none());
+
+        attributes.set("y", -1);
+        assert_eq!(attributes.get_pos("x", "y"), Some(Pos::new(3, -1)));
+    }
+
+    #[test]
+    fn duration_attribute() {
+        let mut attributes = Attributes::empty(WidgetId::ZERO);
+        attributes.set("duration", "200ms");
+        attributes.set("unknown", "200h");
+        assert_eq!(
+            Duration::from_millis(200),
+            attributes.get_duration("duration").unwrap().unwrap()
+        );
+        assert_eq!(
+            attributes.get_duration("unknown").unwrap_err(),
+            UnitError::UnknownUnit("h".into())
+        );
+        assert!(attributes.get_duration("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn cells_attribute() {
+        let mut attributes = Attributes::empty(WidgetId::ZERO);
+        attributes.set("width", "3ch");
+        attributes.set("height", 2u32);
+        attributes.set("depth", "3px");
+        assert_eq!(3, attributes.get_cells("width").unwrap().unwrap());
+        assert_eq!(2, attributes.get_cells("height").unwrap().unwrap());
+        assert_eq!(
+            attributes.get_cells("depth").unwrap_err(),
+            UnitError::UnknownUnit("px".into())
+        );
+    }
+
+    #[test]
+    fn class_attribute() {
+        let mut attributes = Attributes::empty(WidgetId::ZERO);
+        attributes.set("class", "header  bold");
+        assert!(attributes.has_class("header"));
+        assert!(attributes.has_class("bold"));
+        assert!(!attributes.has_class("head"));
+    }
+
     #[test]
     fn contains_attribute() {
         let mut attributes = Attributes::empty(WidgetId::ZERO);
@@ -2,6 +2,7 @@ use std::any::Any;
 use std::cmp::Ordering;
 use std::fmt::{self, Debug};
 use std::ops::ControlFlow;
+use std::rc::Rc;
 
 pub type WidgetId = anathema_store::slab::Key;
 
@@ -15,15 +16,17 @@ use anathema_templates::WidgetComponentId;
 
 pub use self::attributes::{AttributeStorage, Attributes};
 pub use self::factory::Factory;
-pub use self::query::Elements;
+pub use self::query::{find_by_class, next_focus, Elements};
+pub use self::repaint::RepaintQueue;
 use crate::layout::text::StringSession;
 use crate::layout::{Constraints, LayoutCtx, LayoutFilter, PositionCtx};
-use crate::paint::{CellAttributes, PaintCtx, PaintFilter, SizePos};
+use crate::paint::{CellAttributes, PaintCtx, PaintFilter, SizePos, SurfaceCell};
 use crate::WidgetKind;
 
 mod attributes;
 mod factory;
 mod query;
+mod repaint;
 
 #[derive(Debug)]
 pub struct CompEntry {
@@ -35,7 +38,7 @@ pub struct CompEntry {
 
 impl PartialOrd for CompEntry {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.path.cmp(&other.path))
+        Some(self.cmp(other))
     }
 }
 
@@ -192,11 +195,15 @@ pub type LayoutChildren<'a, 'frame, 'bp> = TreeForEach<'a, 'frame, WidgetKind<'b
 pub type PositionChildren<'a, 'frame, 'bp> = TreeForEach<'a, 'frame, WidgetKind<'bp>, LayoutFilter<'frame, 'bp>>;
 pub type PaintChildren<'a, 'frame, 'bp> = TreeForEach<'a, 'frame, WidgetKind<'bp>, PaintFilter<'frame, 'bp>>;
 
-#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum ValueKey<'bp> {
     #[default]
     Value,
     Attribute(&'bp str),
+    /// A map spread onto the element at runtime, e.g `[...state.attributes]`
+    Spread,
+    /// An attribute expanded from the keys of a spread map
+    SpreadAttribute(Rc<str>),
 }
 
 impl ValueKey<'_> {
@@ -204,6 +211,8 @@ impl ValueKey<'_> {
         match self {
             ValueKey::Value => "value",
             ValueKey::Attribute(name) => name,
+            ValueKey::Spread => "...",
+            ValueKey::SpreadAttribute(name) => name,
         }
     }
 }
@@ -240,6 +249,16 @@ pub trait AnyWidget {
     );
 
     fn any_floats(&self) -> bool;
+
+    fn any_invalidate_layout(&mut self);
+
+    fn any_negotiate<'bp>(
+        &mut self,
+        children: LayoutChildren<'_, '_, 'bp>,
+        constraints: Constraints,
+        id: WidgetId,
+        ctx: &mut LayoutCtx<'_, '_, 'bp>,
+    ) -> Size;
 }
 
 impl<T: 'static + Widget> AnyWidget for T {
@@ -285,6 +304,20 @@ impl<T: 'static + Widget> AnyWidget for T {
     fn any_floats(&self) -> bool {
         self.floats()
     }
+
+    fn any_invalidate_layout(&mut self) {
+        self.invalidate_layout()
+    }
+
+    fn any_negotiate<'bp>(
+        &mut self,
+        children: LayoutChildren<'_, '_, 'bp>,
+        constraints: Constraints,
+        id: WidgetId,
+        ctx: &mut LayoutCtx<'_, '_, 'bp>,
+    ) -> Size {
+        self.negotiate(children, constraints, id, ctx)
+    }
 }
 
 impl Debug for dyn AnyWidget {
@@ -329,6 +362,26 @@ pub trait Widget {
     fn floats(&self) -> bool {
         false
     }
+
+    /// Discard any cached layout.
+    /// This is called on every widget above a change in the tree.
+    fn invalidate_layout(&mut self) {}
+
+    /// The size the widget would have within the given constraints,
+    /// without performing a layout.
+    /// A parent can use this to decide what to offer the widget,
+    /// e.g a stack offers its remaining space only if the widget would overflow it.
+    ///
+    /// By default a widget takes up all the space it's offered.
+    fn negotiate<'bp>(
+        &mut self,
+        _children: LayoutChildren<'_, '_, 'bp>,
+        constraints: Constraints,
+        _id: WidgetId,
+        _ctx: &mut LayoutCtx<'_, '_, 'bp>,
+    ) -> Size {
+        constraints.max_size()
+    }
 }
 
 impl Debug for dyn Widget {
@@ -340,7 +393,22 @@ impl Debug for dyn Widget {
 pub trait WidgetRenderer {
     fn draw_glyph(&mut self, c: char, local_pos: Pos);
 
+    /// Draw a cluster of characters in a single cell,
+    /// e.g a character followed by combining marks.
+    /// Surfaces that hold a single character per cell draw the first character.
+    fn draw_cluster(&mut self, cluster: &str, local_pos: Pos) {
+        if let Some(c) = cluster.chars().next() {
+            self.draw_glyph(c, local_pos);
+        }
+    }
+
     fn set_attributes(&mut self, attribs: &dyn CellAttributes, local_pos: Pos);
 
     fn size(&self) -> Size;
+
+    /// Read a cell that was previously painted to the surface.
+    /// Surfaces that can't be read return `None`.
+    fn read_cell(&self, _local_pos: Pos) -> Option<SurfaceCell> {
+        None
+    }
 }
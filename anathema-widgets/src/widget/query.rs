@@ -8,6 +8,8 @@ use anathema_store::tree::{apply_visitor, Node, TreeValues};
 use crate::nodes::element::Element;
 use crate::{AttributeStorage, Attributes, WidgetId, WidgetKind};
 
+const TABINDEX: &str = "tabindex";
+
 // -----------------------------------------------------------------------------
 //   - Elements -
 // -----------------------------------------------------------------------------
@@ -54,6 +56,96 @@ impl<'tree, 'bp> Elements<'tree, 'bp> {
             elements: self,
         }
     }
+
+    pub fn by_class<'a>(&mut self, class: &'a str) -> Query<'_, 'tree, 'bp, Kind<'a>> {
+        Query {
+            filter: Kind::ByClass(class),
+            elements: self,
+        }
+    }
+
+    /// Find the ids of all the elements with the given class
+    pub fn find_by_class(&mut self, class: &str) -> Vec<WidgetId> {
+        find_by_class(self.nodes, self.widgets, self.attributes, class)
+    }
+
+    /// The element after `current` in tab order, see [`next_focus`]
+    pub fn next_focus(&mut self, current: Option<WidgetId>) -> Option<WidgetId> {
+        next_focus(self.nodes, self.widgets, self.attributes, current)
+    }
+}
+
+/// Find the ids of all the elements, starting from `nodes`, that has
+/// the given class in their `class` attribute.
+pub fn find_by_class<'bp>(
+    nodes: &[Node],
+    values: &mut TreeValues<WidgetKind<'bp>>,
+    attribute_storage: &mut AttributeStorage<'bp>,
+    class: &str,
+) -> Vec<WidgetId> {
+    let mut ids = vec![];
+    let mut run = QueryRun {
+        filter: Kind::ByClass(class),
+        f: |el: &mut Element<'_>, _: &mut Attributes<'_>| ids.push(el.id()),
+        continuous: true,
+        attributes: attribute_storage,
+        ancestors: vec![],
+        last: None,
+        changed: vec![],
+    };
+
+    let _ = apply_visitor(nodes, values, &mut run);
+    ids
+}
+
+/// Find the next focusable element after `current`, starting from `nodes`.
+///
+/// An element is focusable if it has a `tabindex` of zero or more.
+/// Elements are ordered by their `tabindex`, and elements with the
+/// same `tabindex` are ordered by their position in the tree.
+///
+/// If there is no `current` element, or it's the last one, the
+/// first focusable element is returned.
+pub fn next_focus<'bp>(
+    nodes: &[Node],
+    values: &mut TreeValues<WidgetKind<'bp>>,
+    attribute_storage: &mut AttributeStorage<'bp>,
+    current: Option<WidgetId>,
+) -> Option<WidgetId> {
+    let mut focusable = TabOrder {
+        attributes: attribute_storage,
+        elements: vec![],
+    };
+    let _ = apply_visitor(nodes, values, &mut focusable);
+
+    let mut elements = focusable.elements;
+    // Stable sort, so tree order is kept for equal indices
+    elements.sort_by_key(|(index, _)| *index);
+
+    let next = current
+        .and_then(|current| elements.iter().position(|(_, id)| *id == current))
+        .map(|index| (index + 1) % elements.len())
+        .unwrap_or(0);
+
+    elements.get(next).map(|(_, id)| *id)
+}
+
+struct TabOrder<'a, 'bp> {
+    attributes: &'a AttributeStorage<'bp>,
+    elements: Vec<(i64, WidgetId)>,
+}
+
+impl<'bp> NodeVisitor<WidgetKind<'bp>> for TabOrder<'_, 'bp> {
+    fn visit(&mut self, value: &mut WidgetKind<'bp>, _path: &[u16], widget_id: WidgetId) -> ControlFlow<bool> {
+        if let WidgetKind::Element(_) = value {
+            match self.attributes.get(widget_id).get_int(TABINDEX) {
+                Some(index) if index >= 0 => self.elements.push((index, widget_id)),
+                _ => {}
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -94,15 +186,30 @@ where
         self.by_filter(Kind::ByAttribute(key, value.into()))
     }
 
+    pub fn by_class<'a>(self, class: &'a str) -> Query<'el, 'tree, 'bp, FilterChain<F, Kind<'a>>> {
+        self.by_filter(Kind::ByClass(class))
+    }
+
     fn query(self, f: impl FnMut(&mut Element<'_>, &mut Attributes<'_>), continuous: bool) {
         let mut run = QueryRun {
             filter: self.filter,
             f,
             continuous,
             attributes: self.elements.attributes,
+            ancestors: vec![],
+            last: None,
+            changed: vec![],
         };
 
-        apply_visitor(self.elements.nodes, self.elements.widgets, &mut run);
+        let _ = apply_visitor(self.elements.nodes, self.elements.widgets, &mut run);
+
+        // The attributes of the matching elements could have changed,
+        // so the layout of their parents can no longer be reused
+        for id in run.changed {
+            if let Some((_, WidgetKind::Element(el))) = self.elements.widgets.get_mut(id) {
+                el.invalidate_layout();
+            }
+        }
     }
 
     pub fn each<T>(self, f: T)
@@ -123,6 +230,7 @@ where
 pub enum Kind<'a> {
     ByTag(&'a str),
     ByAttribute(&'a str, CommonVal<'a>),
+    ByClass(&'a str),
     AtPosition(Pos),
 }
 
@@ -141,6 +249,7 @@ impl<'bp, 'a> Filter<'bp> for Kind<'a> {
                     })
                     .unwrap_or(false)
             }
+            Kind::ByClass(class) => attributes.get(el.container.id).has_class(class),
             Kind::AtPosition(pos) => {
                 let region = Region::from((el.container.pos, el.container.size));
                 region.contains(*pos)
@@ -195,6 +304,16 @@ where
             b: Kind::ByAttribute(key, value.into()),
         }
     }
+
+    pub fn by_class(self, class: &'a str) -> FilterChain<Self, Kind<'a>>
+    where
+        Self: Sized,
+    {
+        FilterChain {
+            a: self,
+            b: Kind::ByClass(class),
+        }
+    }
 }
 
 impl<'bp, A: Filter<'bp>, B: Filter<'bp>> Filter<'bp> for FilterChain<A, B> {
@@ -214,6 +333,11 @@ pub struct QueryRun<'bp, 'tag, T: Filter<'bp>, F> {
     f: F,
     continuous: bool,
     attributes: &'tag mut AttributeStorage<'bp>,
+    // The nodes above the node being visited
+    ancestors: Vec<WidgetId>,
+    last: Option<WidgetId>,
+    // The ancestors of the elements passed to `f`
+    changed: Vec<WidgetId>,
 }
 
 // impl QueryRun {
@@ -249,11 +373,13 @@ impl<'bp, 'tag, T: Filter<'bp>, F> NodeVisitor<WidgetKind<'bp>> for QueryRun<'bp
 where
     F: FnMut(&mut Element<'bp>, &mut Attributes<'_>),
 {
-    fn visit(&mut self, value: &mut WidgetKind<'bp>, _path: &[u16], _widget_id: WidgetId) -> ControlFlow<bool> {
+    fn visit(&mut self, value: &mut WidgetKind<'bp>, _path: &[u16], widget_id: WidgetId) -> ControlFlow<bool> {
+        self.last = Some(widget_id);
         if let WidgetKind::Element(el) = value {
             if self.filter.filter(el, self.attributes) {
                 let attributes = self.attributes.get_mut(el.id());
                 (self.f)(el, attributes);
+                self.changed.extend_from_slice(&self.ancestors);
                 if !self.continuous {
                     return ControlFlow::Break(false);
                 }
@@ -264,4 +390,12 @@ where
 
         ControlFlow::Continue(())
     }
+
+    fn push(&mut self) {
+        self.ancestors.extend(self.last.take());
+    }
+
+    fn pop(&mut self) {
+        self.ancestors.pop();
+    }
 }
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+
+use super::{WidgetId, WidgetTree};
+use crate::nodes::invalidate_layout;
+use crate::WidgetKind;
+
+/// Widgets waiting to be repainted.
+///
+/// Any number of requests for the same widget within a frame
+/// results in a single repaint once the queue is flushed.
+#[derive(Debug, Default)]
+pub struct RepaintQueue {
+    widgets: HashSet<WidgetId>,
+}
+
+impl RepaintQueue {
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Request a repaint of a widget.
+    /// Returns `false` if the widget is already waiting to be repainted.
+    pub fn request(&mut self, widget_id: WidgetId) -> bool {
+        self.widgets.insert(widget_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.widgets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.widgets.is_empty()
+    }
+
+    /// Invalidate the layout of every requested widget and the elements above it,
+    /// so the next frame lays out and paints them again.
+    /// Widgets that have since been removed from the tree are ignored.
+    ///
+    /// Returns the number of repaints.
+    pub fn flush(&mut self, tree: &mut WidgetTree<'_>) -> usize {
+        let mut count = 0;
+
+        for widget_id in self.widgets.drain() {
+            let Some(path) = tree.try_path(widget_id) else { continue };
+            if let Some(WidgetKind::Element(el)) = tree.get_mut_by_id(widget_id) {
+                el.invalidate_layout();
+            }
+            invalidate_layout(&path, tree);
+            count += 1;
+        }
+
+        count
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nodes::controlflow::ControlFlow;
+
+    #[test]
+    fn coalesce_repaints() {
+        let mut tree = WidgetTree::empty();
+        let widget_id = tree
+            .insert(&[])
+            .commit_child(WidgetKind::ControlFlow(ControlFlow))
+            .unwrap();
+        let removed_id = tree
+            .insert(&[])
+            .commit_child(WidgetKind::ControlFlow(ControlFlow))
+            .unwrap();
+
+        let mut queue = RepaintQueue::empty();
+        assert!(queue.request(widget_id));
+        assert!(!queue.request(widget_id));
+        assert!(!queue.request(widget_id));
+        assert!(queue.request(removed_id));
+        assert_eq!(queue.len(), 2);
+
+        // The removed widget is not repainted
+        tree.remove(&[1]);
+        assert_eq!(queue.flush(&mut tree), 1);
+        assert!(queue.is_empty());
+        assert_eq!(queue.flush(&mut tree), 0);
+    }
+}
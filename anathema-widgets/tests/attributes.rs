@@ -1,3 +1,5 @@
+use anathema_state::Map;
+use anathema_widgets::Elements;
 use run::TestCase;
 mod run;
 
@@ -34,3 +36,106 @@ test
         })
         .expect_frame(frame);
 }
+
+#[test]
+fn find_by_class() {
+    let template = "
+test
+    test [class: 'a b']
+    test [class: 'b']
+    test [class: 'c']
+    test
+    test [class: 'c b']
+        ";
+
+    TestCase::setup(template)
+        .build(())
+        .with_query(0, |_state, mut elements| {
+            let ids = elements.find_by_class("b");
+            assert_eq!(ids.len(), 3);
+
+            let mut count = 0;
+            elements.by_class("b").each(|_el, attribs| {
+                assert!(attribs.has_class("b"));
+                count += 1;
+            });
+            assert_eq!(count, 3);
+
+            assert_eq!(elements.find_by_class("c").len(), 2);
+            assert!(elements.find_by_class("d").is_empty());
+        });
+}
+
+#[test]
+fn tab_order() {
+    let template = "
+test
+    test [tabindex: 2]
+    test [tabindex: 1, class: 'first']
+    test
+    test [tabindex: 1, class: 'second']
+    test [tabindex: -1]
+        ";
+
+    TestCase::setup(template)
+        .build(())
+        .with_query(0, |_state, mut elements| {
+            let first = elements.find_by_class("first")[0];
+            let second = elements.find_by_class("second")[0];
+            let mut last = None;
+            elements.by_attribute("tabindex", 2).first(|el, _| last = Some(el.id()));
+            let last = last.unwrap();
+
+            assert_eq!(elements.next_focus(None), Some(first));
+            assert_eq!(elements.next_focus(Some(first)), Some(second));
+            assert_eq!(elements.next_focus(Some(second)), Some(last));
+            assert_eq!(elements.next_focus(Some(last)), Some(first));
+        });
+}
+
+fn assert_attributes<S>(
+    expected: &'static [(&'static str, Option<i64>)],
+) -> impl for<'a, 'b> FnOnce(&mut S, Elements<'a, 'b>) {
+    move |_, mut elements| {
+        let mut found = false;
+        elements.by_tag("test").first(|_el, attribs| {
+            for (key, value) in expected {
+                assert_eq!(attribs.get::<i64>(key), *value, "attribute {key}");
+            }
+            found = true;
+        });
+        assert!(found);
+    }
+}
+
+#[test]
+fn spread_state_map() {
+    let template = "
+test
+    test [a: 1, ...attrs]
+        ";
+
+    let mut attrs = Map::<i64>::empty();
+    attrs.insert("a", 5);
+    attrs.insert("b", 2);
+    let mut state = Map::<Map<i64>>::empty();
+    state.insert("attrs", attrs);
+
+    TestCase::setup(template)
+        .build(state)
+        // The attributes of the template are not overridden
+        .with_query(0, assert_attributes(&[("a", Some(1)), ("b", Some(2)), ("c", None)]))
+        .with_state(0, |state| {
+            let mut state = state.to_mut();
+            let attrs = state.get_mut("attrs").unwrap();
+            attrs.insert("c", 3);
+            attrs.remove("b");
+        })
+        .with_query(0, assert_attributes(&[("a", Some(1)), ("b", None), ("c", Some(3))]))
+        .with_state(0, |state| {
+            let mut state = state.to_mut();
+            let attrs = state.get_mut("attrs").unwrap();
+            *attrs.to_mut().get_mut("c").unwrap().to_mut() = 4;
+        })
+        .with_query(0, assert_attributes(&[("c", Some(4))]));
+}
@@ -1,4 +1,5 @@
 use anathema_state::{CommonVal, Path, PendingValue, State, Subscriber, Value, ValueRef};
+use anathema_widgets::layout::Capabilities;
 use run::TestCase;
 mod run;
 
@@ -62,3 +63,31 @@ else
         "#,
     );
 }
+
+#[test]
+fn capability_fallback() {
+    let state = TestState { is_true: false.into() };
+    let capabilities = Capabilities {
+        unicode: false,
+        ..Default::default()
+    };
+    TestCase::setup_with_capabilities(
+        r#"
+if unicode
+    test "█"
+else
+    test "+"
+    "#,
+        capabilities,
+    )
+    .build(state)
+    .expect_frame(
+        r#"
+<control flow>
+    <if cond = false>
+        test Str("█")
+    <else>
+        test Str("+")
+        "#,
+    );
+}
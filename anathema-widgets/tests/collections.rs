@@ -127,3 +127,49 @@ for val in list
         })
         .expect_frame(f2);
 }
+
+#[test]
+fn collection_empty_body() {
+    let template = r#"
+for val in list
+    test val
+else
+    test 0
+    "#;
+
+    let f1 = r#"
+<for>
+    <iter binding = val, index = 0>
+        test Int(1)
+    "#;
+
+    let f2 = r#"
+<for>
+    test Int(0)
+    "#;
+
+    let f3 = r#"
+<for>
+    <iter binding = val, index = 0>
+        test Int(2)
+    "#;
+
+    let mut state = Map::<List<_>>::empty();
+    let list = Value::<List<_>>::from_iter([1]);
+    state.insert("list", list);
+    TestCase::setup(template)
+        .build(state)
+        .expect_frame(f1)
+        .with_state(0, |state| {
+            if let Some(list) = state.to_mut().get_mut("list") {
+                let _ = list.remove(0);
+            }
+        })
+        .expect_frame(f2)
+        .with_state(0, |state| {
+            if let Some(list) = state.to_mut().get_mut("list") {
+                list.push_back(2);
+            }
+        })
+        .expect_frame(f3);
+}
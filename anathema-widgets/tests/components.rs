@@ -0,0 +1,85 @@
+use anathema_state::{CommonVal, Path, PendingValue, State, Subscriber, Value, ValueRef};
+use run::TestCase;
+mod run;
+
+struct CompState {
+    value: Value<usize>,
+}
+
+impl State for CompState {
+    fn to_common(&self) -> Option<CommonVal<'_>> {
+        None
+    }
+
+    fn state_get(&self, path: Path<'_>, sub: Subscriber) -> Option<ValueRef> {
+        match path {
+            Path::Key("value") => Some(self.value.value_ref(sub)),
+            _ => None,
+        }
+    }
+
+    fn state_lookup(&self, path: Path<'_>) -> Option<PendingValue> {
+        match path {
+            Path::Key("value") => Some(self.value.to_pending()),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn rebind_component_state() {
+    let root = "
+    test
+        @comp
+    ";
+    let comp = "test value";
+
+    TestCase::setup_with_components(root, &[("comp", comp)])
+        .build(())
+        .expect_frame(
+            "
+test
+    <component>
+        test",
+        )
+        .rebind_component(0, || CompState { value: 1.into() })
+        .expect_frame(
+            "
+test
+    <component>
+        test Int(1)",
+        )
+        .rebind_component(0, || CompState { value: 2.into() })
+        .expect_frame(
+            "
+test
+    <component>
+        test Int(2)",
+        );
+}
+
+#[test]
+fn rebind_every_component_instance() {
+    let root = "
+    test
+        @comp
+        @comp
+    ";
+    let comp = "test value";
+
+    let mut value = 0;
+    TestCase::setup_with_components(root, &[("comp", comp)])
+        .build(())
+        .rebind_component(0, || {
+            value += 1;
+            CompState { value: value.into() }
+        })
+        .expect_frame(
+            "
+test
+    <component>
+        test Int(1)
+    <component>
+        test Int(2)",
+        );
+}
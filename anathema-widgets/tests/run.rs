@@ -4,13 +4,15 @@ use std::ops::ControlFlow;
 use anathema_geometry::{Pos, Size};
 use anathema_state::{drain_changes, drain_futures, Changes, FutureValues, State, StateId, States};
 use anathema_templates::blueprints::Blueprint;
-use anathema_templates::{Document, Globals};
+use anathema_templates::{Document, Globals, ToSourceKind};
 use anathema_widgets::components::ComponentRegistry;
 use anathema_widgets::layout::text::StringStorage;
-use anathema_widgets::layout::{layout_widget, position_widget, Constraints, LayoutCtx, LayoutFilter, Viewport};
+use anathema_widgets::layout::{
+    layout_widget, position_widget, Capabilities, Constraints, LayoutCtx, LayoutFilter, Viewport,
+};
 use anathema_widgets::{
-    eval_blueprint, try_resolve_future_values, update_tree, AttributeStorage, Components, Elements, EvalContext,
-    Factory, FloatingWidgets, LayoutChildren, Scope, Stringify, Widget, WidgetTree,
+    eval_blueprint, rebind_component_state, try_resolve_future_values, update_tree, AttributeStorage, Components,
+    Elements, EvalContext, Factory, FloatingWidgets, LayoutChildren, Scope, Stringify, Widget, WidgetTree,
 };
 
 #[macro_export]
@@ -192,19 +194,36 @@ where
         self
     }
 
-    /// Perform a state changing operation.
-    /// This will also apply future values
+    /// Replace the state of every mounted instance of a component and rebuild their bodies.
     #[allow(dead_code)]
-    pub fn with_state<F>(&mut self, state_id: impl Into<StateId>, f: F) -> &mut Self
-    where
-        F: FnOnce(&mut S),
-    {
-        let state_id = state_id.into();
-        let state = self.states.get_mut(state_id).unwrap();
-        f(state.to_any_mut().downcast_mut().unwrap());
-        self.apply_futures();
-        self.update_tree();
+    pub fn rebind_component<C: 'static + State>(
+        &mut self,
+        component_id: usize,
+        mut state: impl FnMut() -> C,
+    ) -> &mut Self {
+        let mut scope = Scope::with_capacity(10);
+        scope.insert_state(StateId::ZERO);
+
+        let previous = rebind_component_state(
+            self.globals,
+            &self.factory,
+            &mut scope,
+            &mut self.states,
+            &mut self.component_registry,
+            component_id.into(),
+            || Box::new(state()),
+            &mut self.tree,
+            &mut self.attribute_storage,
+            &mut self.floating_widgets,
+            &mut self.components,
+        );
+        assert!(!previous.is_empty(), "the component should be mounted");
 
+        self.layout();
+        self
+    }
+
+    fn layout(&mut self) {
         let mut filter = LayoutFilter::new(false, &self.attribute_storage);
         self.tree.for_each(&mut filter).first(&mut |widget, children, values| {
             let mut layout_ctx = LayoutCtx::new(self.text.new_session(), &self.attribute_storage, &self.viewport);
@@ -217,6 +236,21 @@ where
                 false,
             );
         });
+    }
+
+    /// Perform a state changing operation.
+    /// This will also apply future values
+    #[allow(dead_code)]
+    pub fn with_state<F>(&mut self, state_id: impl Into<StateId>, f: F) -> &mut Self
+    where
+        F: FnOnce(&mut S),
+    {
+        let state_id = state_id.into();
+        let state = self.states.get_mut(state_id).unwrap();
+        f(state.to_any_mut().downcast_mut().unwrap());
+        self.apply_futures();
+        self.update_tree();
+        self.layout();
 
         // anathema_state::debug::Debug
         //     .heading()
@@ -235,17 +269,53 @@ where
 pub struct TestCase {
     blueprint: Blueprint,
     globals: Globals,
+    components: Vec<usize>,
 }
 
 impl TestCase {
+    #[allow(dead_code)]
     pub fn setup(src: &str) -> Self {
-        let (blueprint, globals) = Document::new(src).compile().unwrap();
-        Self { blueprint, globals }
+        Self::setup_with_components(src, &[])
+    }
+
+    /// Setup a test case with a set of named components.
+    /// Every component is registered as a prototype with an empty state,
+    /// so a component can be used more than once.
+    #[allow(dead_code)]
+    pub fn setup_with_components(src: &str, components: &[(&str, &str)]) -> Self {
+        let mut doc = Document::new(src);
+        let components = components
+            .iter()
+            .map(|(name, src)| doc.add_component(*name, src.to_template()).unwrap())
+            .collect();
+        let (blueprint, globals) = doc.compile().unwrap();
+        Self {
+            blueprint,
+            globals,
+            components,
+        }
+    }
+
+    /// Setup a test case where the templates are compiled
+    /// with the given terminal capabilities.
+    #[allow(dead_code)]
+    pub fn setup_with_capabilities(src: &str, capabilities: Capabilities) -> Self {
+        let mut doc = Document::new(src);
+        capabilities.set_globals(&mut doc);
+        let (blueprint, globals) = doc.compile().unwrap();
+        Self {
+            blueprint,
+            globals,
+            components: vec![],
+        }
     }
 
     pub fn build<S: 'static + State>(&self, state: S) -> TestCaseRunner<'_, S> {
         let tree = WidgetTree::empty();
-        let components = ComponentRegistry::new();
+        let mut components = ComponentRegistry::new();
+        for id in &self.components {
+            components.add_prototype((*id).into(), || (), || ());
+        }
         let mut states = States::new();
         states.insert(Box::new(state));
 